@@ -1,5 +1,9 @@
 use std::path::{Path, PathBuf};
 
+use goblin::elf::{program_header, sym, Sym};
+use itertools::Itertools;
+use regex::Regex;
+
 #[derive(thiserror::Error, Debug)]
 pub enum AnalysysError {
     #[error("failed to open binary for analysis: {0:?}")]
@@ -21,6 +25,68 @@ pub struct ElfInfo {
 pub struct Function {
     pub name: String,
     pub offset: usize,
+    pub size: usize,
+}
+
+impl ElfInfo {
+    /// function containing given offset, none if it is outside of every known function
+    pub fn function_at(&self, offset: usize) -> Option<&Function> {
+        self.functions
+            .iter()
+            .find(|function| (function.offset..function.offset + function.size).contains(&offset))
+    }
+
+    /// keep only functions accepted by filter
+    pub fn with_symbol_filter(mut self, filter: &SymbolFilter) -> Self {
+        self.functions
+            .retain(|function| filter.accepts(&function.name));
+        self
+    }
+}
+
+/// Selects functions by name with regexes, function is accepted if it
+/// matches one of include patterns (or there are none) and none of
+/// exclude ones.
+#[derive(Default)]
+pub struct SymbolFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl SymbolFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self, regex::Error> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| Regex::new(pattern))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(SymbolFilter {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    pub fn accepts(&self, name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|regex| regex.is_match(name)))
+            && !self.exclude.iter().any(|regex| regex.is_match(name))
+    }
+}
+
+const PAGE_SIZE: u64 = 0x1000;
+
+/// Order of preference among symbols naming the same function: global
+/// definitions go before weak and local ones, names with fewer leading
+/// underscores (`write` rather than `__libc_write`) go first.
+fn alias_rank(symbol: &Sym, name: &str) -> (u8, usize) {
+    let binding = match symbol.st_bind() {
+        sym::STB_GLOBAL => 0,
+        sym::STB_WEAK => 1,
+        _ => 2,
+    };
+
+    (binding, name.len() - name.trim_start_matches('_').len())
 }
 
 pub fn analyze_binary<P: AsRef<Path>>(path: P) -> Result<ElfInfo, AnalysysError> {
@@ -42,10 +108,22 @@ pub fn analyze_binary<P: AsRef<Path>>(path: P) -> Result<ElfInfo, AnalysysError>
         }
     };
 
-    let functions = elf
+    // breakpoints are placed relative to first mapping of executable,
+    // where its lowest segment is loaded (address 0 unless it is ET_EXEC)
+    let image_base = elf
+        .program_headers
+        .iter()
+        .filter(|header| header.p_type == program_header::PT_LOAD)
+        .map(|header| header.p_vaddr & !(PAGE_SIZE - 1))
+        .min()
+        .unwrap_or(0);
+
+    let mut candidates = elf
         .syms
         .iter()
         .filter_map(|symbol| {
+            // ifunc resolvers have their own type and are left out, they run
+            // while relocations are processed
             if !symbol.is_function() {
                 return None;
             }
@@ -54,11 +132,38 @@ pub fn analyze_binary<P: AsRef<Path>>(path: P) -> Result<ElfInfo, AnalysysError>
                 return None;
             }
 
+            // undefined functions of ET_EXEC may have address of their PLT stub
+            let section = elf.section_headers.get(symbol.st_shndx)?;
+            if !section.is_executable() {
+                return None;
+            }
+
+            let section_name = elf.shdr_strtab.get_at(section.sh_name).unwrap_or_default();
+            if section_name.starts_with(".plt") {
+                return None;
+            }
+
             let name = elf.strtab.get_at(symbol.st_name)?.to_string();
-            let offset = symbol.st_value as usize;
+            let offset = symbol.st_value.checked_sub(image_base)? as usize;
+            let size = symbol.st_size as usize;
 
-            Some(Function { name, offset })
+            Some((alias_rank(&symbol, &name), Function { name, offset, size }))
         })
+        .collect_vec();
+
+    // aliases (weak and internal names of one function) share address,
+    // function gets one breakpoint under its canonical name
+    candidates.sort_by(|(rank_a, a), (rank_b, b)| {
+        a.offset
+            .cmp(&b.offset)
+            .then(rank_a.cmp(rank_b))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    candidates.dedup_by_key(|(_rank, function)| function.offset);
+
+    let functions = candidates
+        .into_iter()
+        .map(|(_rank, function)| function)
         .collect();
 
     Ok(ElfInfo {
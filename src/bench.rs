@@ -0,0 +1,158 @@
+use std::{
+    path::Path,
+    process,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    analysys,
+    configuration::{EvaluatorKind, FuzzConfig, InputOptions, PassStyle},
+    execution::{self, RunTrace},
+    fuzzing::Evaluator,
+    grammar::generation::Generator,
+    sample::{Sample, TreeNode, TreeNodeItem},
+};
+
+type DynEvaluator = Box<dyn Evaluator<Item = Sample, EvalResult = RunTrace>>;
+
+/// Sample executed by benchmark: given file, first seed (by name) or
+/// sample generated from grammar.
+fn load_sample(config: &FuzzConfig, sample: Option<&Path>) -> Result<Sample, anyhow::Error> {
+    let path = match (sample, &config.input) {
+        (Some(path), _) => path.to_path_buf(),
+        (None, InputOptions::Seeds { seeds }) => {
+            let mut entries = std::fs::read_dir(seeds)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            entries.sort();
+
+            entries
+                .into_iter()
+                .find(|path| path.is_file())
+                .ok_or_else(|| anyhow::anyhow!("seeds directory {seeds} is empty"))?
+        }
+        (None, InputOptions::Grammar { grammar }) => {
+            let grammar = crate::grammar::parse_grammar(&std::fs::read_to_string(grammar)?)?;
+
+            return Ok(Generator::new(grammar, 30).generate());
+        }
+    };
+
+    let content = std::fs::read(&path)?;
+    let tree: TreeNode = TreeNodeItem::Data(content, None).into();
+
+    Ok(tree.fold_into_sample())
+}
+
+pub fn build_evaluator(
+    config: &FuzzConfig,
+    evaluator: EvaluatorKind,
+    pass_style: PassStyle,
+) -> Result<DynEvaluator, anyhow::Error> {
+    let timeout = config.binary.timeout.map(Duration::from_millis);
+    let credentials = config.binary.credentials();
+    let crash_signals = config.binary.crash_signals();
+
+    Ok(match evaluator {
+        #[cfg(not(target_os = "linux"))]
+        EvaluatorKind::Trace => anyhow::bail!("tracing is only supported on Linux"),
+        #[cfg(target_os = "linux")]
+        EvaluatorKind::Trace => Box::new(
+            execution::TraceEvaluator::new(
+                analysys::analyze_binary(&config.binary.path)?
+                    .with_symbol_filter(&config.binary.symbol_filter()),
+                pass_style,
+            )
+            .with_timeout(timeout)
+            .with_crash_signals(crash_signals)
+            .with_wrapper(config.binary.wrapper.clone())
+            .with_stdin_feed(config.binary.stdin_feed())
+            .with_credentials(credentials),
+        ),
+        EvaluatorKind::ExitCode => Box::new(
+            execution::UntracedEvaluator::new(config.binary.path.clone(), pass_style)
+                .with_timeout(timeout)
+                .with_crash_signals(crash_signals)
+                .with_wrapper(config.binary.wrapper.clone())
+                .with_stdin_feed(config.binary.stdin_feed())
+                .with_credentials(credentials),
+        ),
+    })
+}
+
+#[derive(Default)]
+struct Measurement {
+    executions: usize,
+    timeouts: usize,
+    failures: usize,
+    elapsed: Duration,
+}
+
+/// run sample over and over for given duration
+fn measure(evaluator: &mut DynEvaluator, sample: &Sample, duration: Duration) -> Measurement {
+    let mut measurement = Measurement::default();
+    let started = Instant::now();
+
+    while started.elapsed() < duration {
+        match evaluator.score(sample.clone()) {
+            Ok(_) => measurement.executions += 1,
+            Err(e) if execution::is_timeout(&e) => measurement.timeouts += 1,
+            Err(_) => measurement.failures += 1,
+        }
+    }
+
+    measurement.elapsed = started.elapsed();
+    measurement
+}
+
+/// Execute the same sample for every combination of evaluator and pass
+/// style and print raw execution speed of each.
+pub fn run_bench(config: &FuzzConfig, sample: Option<&Path>, seconds: f64) {
+    let sample = match load_sample(config, sample) {
+        Ok(sample) => sample,
+        Err(e) => {
+            eprintln!("failed to load sample for benchmark: {e}");
+            process::exit(exitcode::NOINPUT)
+        }
+    };
+
+    execution::disable_aslr();
+
+    let duration = Duration::from_secs_f64(seconds);
+
+    println!(
+        "running {} on sample of {} bytes for {seconds}s per combination",
+        config.binary.path,
+        sample.get_folded().len()
+    );
+
+    println!(
+        "{:<10} {:<10} {:>10} {:>12} {:>9} {:>9}",
+        "evaluator", "pass style", "executions", "speed", "timeouts", "failures"
+    );
+
+    for evaluator_kind in [EvaluatorKind::Trace, EvaluatorKind::ExitCode] {
+        for pass_style in [PassStyle::Stdin, PassStyle::File] {
+            let name = evaluator_kind.name();
+            let style = pass_style.name();
+
+            let mut evaluator = match build_evaluator(config, evaluator_kind, pass_style) {
+                Ok(evaluator) => evaluator,
+                Err(e) => {
+                    println!("{name:<10} {style:<10} skipped: {e}");
+                    continue;
+                }
+            };
+
+            let measurement = measure(&mut evaluator, &sample, duration);
+
+            let speed = measurement.executions as f64 / measurement.elapsed.as_secs_f64();
+
+            println!(
+                "{name:<10} {style:<10} {:>10} {:>10.1}/s {:>9} {:>9}",
+                measurement.executions, speed, measurement.timeouts, measurement.failures
+            );
+        }
+    }
+}
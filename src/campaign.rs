@@ -0,0 +1,217 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    configuration::{FuzzConfig, InputOptions},
+    execution::RunTrace,
+    log::log,
+    sample::{Sample, TreeNode},
+    sample_library::Library as LibT,
+    session,
+    state::{Library, MutatorStats, State},
+    storage::write_atomic,
+};
+
+/// Files campaign state is read from at start and written to while fuzzing.
+#[derive(Clone, Debug, Default)]
+pub struct StateFiles {
+    pub import: Option<PathBuf>,
+    pub export: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedEntry {
+    trace: RunTrace,
+    tree: TreeNode,
+    folded: Vec<u8>,
+    name: Option<String>,
+    exec_time: Duration,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedStats {
+    elapsed: Duration,
+    tested_samples: usize,
+    improvements: usize,
+    total_crashes: usize,
+    total_nonzero: usize,
+    total_working: usize,
+    duplicates: usize,
+    failed: usize,
+    spawn_failures: usize,
+    #[serde(default)]
+    input_failures: usize,
+    #[serde(default)]
+    input_cut_short: usize,
+    timeouts: usize,
+    empty_traces: usize,
+    exit_codes: HashSet<i32>,
+    mutators: HashMap<String, MutatorStats>,
+    covered_functions: HashSet<usize>,
+    covered_rules: HashSet<String>,
+}
+
+/// Everything needed to continue campaign elsewhere: library with names of
+/// entries, hit counts used for scheduling and stats.
+#[derive(Serialize, Deserialize)]
+struct CampaignState {
+    /// sha256 of target executable at the time of export
+    target_hash: Option<String>,
+    /// sha256 of grammar trees of entries were generated from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    grammar_hash: Option<String>,
+    entries: Vec<SavedEntry>,
+    hits: HashMap<usize, usize>,
+    stats: SavedStats,
+}
+
+/// sha256 of grammar file, none when fuzzing with seeds
+fn grammar_hash(config: &FuzzConfig) -> Option<String> {
+    match &config.input {
+        InputOptions::Grammar { grammar } => session::sha256_file(Path::new(grammar)).ok(),
+        InputOptions::Seeds { .. } => None,
+    }
+}
+
+/// Write library and stats as gzipped json.
+pub fn export_state(
+    path: &Path,
+    config: &FuzzConfig,
+    library: &Library,
+    state: &State,
+) -> Result<(), anyhow::Error> {
+    let campaign = CampaignState {
+        target_hash: session::sha256_file(Path::new(&config.binary.path)).ok(),
+        grammar_hash: grammar_hash(config),
+        entries: library
+            .iter()
+            .map(|(trace, entry, sample)| {
+                let (tree, folded) = sample.clone().strip();
+
+                SavedEntry {
+                    trace: trace.clone(),
+                    tree,
+                    folded,
+                    name: entry.unique_name.clone(),
+                    exec_time: entry.exec_time,
+                }
+            })
+            .collect(),
+        hits: library.hit_counts().clone(),
+        stats: SavedStats {
+            elapsed: state.start_time.elapsed(),
+            tested_samples: state.tested_samples,
+            improvements: state.improvements,
+            total_crashes: state.total_crashes,
+            total_nonzero: state.total_nonzero,
+            total_working: state.total_working,
+            duplicates: state.duplicates,
+            failed: state.failed,
+            spawn_failures: state.spawn_failures,
+            input_failures: state.input_failures,
+            input_cut_short: state.input_cut_short,
+            timeouts: state.timeouts,
+            empty_traces: state.empty_traces,
+            exit_codes: state.exit_codes_seen.keys().copied().collect(),
+            mutators: state
+                .mutators
+                .iter()
+                .map(|(name, stats)| (name.to_string(), stats.clone()))
+                .collect(),
+            covered_functions: state.covered_functions.clone(),
+            covered_rules: state.covered_rules.clone(),
+        },
+    };
+
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    serde_json::to_writer(&mut encoder, &campaign)?;
+    encoder.flush()?;
+
+    write_atomic(path, &encoder.finish()?)?;
+
+    Ok(())
+}
+
+/// Fill empty library and stats from state exported earlier, returns
+/// number of imported entries. `mutators` are names of mutators of
+/// current campaign, stats of unknown ones are dropped.
+pub fn import_state(
+    path: &Path,
+    config: &FuzzConfig,
+    library: &mut Library,
+    state: &mut State,
+    mutators: &[&'static str],
+) -> Result<usize, anyhow::Error> {
+    let mut content = vec![];
+    GzDecoder::new(std::fs::File::open(path)?)
+        .read_to_end(&mut content)
+        .context("decompressing campaign state")?;
+
+    let campaign: CampaignState =
+        serde_json::from_slice(&content).context("parsing campaign state")?;
+
+    // trees may reference rules and tokens that are gone from edited grammar
+    if campaign.grammar_hash.is_some() && campaign.grammar_hash != grammar_hash(config) {
+        anyhow::bail!("grammar changed since campaign state was exported, its samples can not be mutated with current one");
+    }
+
+    let target_hash = session::sha256_file(Path::new(&config.binary.path)).ok();
+
+    if campaign.target_hash.is_some() && campaign.target_hash != target_hash {
+        log!(warning: "target changed since campaign state was exported, imported coverage may be stale");
+    }
+
+    let imported = campaign.entries.len();
+
+    for entry in campaign.entries {
+        let sample = Sample::recombine(entry.tree, entry.folded);
+
+        library.upsert(entry.trace.clone(), sample, entry.exec_time);
+
+        if let Some(name) = entry.name {
+            library.add_name(&entry.trace, name);
+        }
+    }
+
+    library.set_hit_counts(campaign.hits);
+
+    let stats = campaign.stats;
+    let now = Instant::now();
+
+    state.start_time = now.checked_sub(stats.elapsed).unwrap_or(now);
+    state.tested_samples = stats.tested_samples;
+    state.improvements = stats.improvements;
+    state.total_crashes = stats.total_crashes;
+    state.total_nonzero = stats.total_nonzero;
+    state.total_working = stats.total_working;
+    state.duplicates = stats.duplicates;
+    state.failed = stats.failed;
+    state.spawn_failures = stats.spawn_failures;
+    state.input_failures = stats.input_failures;
+    state.input_cut_short = stats.input_cut_short;
+    state.timeouts = stats.timeouts;
+    state.empty_traces = stats.empty_traces;
+    state.exit_codes_seen = stats
+        .exit_codes
+        .into_iter()
+        .map(|code| (code, now))
+        .collect();
+    state.covered_functions = stats.covered_functions;
+    state.covered_rules = stats.covered_rules;
+
+    for (name, stats) in stats.mutators {
+        if let Some(name) = mutators.iter().find(|known| **known == name) {
+            state.mutators.insert(name, stats);
+        }
+    }
+
+    Ok(imported)
+}
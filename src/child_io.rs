@@ -0,0 +1,430 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    io::{ErrorKind, Read, Write},
+    process::{Child, ChildStdin},
+    sync::atomic::AtomicBool,
+    time::Duration,
+};
+#[cfg(unix)]
+use std::{
+    os::fd::{AsRawFd, RawFd},
+    sync::atomic::Ordering,
+    time::Instant,
+};
+
+/// milliseconds between checks of stop flag while streams are idle
+#[cfg(unix)]
+const POLL_INTERVAL: i32 = 10;
+
+/// bytes of response to one message kept for classifying it
+const RESPONSE_LIMIT: usize = 4096;
+
+#[cfg(unix)]
+trait OutputStream: Read + AsRawFd + Send {}
+
+#[cfg(unix)]
+impl<T: Read + AsRawFd + Send> OutputStream for T {}
+
+/// How input is written to stdin of target.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StdinFeed {
+    /// keep stdin open after input is written until target exits
+    pub keep_open: bool,
+    /// write input in pieces of given size, messages are split further
+    pub chunk: Option<usize>,
+    /// delay after every piece of input (chunk or message) but the last
+    pub delay: Duration,
+    /// next piece is written as soon as target writes to stdout, `delay` is the longest wait
+    pub wait_response: bool,
+    /// Read stdout written in response to every piece of input (whole
+    /// input is one message unless written in chunks) and classify it.
+    pub read_response: bool,
+}
+
+impl StdinFeed {
+    /// whether stdout of target is read rather than discarded
+    pub fn reads_stdout(&self) -> bool {
+        self.wait_response || self.read_response
+    }
+}
+
+/// What is left of streams once child exited.
+pub struct Pumped {
+    /// stdin kept open, it should be dropped once child exits
+    pub held_stdin: Option<ChildStdin>,
+    /// class of responses of target when they are read
+    pub response: Option<u64>,
+    /// bytes of input target closed stdin before reading
+    pub unsent: usize,
+}
+
+/// End of piece of input starting at `sent`: piece ends with its message
+/// and is no longer than chunk.
+fn piece_end(input: &[u8], messages: &[usize], chunk: Option<usize>, sent: usize) -> usize {
+    let message_end = messages
+        .iter()
+        .copied()
+        .find(|&start| start > sent)
+        .unwrap_or(input.len());
+
+    match chunk {
+        Some(chunk) => message_end.min(sent + chunk),
+        None => message_end,
+    }
+}
+
+/// Feeds input to child and drains its output. Every stream is serviced
+/// by single poll(2) loop, so neither big input nor chatty target blocks
+/// the other side and no thread per stream is needed.
+#[cfg(unix)]
+pub struct ChildIo<'a> {
+    input: &'a [u8],
+    /// offsets at which messages of input other than the first start
+    messages: &'a [usize],
+    /// bytes of input written so far
+    sent: usize,
+    /// bytes of input left when target closed stdin
+    unsent: usize,
+    stdin: Option<ChildStdin>,
+    outputs: Vec<Box<dyn OutputStream>>,
+    feed: StdinFeed,
+    /// next piece of input is not written before this moment
+    next_piece: Instant,
+    /// end of piece of input being written
+    piece_end: usize,
+    /// pieces started so far
+    pieces_started: usize,
+    /// stdin kept open after input was written
+    held: Option<ChildStdin>,
+    stdout: Option<RawFd>,
+    /// stdout read after each message, none if responses are not read
+    responses: Option<Vec<Vec<u8>>>,
+}
+
+#[cfg(unix)]
+fn set_nonblocking(fd: &impl AsRawFd) -> std::io::Result<()> {
+    let fd = fd.as_raw_fd();
+
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+    if flags < 0 || unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+impl<'a> ChildIo<'a> {
+    /// Take piped streams of child, streams that are not piped are ignored.
+    /// Every message of input starting at one of `messages` offsets is a
+    /// separate piece written after delay or response to previous one.
+    pub fn new(
+        input: &'a [u8],
+        messages: &'a [usize],
+        child: &mut Child,
+        feed: StdinFeed,
+    ) -> std::io::Result<Self> {
+        let mut outputs: Vec<Box<dyn OutputStream>> = vec![];
+        let mut stdout_fd = None;
+
+        if let Some(stdout) = child.stdout.take() {
+            set_nonblocking(&stdout)?;
+            stdout_fd = Some(stdout.as_raw_fd());
+            outputs.push(Box::new(stdout));
+        }
+
+        if let Some(stderr) = child.stderr.take() {
+            set_nonblocking(&stderr)?;
+            outputs.push(Box::new(stderr));
+        }
+
+        let stdin = child.stdin.take();
+        if let Some(stdin) = &stdin {
+            set_nonblocking(stdin)?;
+        }
+
+        let mut io = ChildIo {
+            input,
+            messages,
+            sent: 0,
+            unsent: 0,
+            stdin,
+            outputs,
+            feed,
+            next_piece: Instant::now(),
+            piece_end: 0,
+            pieces_started: 0,
+            held: None,
+            stdout: stdout_fd,
+            responses: feed.read_response.then(|| vec![vec![]]),
+        };
+
+        // nothing to write, close stdin so that target sees end of input
+        if io.input.is_empty() {
+            io.finish_input();
+        }
+
+        Ok(io)
+    }
+
+    /// Service streams until all of them are closed or `stop` is set (child
+    /// exited but processes it left behind may hold its output open).
+    pub fn pump(mut self, stop: &AtomicBool) -> std::io::Result<Pumped> {
+        while self.stdin.is_some() || !self.outputs.is_empty() {
+            if stop.load(Ordering::SeqCst) {
+                break;
+            }
+
+            // stdin is not polled until delay before next piece passes
+            let waiting = self.next_piece.saturating_duration_since(Instant::now());
+            let timeout = match (&self.stdin, waiting.is_zero()) {
+                (Some(_), false) => (waiting.as_millis() as i32).clamp(1, POLL_INTERVAL),
+                _ => POLL_INTERVAL,
+            };
+
+            let mut fds = self
+                .stdin
+                .iter()
+                .filter(|_| waiting.is_zero())
+                .map(|stdin| libc::pollfd {
+                    fd: stdin.as_raw_fd(),
+                    events: libc::POLLOUT,
+                    revents: 0,
+                })
+                .chain(self.outputs.iter().map(|output| libc::pollfd {
+                    fd: output.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                }))
+                .collect::<Vec<_>>();
+
+            let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+
+            if ready < 0 {
+                let error = std::io::Error::last_os_error();
+                if error.kind() == ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(error);
+            }
+
+            if ready > 0 {
+                self.write_input()?;
+                self.drain_outputs();
+            }
+        }
+
+        Ok(Pumped {
+            held_stdin: self.held,
+            response: self.responses.as_deref().map(response_class),
+            unsent: self.unsent,
+        })
+    }
+
+    /// Write as much of input (or of current piece) as pipe accepts, stdin
+    /// is closed or held once input is written.
+    fn write_input(&mut self) -> std::io::Result<()> {
+        if Instant::now() < self.next_piece {
+            return Ok(());
+        }
+
+        let Some(stdin) = &mut self.stdin else {
+            return Ok(());
+        };
+
+        if self.sent == self.piece_end {
+            self.piece_end = piece_end(self.input, self.messages, self.feed.chunk, self.sent);
+
+            // output that comes after this point answers next piece
+            if let Some(responses) = self.responses.as_mut().filter(|_| self.pieces_started > 0) {
+                responses.push(vec![]);
+            }
+            self.pieces_started += 1;
+        }
+
+        while self.sent < self.piece_end {
+            match stdin.write(&self.input[self.sent..self.piece_end]) {
+                Ok(written) => self.sent += written,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                // target may exit without reading whole input
+                Err(e) if e.kind() == ErrorKind::BrokenPipe => {
+                    self.unsent = self.input.len() - self.sent;
+                    self.sent = self.input.len();
+                    break;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        if self.sent == self.input.len() {
+            self.finish_input();
+        } else {
+            self.next_piece = Instant::now() + self.feed.delay;
+        }
+
+        Ok(())
+    }
+
+    /// close stdin so that target sees end of input, unless it is kept open
+    fn finish_input(&mut self) {
+        let stdin = self.stdin.take();
+
+        if self.feed.keep_open {
+            self.held = stdin;
+        }
+    }
+
+    /// Read everything available, closed streams are dropped. Stdout is kept
+    /// as response to last message if responses are read, rest is discarded.
+    fn drain_outputs(&mut self) {
+        let mut buffer = [0; 4096];
+
+        let stdout = self.stdout;
+        let mut answered = false;
+        let mut response = self
+            .responses
+            .as_mut()
+            .and_then(|responses| responses.last_mut());
+
+        self.outputs.retain_mut(|output| loop {
+            match output.read(&mut buffer) {
+                Ok(0) => return false,
+                Ok(read) if Some(output.as_raw_fd()) == stdout => {
+                    answered = true;
+
+                    if let Some(response) = response.as_mut() {
+                        let kept = read.min(RESPONSE_LIMIT.saturating_sub(response.len()));
+                        response.extend_from_slice(&buffer[..kept]);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return true,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => return false,
+            }
+        });
+
+        // target answered previous piece, next one needs not wait any longer
+        if answered && self.feed.wait_response && self.sent == self.piece_end {
+            self.next_piece = self.next_piece.min(Instant::now());
+        }
+    }
+}
+
+/// Feeds input to child and drains its output without poll(2): input is
+/// written from calling thread while output streams are drained by thread
+/// each. Pieces are written after delay only, whole stdout is one response.
+#[cfg(not(unix))]
+pub struct ChildIo<'a> {
+    input: &'a [u8],
+    messages: &'a [usize],
+    child: &'a mut Child,
+    feed: StdinFeed,
+}
+
+#[cfg(not(unix))]
+impl<'a> ChildIo<'a> {
+    /// Take piped streams of child, streams that are not piped are ignored.
+    pub fn new(
+        input: &'a [u8],
+        messages: &'a [usize],
+        child: &'a mut Child,
+        feed: StdinFeed,
+    ) -> std::io::Result<Self> {
+        Ok(ChildIo {
+            input,
+            messages,
+            child,
+            feed,
+        })
+    }
+
+    /// Write input and read output until child closes its streams, `stop`
+    /// is not checked as blocked reads can not be interrupted.
+    pub fn pump(self, _stop: &AtomicBool) -> std::io::Result<Pumped> {
+        let stdout = self.child.stdout.take().map(|mut stdout| {
+            std::thread::spawn(move || {
+                let mut response = vec![];
+                let mut buffer = [0; 4096];
+
+                while let Ok(read @ 1..) = stdout.read(&mut buffer) {
+                    let kept = read.min(RESPONSE_LIMIT.saturating_sub(response.len()));
+                    response.extend_from_slice(&buffer[..kept]);
+                }
+
+                response
+            })
+        });
+
+        let stderr = self.child.stderr.take().map(|mut stderr| {
+            std::thread::spawn(move || std::io::copy(&mut stderr, &mut std::io::sink()))
+        });
+
+        let mut held = None;
+        let mut unsent = 0;
+
+        if let Some(mut stdin) = self.child.stdin.take() {
+            let mut sent = 0;
+
+            while sent < self.input.len() {
+                if sent > 0 {
+                    std::thread::sleep(self.feed.delay);
+                }
+
+                let end = piece_end(self.input, self.messages, self.feed.chunk, sent);
+
+                match stdin.write_all(&self.input[sent..end]) {
+                    Ok(()) => sent = end,
+                    // target may exit without reading whole input
+                    Err(e) if e.kind() == ErrorKind::BrokenPipe => {
+                        unsent = self.input.len() - sent;
+                        break;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+
+            if self.feed.keep_open {
+                held = Some(stdin);
+            }
+        }
+
+        let response = stdout.map(|reader| reader.join().unwrap_or_default());
+
+        if let Some(reader) = stderr {
+            let _ = reader.join();
+        }
+
+        Ok(Pumped {
+            held_stdin: held,
+            response: response
+                .filter(|_| self.feed.read_response)
+                .map(|response| response_class(&[response])),
+            unsent,
+        })
+    }
+}
+
+/// Class of responses to messages: first line of every response with
+/// numbers blanked out (counters, timestamps, lengths) and magnitude of its
+/// length, so that the same kind of answer gets the same class.
+pub fn response_class(responses: &[Vec<u8>]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    for response in responses {
+        let line = response.split(|&byte| byte == b'\n').next().unwrap_or(&[]);
+
+        let mut shape = line
+            .iter()
+            .map(|&byte| if byte.is_ascii_digit() { b'0' } else { byte })
+            .collect::<Vec<_>>();
+        shape.dedup_by(|a, b| *a == b'0' && *b == b'0');
+
+        shape.hash(&mut hasher);
+        response.len().checked_ilog2().hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
@@ -0,0 +1,226 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+
+use crate::{
+    campaign::StateFiles,
+    configuration::{FuzzConfig, PassStyle, DEFAULT_CONFIGS},
+};
+
+pub const CONFIG_TEMPLATE: &str = include_str!("config_template.toml");
+
+/// Hybrid graybox fuzzer that uses ptrace for coverage measurement
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    /// path to configuration file in toml, json or yaml format
+    /// [default: first existing of fuzz.toml, fuzz.yaml, fuzz.yml, fuzz.json]
+    #[arg(short, long)]
+    pub config: Option<String>,
+
+    /// apply `[profile.<name>]` section of configuration on top of base settings
+    #[arg(short, long, global = true)]
+    pub profile: Option<String>,
+
+    /// start even if output directory is used by another running instance
+    #[arg(long)]
+    pub force: bool,
+
+    /// continue campaign from state written by `--export-state` instead of running seeds
+    #[arg(long)]
+    pub import_state: Option<PathBuf>,
+
+    /// write campaign state (library, scheduling and stats) with every stats event and on exit
+    #[arg(long)]
+    pub export_state: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub overrides: ConfigOverrides,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Cli {
+    pub fn state_files(&self) -> StateFiles {
+        StateFiles {
+            import: self.import_state.clone(),
+            export: self.export_state.clone(),
+        }
+    }
+
+    pub fn config_path(&self) -> String {
+        if let Some(path) = &self.config {
+            return path.clone();
+        }
+
+        DEFAULT_CONFIGS
+            .iter()
+            .find(|path| std::path::Path::new(path).exists())
+            .unwrap_or(&DEFAULT_CONFIGS[0])
+            .to_string()
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// run fuzzing campaign (default)
+    Fuzz,
+
+    /// create or inspect configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+
+    /// print derivation tree of sample saved in grammar mode
+    Show {
+        /// path to saved sample
+        sample: PathBuf,
+    },
+
+    /// write saved crashes as rust regression tests running target on them
+    Export {
+        /// file receiving generated tests
+        #[arg(long, default_value = "bocchi_regressions.rs")]
+        file: PathBuf,
+
+        /// names of crashes in output directory to export [default: every crash]
+        names: Vec<String>,
+    },
+
+    /// measure execution speed of target for every evaluator and pass style
+    Bench {
+        /// seconds spent running each combination
+        #[arg(long, default_value_t = 5.0, value_parser = parse_seconds)]
+        seconds: f64,
+
+        /// sample to execute [default: first seed or sample generated from grammar]
+        sample: Option<PathBuf>,
+    },
+
+    /// run target on every file of directory and print exit status, trace
+    /// size and time of each
+    ReplayDir {
+        /// directory with inputs, hidden files and sidecars of saved inputs are skipped
+        directory: PathBuf,
+    },
+
+    /// run target on saved crashes and exit with code 1 if any of them
+    /// still crashes
+    Verify {
+        /// directory with saved crashes [default: `output.directory`]
+        #[arg(long)]
+        crashes: Option<PathBuf>,
+    },
+
+    /// shrink saved hang to smaller input target still times out on and
+    /// record functions it was stuck in
+    MinimizeHang {
+        /// path to saved hang
+        sample: PathBuf,
+
+        /// file receiving minimized input [default: <sample>.min]
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+
+    /// run as fuzzing engine accepting libFuzzer-style arguments
+    /// (`-runs=N`, `-max_total_time=S`, `-artifact_prefix=P`, corpus directory)
+    Libfuzzer {
+        /// target executable, receives path to input file as the only argument
+        target: String,
+
+        /// libFuzzer flags and corpus directories
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// write configuration template listing every option (commented when written as toml)
+    Init {
+        /// overwrite existing configuration file
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// print effective configuration (file merged with command line overrides)
+    Show,
+}
+
+/// Options taking precedence over values from configuration file.
+#[derive(Args, Debug)]
+pub struct ConfigOverrides {
+    /// override `binary.path`
+    #[arg(long, global = true)]
+    pub binary: Option<String>,
+
+    /// override `binary.pass_style`
+    #[arg(long, global = true)]
+    pub pass_style: Option<PassStyle>,
+
+    /// override `output.directory`
+    #[arg(long, global = true)]
+    pub output: Option<String>,
+
+    /// override `output.debug`
+    #[arg(long, global = true)]
+    pub debug: bool,
+
+    /// override `output.headless`
+    #[arg(long, global = true)]
+    pub headless: bool,
+
+    /// override `fuzzing.max_runs`
+    #[arg(long, global = true)]
+    pub max_runs: Option<usize>,
+
+    /// override `fuzzing.max_time`
+    #[arg(long, global = true)]
+    pub max_time: Option<u64>,
+}
+
+impl ConfigOverrides {
+    pub fn apply(&self, config: &mut FuzzConfig) {
+        if let Some(binary) = &self.binary {
+            config.binary.path = binary.clone();
+        }
+
+        if let Some(pass_style) = self.pass_style {
+            config.binary.pass_style = pass_style;
+        }
+
+        if let Some(output) = &self.output {
+            config.output.directory = output.clone();
+        }
+
+        if self.debug {
+            config.output.debug = true;
+        }
+
+        if self.headless {
+            config.output.headless = true;
+        }
+
+        if let Some(max_runs) = self.max_runs {
+            config.fuzzing.max_runs = Some(max_runs);
+        }
+
+        if let Some(max_time) = self.max_time {
+            config.fuzzing.max_time = Some(max_time);
+        }
+    }
+}
+
+/// duration in seconds that `Duration::from_secs_f64` accepts
+fn parse_seconds(value: &str) -> Result<f64, String> {
+    let seconds: f64 = value.parse().map_err(|e| format!("{e}"))?;
+
+    if !(seconds.is_finite() && seconds > 0.0) {
+        return Err(format!("{seconds} is not a positive number of seconds"));
+    }
+
+    Ok(seconds)
+}
@@ -0,0 +1,122 @@
+use std::ops::Range;
+
+use rand::Rng;
+
+use crate::{
+    execution::RunTrace,
+    fuzzing::Evaluator,
+    sample::{Patch, PatchKind, Sample},
+    sample_library::Footprint,
+};
+
+/// Byte ranges of sample whose change alters execution trace.
+#[derive(Clone, Debug, Default)]
+pub struct EffectiveBytes {
+    ranges: Vec<Range<usize>>,
+}
+
+impl EffectiveBytes {
+    fn new(mut ranges: Vec<Range<usize>>) -> Self {
+        ranges.sort_by_key(|range| range.start);
+
+        let mut merged: Vec<Range<usize>> = vec![];
+
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+
+        EffectiveBytes { ranges: merged }
+    }
+
+    /// number of effective bytes
+    pub fn count(&self) -> usize {
+        self.ranges.iter().map(|range| range.len()).sum()
+    }
+
+    /// uniformly chosen effective byte, none if there are no such bytes
+    pub fn random_position(&self) -> Option<usize> {
+        let mut offset = rand::thread_rng().gen_range(0..self.count().max(1));
+
+        for range in &self.ranges {
+            if offset < range.len() {
+                return Some(range.start + offset);
+            }
+            offset -= range.len();
+        }
+
+        None
+    }
+}
+
+impl Footprint for EffectiveBytes {
+    fn footprint(&self) -> usize {
+        self.ranges.capacity() * std::mem::size_of::<Range<usize>>()
+    }
+}
+
+/// executions spent on colorization of single sample besides two baseline runs
+const COLORIZATION_RUNS: usize = 64;
+
+/// Find bytes target inspects: regions of sample are replaced with random
+/// junk and ones where trace changes are split further until single bytes
+/// are left or run budget is spent (remaining regions count as effective).
+/// Returns `None` if sample does not produce the same trace twice.
+pub fn colorize<E>(
+    evaluator: &mut E,
+    sample: &Sample,
+) -> Result<Option<EffectiveBytes>, anyhow::Error>
+where
+    E: Evaluator<Item = Sample, EvalResult = RunTrace>,
+{
+    let size = sample.get_folded().len();
+
+    if size == 0 {
+        return Ok(None);
+    }
+
+    let baseline = evaluator.score(sample.clone())?.result;
+
+    if evaluator.score(sample.clone())?.result != baseline {
+        return Ok(None);
+    }
+
+    let mut rng = rand::thread_rng();
+
+    let mut pending = Vec::with_capacity(COLORIZATION_RUNS);
+    pending.push(0..size);
+
+    let mut effective = vec![];
+    let mut runs = 0;
+
+    while let Some(range) = pending.pop() {
+        if runs >= COLORIZATION_RUNS {
+            effective.push(range);
+            continue;
+        }
+
+        runs += 1;
+
+        let junk = sample.clone().apply_patch(Patch {
+            position: range.start,
+            kind: PatchKind::Replacement(range.clone().map(|_| rng.gen()).collect()),
+        });
+
+        if evaluator.score(junk)?.result == baseline {
+            continue;
+        }
+
+        if range.len() == 1 {
+            effective.push(range);
+            continue;
+        }
+
+        let middle = range.start + range.len() / 2;
+        pending.push(middle..range.end);
+        pending.push(range.start..middle);
+    }
+
+    Ok(Some(EffectiveBytes::new(effective)))
+}
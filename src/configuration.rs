@@ -1,27 +1,248 @@
-use std::collections::HashSet;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::{collections::HashSet, path::Path, time::Duration};
 
-use serde_derive::Deserialize;
+use anyhow::{anyhow, bail};
+use beau_collector::BeauCollector;
+use bocchifuzz::scheduler::{self, Scheduler};
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
-#[derive(Clone, Debug, Deserialize)]
+use crate::{
+    analysys::SymbolFilter, child_io::StdinFeed, execution::signal_number, privileges::Credentials,
+};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct FuzzConfig {
+    /// sha256 of configuration file content
+    #[serde(skip)]
+    pub hash: String,
+
     pub binary: BinaryConfig,
 
     pub input: InputOptions,
 
     #[serde(default)]
     pub output: OutputOptions,
+
+    #[serde(default)]
+    pub log: LogOptions,
+
+    #[serde(default)]
+    pub fuzzing: FuzzingOptions,
+
+    #[serde(default)]
+    pub triage: TriageOptions,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct BinaryConfig {
     pub path: String,
     pub pass_style: PassStyle,
 
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "ExitCodeFilter::accepts_any")]
     pub interesting_codes: ExitCodeFilter,
+
+    /// how target executions are scored
+    #[serde(default)]
+    pub evaluator: EvaluatorKind,
+
+    /// analyze target again if it changes on disk during campaign
+    #[serde(default)]
+    pub reanalyze_on_change: bool,
+
+    /// what besides coverage tells library entries apart
+    #[serde(default)]
+    pub trace_identity: TraceIdentity,
+
+    /// milliseconds after which target is killed, no limit if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+
+    /// times program counter of timed out target is sampled before it is killed
+    #[serde(default = "default_stuck_samples")]
+    pub stuck_samples: usize,
+
+    /// second build deciding whether sample crashes, coverage still comes from `path`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detector: Option<DetectorConfig>,
+
+    /// second build every new path and crash is run on once found, its verdict is attached to them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmation: Option<DetectorConfig>,
+
+    /// executions after which functions hit by nearly every one of them are no longer traced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prune_warmup: Option<usize>,
+
+    /// share of warmup executions function has to be hit by to be pruned
+    #[serde(default = "default_prune_threshold")]
+    pub prune_threshold: f64,
+
+    /// regexes of names of functions that are traced, every function if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include_symbols: Vec<String>,
+
+    /// regexes of names of functions that are never traced
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_symbols: Vec<String>,
+
+    /// user (name, uid or `uid:gid`) target processes run as when fuzzer runs as root
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_as: Option<String>,
+
+    /// signals (names or numbers) target has to be killed by to count as crash, any signal if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub crash_signals: Vec<String>,
+
+    /// command target is run through, path to target (and input file) is appended to it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wrapper: Vec<String>,
+
+    /// what happens to stdin of target once input is written
+    #[serde(default)]
+    pub stdin: StdinMode,
+
+    /// bytes written at once when stdin is fed in chunks
+    #[serde(default = "default_stdin_chunk")]
+    pub stdin_chunk: usize,
+
+    /// milliseconds between chunks or messages written to stdin
+    #[serde(default = "default_stdin_delay")]
+    pub stdin_delay: u64,
+
+    /// what next chunk or message written to stdin waits for
+    #[serde(default)]
+    pub message_sync: MessageSync,
+
+    /// tell paths apart by class of what target writes to stdout in response to input
+    #[serde(default)]
+    pub read_response: bool,
+}
+
+fn default_stuck_samples() -> usize {
+    3
+}
+
+fn default_stdin_chunk() -> usize {
+    64
+}
+
+fn default_stdin_delay() -> u64 {
+    10
+}
+
+fn default_prune_threshold() -> f64 {
+    0.99
+}
+
+impl BinaryConfig {
+    /// credentials of `run_as` user, resolved when configuration is validated
+    pub fn credentials(&self) -> Option<Credentials> {
+        self.run_as.as_deref().map(|user| {
+            Credentials::resolve(user).expect("run_as user is resolved when checking configuration")
+        })
+    }
+
+    /// functions selected by `include_symbols` and `exclude_symbols`
+    pub fn symbol_filter(&self) -> SymbolFilter {
+        SymbolFilter::new(&self.include_symbols, &self.exclude_symbols)
+            .expect("symbol patterns are checked when validating configuration")
+    }
+
+    /// numbers of `crash_signals`, none if every signal is a crash
+    pub fn crash_signals(&self) -> Option<HashSet<i32>> {
+        if self.crash_signals.is_empty() {
+            return None;
+        }
+
+        Some(
+            self.crash_signals
+                .iter()
+                .map(|signal| {
+                    signal_number(signal)
+                        .expect("crash signals are checked when validating configuration")
+                })
+                .collect(),
+        )
+    }
+
+    /// how input is written to stdin of target
+    pub fn stdin_feed(&self) -> StdinFeed {
+        StdinFeed {
+            keep_open: self.stdin == StdinMode::Open,
+            chunk: (self.stdin == StdinMode::Chunks).then_some(self.stdin_chunk),
+            delay: Duration::from_millis(self.stdin_delay),
+            wait_response: self.message_sync == MessageSync::Response,
+            read_response: self.read_response,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EvaluatorKind {
+    /// function coverage collected with ptrace, Linux only
+    #[cfg_attr(target_os = "linux", default)]
+    Trace,
+    /// exit status only, no coverage feedback
+    #[cfg_attr(not(target_os = "linux"), default)]
+    ExitCode,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+impl EvaluatorKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            EvaluatorKind::Trace => "trace",
+            EvaluatorKind::ExitCode => "exitcode",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceIdentity {
+    /// coverage and exit status, every exit code is distinct
+    #[default]
+    Status,
+    /// coverage and whether target exited with zero or nonzero code or crashed
+    Nonzero,
+    /// coverage and whether target crashed, exit codes are ignored
+    Coverage,
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StdinMode {
+    /// stdin is closed once whole input is written, target sees end of input
+    #[default]
+    Close,
+    /// stdin stays open until target exits, for targets reading interactively
+    Open,
+    /// input is written in chunks with delay between them, stdin is closed afterwards
+    Chunks,
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageSync {
+    /// next piece of input is written after `stdin_delay`
+    #[default]
+    Delay,
+    /// next piece is written once target writes to stdout, `stdin_delay` at most
+    Response,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DetectorConfig {
+    pub path: String,
+
+    /// exit codes reported as crash in addition to signals
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    pub crash_codes: HashSet<i32>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum InputOptions {
     Grammar { grammar: String },
@@ -31,7 +252,7 @@ pub enum InputOptions {
 #[derive(Copy, Clone, Debug, Deserialize, Default)]
 pub struct StdinFuzzingOptions {}
 
-#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Eq, Default)]
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Default, clap::ValueEnum)]
 #[serde(rename_all = "lowercase")]
 pub enum PassStyle {
     #[default]
@@ -39,13 +260,22 @@ pub enum PassStyle {
     File,
 }
 
+impl PassStyle {
+    pub fn name(self) -> &'static str {
+        match self {
+            PassStyle::Stdin => "stdin",
+            PassStyle::File => "file",
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Default)]
 pub struct SeedOptions {
     #[serde(default)]
     pub path: Option<String>,
 }
 
-#[derive(Clone, Debug, Deserialize, Default)]
+#[derive(Clone, Debug, Deserialize, Serialize, Default)]
 #[serde(untagged)]
 pub enum ExitCodeFilter {
     #[default]
@@ -66,13 +296,46 @@ impl ExitCodeFilter {
     }
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct OutputOptions {
     #[serde(default = "default_output_dir")]
     pub directory: String,
 
+    /// record verbose debug messages
     #[serde(default)]
     pub debug: bool,
+
+    /// print messages and periodic stats to stderr instead of displaying terminal interface
+    #[serde(default)]
+    pub headless: bool,
+
+    /// seconds over which execution speed is averaged
+    #[serde(default = "default_speed_window")]
+    pub speed_window: f64,
+
+    /// directory inside output directory where corpus is periodically exported in LibAFL layout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub libafl_corpus: Option<String>,
+
+    /// file inside output directory receiving number of executions hitting every function
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function_stats: Option<String>,
+
+    /// seconds between snapshots of corpus and crashes, no snapshots if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot_interval: Option<u64>,
+
+    /// number of most recent snapshots to keep
+    #[serde(default = "default_snapshot_keep")]
+    pub snapshot_keep: usize,
+
+    /// collect core dump of every new crash next to it
+    #[serde(default)]
+    pub core_dumps: bool,
+
+    /// save inputs target timed out on to `hangs` directory inside output directory
+    #[serde(default)]
+    pub save_hangs: bool,
 }
 
 impl Default for OutputOptions {
@@ -80,6 +343,14 @@ impl Default for OutputOptions {
         Self {
             directory: "output".to_string(),
             debug: false,
+            headless: false,
+            speed_window: default_speed_window(),
+            libafl_corpus: None,
+            function_stats: None,
+            snapshot_interval: None,
+            snapshot_keep: default_snapshot_keep(),
+            core_dumps: false,
+            save_hangs: false,
         }
     }
 }
@@ -88,13 +359,737 @@ fn default_output_dir() -> String {
     "output".to_string()
 }
 
+fn default_speed_window() -> f64 {
+    5.0
+}
+
+fn default_snapshot_keep() -> usize {
+    5
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FuzzingOptions {
+    /// start in crash exploitation mode, mostly mutating known crashes
+    #[serde(default)]
+    pub exploit_crashes: bool,
+
+    /// share of executions running freshly generated sample in grammar mode
+    #[serde(default = "default_fresh_samples")]
+    pub fresh_samples: f64,
+
+    /// exponent of size factor in energy of library entries, 0 ignores size
+    #[serde(default = "default_size_weight")]
+    pub size_weight: f64,
+
+    /// how library entries picked for mutation are weighted
+    #[serde(default)]
+    pub scheduler: SchedulerKind,
+
+    /// pause fuzzing if share of failed executions among last `error_window` exceeds this value
+    #[serde(default = "default_max_error_rate")]
+    pub max_error_rate: f64,
+
+    /// number of recent executions considered by `max_error_rate`
+    #[serde(default = "default_error_window")]
+    pub error_window: usize,
+
+    /// niceness of fuzzing thread and target, inherited by spawned processes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nice: Option<i32>,
+
+    /// pin fuzzing thread and target to given CPU core
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu: Option<usize>,
+
+    /// stop after given number of executions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_runs: Option<usize>,
+
+    /// stop after given number of seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_time: Option<u64>,
+
+    /// input is text, mutate lines, tokens and numbers instead of raw bytes
+    #[serde(default)]
+    pub text: bool,
+
+    /// find bytes target inspects in library entries and direct binary mutations at them
+    #[serde(default)]
+    pub colorize: bool,
+
+    /// parts of input binary mutations avoid or prefer
+    #[serde(default)]
+    pub focus: FocusOptions,
+
+    /// number of times new crash is run again to tell deterministic crashes from flaky ones
+    #[serde(default)]
+    pub confirm_crashes: usize,
+
+    /// executions spent shrinking every new crash before it is saved, not minimized if 0
+    #[serde(default)]
+    pub minimize_crashes: usize,
+
+    /// megabytes library and fuzzer buffers may take, least valuable library entries are evicted past it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_budget: Option<usize>,
+
+    /// keep fuzzing below execution rate or share of CPU time by sleeping between executions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub throttle: Option<Throttle>,
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SchedulerKind {
+    /// every entry is equally likely
+    Uniform,
+    /// entries reaching more functions
+    Coverage,
+    /// entries reaching rarely executed functions
+    #[default]
+    Rarity,
+    /// entries executing quickly
+    Fast,
+}
+
+impl SchedulerKind {
+    pub fn build(self) -> Box<dyn Scheduler> {
+        match self {
+            SchedulerKind::Uniform => Box::new(scheduler::Uniform),
+            SchedulerKind::Coverage => Box::new(scheduler::Coverage),
+            SchedulerKind::Rarity => Box::new(scheduler::Rarity),
+            SchedulerKind::Fast => Box::new(scheduler::Fast),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Throttle {
+    /// target executions per second
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execs: Option<f64>,
+
+    /// percents of one CPU core taken by fuzzer and target together
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu: Option<f64>,
+}
+
+impl Default for FuzzingOptions {
+    fn default() -> Self {
+        Self {
+            exploit_crashes: false,
+            fresh_samples: default_fresh_samples(),
+            size_weight: default_size_weight(),
+            scheduler: Default::default(),
+            max_error_rate: default_max_error_rate(),
+            error_window: default_error_window(),
+            nice: None,
+            cpu: None,
+            max_runs: None,
+            max_time: None,
+            text: false,
+            colorize: false,
+            focus: Default::default(),
+            confirm_crashes: 0,
+            minimize_crashes: 0,
+            memory_budget: None,
+            throttle: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FocusOptions {
+    /// byte ranges (start and end offsets, end excluded) binary mutations must not touch
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protect: Vec<(usize, usize)>,
+
+    /// grammar rules whose bytes binary mutations must not touch
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protect_rules: Vec<String>,
+
+    /// byte ranges binary mutations are moved into
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hot: Vec<(usize, usize)>,
+
+    /// grammar rules whose bytes binary mutations are moved into
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hot_rules: Vec<String>,
+
+    /// share of binary mutations moved into hot ranges
+    #[serde(default = "default_hot_rate")]
+    pub hot_rate: f64,
+}
+
+impl Default for FocusOptions {
+    fn default() -> Self {
+        Self {
+            protect: vec![],
+            protect_rules: vec![],
+            hot: vec![],
+            hot_rules: vec![],
+            hot_rate: default_hot_rate(),
+        }
+    }
+}
+
+impl FocusOptions {
+    pub fn rules(&self) -> impl Iterator<Item = &String> {
+        self.protect_rules.iter().chain(&self.hot_rules)
+    }
+}
+
+fn default_hot_rate() -> f64 {
+    0.5
+}
+
+fn default_size_weight() -> f64 {
+    1.0
+}
+
+fn default_max_error_rate() -> f64 {
+    0.1
+}
+
+fn default_error_window() -> usize {
+    1000
+}
+
+fn default_fresh_samples() -> f64 {
+    0.05
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Default)]
+pub struct TriageOptions {
+    /// analyze new crashes with casr-gdb or casr-san if installed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub casr: Option<CasrTool>,
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CasrTool {
+    Gdb,
+    San,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LogOptions {
+    /// number of messages kept for display, rounded up to a power of two
+    #[serde(default = "default_log_capacity")]
+    pub capacity: usize,
+
+    /// path to file receiving fuzzing events, relative to output directory
+    #[serde(default = "default_event_log")]
+    pub events: String,
+
+    /// size in bytes after which event log is rotated, never rotate if not set
+    #[serde(default)]
+    pub max_size: Option<u64>,
+
+    /// number of rotated event logs to keep
+    #[serde(default = "default_log_keep")]
+    pub keep: usize,
+
+    /// seconds between stats events
+    #[serde(default = "default_stats_interval")]
+    pub stats_interval: u64,
+}
+
+impl Default for LogOptions {
+    fn default() -> Self {
+        Self {
+            capacity: default_log_capacity(),
+            events: default_event_log(),
+            max_size: None,
+            keep: default_log_keep(),
+            stats_interval: default_stats_interval(),
+        }
+    }
+}
+
+fn default_log_capacity() -> usize {
+    128
+}
+
+fn default_event_log() -> String {
+    "fuzzing.log".to_string()
+}
+
+fn default_log_keep() -> usize {
+    3
+}
+
+fn default_stats_interval() -> u64 {
+    60
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// guess format by file extension, falling back to toml
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("json") => ConfigFormat::Json,
+            Some("yaml" | "yml") => ConfigFormat::Yaml,
+            _ => ConfigFormat::Toml,
+        }
+    }
+
+    fn parse(self, content: &str) -> Result<toml::value::Table, ConfigReadError> {
+        match self {
+            ConfigFormat::Toml => toml::from_str(content).map_err(|e| e.into()),
+            ConfigFormat::Json => serde_json::from_str(content).map_err(|e| e.into()),
+            ConfigFormat::Yaml => serde_yaml::from_str(content).map_err(|e| e.into()),
+        }
+        .map_err(ConfigReadError::ParseError)
+    }
+
+    pub fn serialize<T: serde::Serialize>(self, value: &T) -> Result<String, anyhow::Error> {
+        Ok(match self {
+            ConfigFormat::Toml => toml::to_string(value)?,
+            ConfigFormat::Json => serde_json::to_string_pretty(value)? + "\n",
+            ConfigFormat::Yaml => serde_yaml::to_string(value)?,
+        })
+    }
+}
+
+/// Candidates for configuration file when none is given explicitly.
+pub const DEFAULT_CONFIGS: [&str; 4] = ["fuzz.toml", "fuzz.yaml", "fuzz.yml", "fuzz.json"];
+
 pub enum ConfigReadError {
     ReadError(std::io::Error),
-    ParseError(toml::de::Error),
+    ParseError(anyhow::Error),
+    UnknownProfile {
+        name: String,
+        available: Vec<String>,
+    },
+    UndefinedVariable(String),
+}
+
+/// replace `${VAR}` references with values of environment variables
+fn expand_variables(value: &str) -> Result<String, ConfigReadError> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+
+        let name = &rest[start + 2..start + end];
+        let substitution = std::env::var(name)
+            .map_err(|_| ConfigReadError::UndefinedVariable(name.to_string()))?;
+
+        result.push_str(&rest[..start]);
+        result.push_str(&substitution);
+        rest = &rest[start + end + 1..];
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+fn interpolate(value: &mut toml::Value) -> Result<(), ConfigReadError> {
+    match value {
+        toml::Value::String(s) => *s = expand_variables(s)?,
+        toml::Value::Array(items) => {
+            for item in items {
+                interpolate(item)?;
+            }
+        }
+        toml::Value::Table(table) => {
+            for (_key, item) in table.iter_mut() {
+                interpolate(item)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
 }
 
-pub fn load_config<P: AsRef<std::path::Path>>(path: P) -> Result<FuzzConfig, ConfigReadError> {
+/// recursively overwrite values of `base` with ones from `overlay`
+fn merge_tables(base: &mut toml::value::Table, overlay: toml::value::Table) {
+    for (key, value) in overlay {
+        match value {
+            toml::Value::Table(overlay) => {
+                if let Some(toml::Value::Table(existing)) = base.get_mut(&key) {
+                    merge_tables(existing, overlay);
+                } else {
+                    base.insert(key, toml::Value::Table(overlay));
+                }
+            }
+            value => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Read config (toml, json or yaml depending on extension), applying `[profile.<name>]` section on top of base settings if profile is given.
+/// `${VAR}` references in string values are substituted from environment.
+pub fn load_config<P: AsRef<std::path::Path>>(
+    path: P,
+    profile: Option<&str>,
+) -> Result<FuzzConfig, ConfigReadError> {
+    let format = ConfigFormat::from_path(&path);
+
     let config: String = std::fs::read_to_string(path).map_err(ConfigReadError::ReadError)?;
 
-    toml::from_str::<FuzzConfig>(&config).map_err(ConfigReadError::ParseError)
+    let mut table = format.parse(&config)?;
+
+    let mut profiles = match table.remove("profile") {
+        Some(toml::Value::Table(profiles)) => profiles,
+        _ => Default::default(),
+    };
+
+    if let Some(name) = profile {
+        match profiles.remove(name) {
+            Some(toml::Value::Table(overlay)) => merge_tables(&mut table, overlay),
+            _ => {
+                return Err(ConfigReadError::UnknownProfile {
+                    name: name.to_string(),
+                    available: profiles.keys().cloned().collect(),
+                })
+            }
+        }
+    }
+
+    let mut value = toml::Value::Table(table);
+
+    interpolate(&mut value)?;
+
+    let mut parsed = value
+        .try_into::<FuzzConfig>()
+        .map_err(|e| ConfigReadError::ParseError(e.into()))?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(config.as_bytes());
+    if let Some(name) = profile {
+        hasher.update(name.as_bytes());
+    }
+    parsed.hash = format!("{:x}", hasher.finalize());
+
+    Ok(parsed)
+}
+
+type ValidateResult = Result<(), anyhow::Error>;
+
+/// check that paths mentioned in config are usable, reporting all problems at once
+pub fn validate_config(config: &FuzzConfig) -> ValidateResult {
+    let checks = [
+        check_binary,
+        check_detector,
+        check_input,
+        check_output,
+        check_fuzzing,
+    ];
+
+    let _ = checks
+        .into_iter()
+        .map(|check| check(config))
+        .bcollect::<Vec<_>>()?;
+
+    Ok(())
+}
+
+fn check_binary(config: &FuzzConfig) -> ValidateResult {
+    check_executable(&config.binary.path, "binary.path")?;
+
+    if cfg!(not(target_os = "linux")) && config.binary.evaluator == EvaluatorKind::Trace {
+        bail!("tracing is only supported on Linux, use `exitcode` evaluator, check `binary.evaluator`");
+    }
+
+    if config.binary.prune_warmup == Some(0) {
+        bail!("pruning warmup is empty, check `binary.prune_warmup`");
+    }
+
+    let threshold = config.binary.prune_threshold;
+
+    if !(threshold > 0.0 && threshold <= 1.0) {
+        bail!("prune threshold {threshold} is not in (0, 1], check `binary.prune_threshold`");
+    }
+
+    if cfg!(not(unix)) && !config.binary.crash_signals.is_empty() {
+        bail!("targets are only killed by signals on Unix, check `binary.crash_signals`");
+    }
+
+    if let Some(signal) = config
+        .binary
+        .crash_signals
+        .iter()
+        .find(|signal| signal_number(signal).is_none())
+    {
+        bail!("unknown signal `{signal}`, check `binary.crash_signals`");
+    }
+
+    for (patterns, option) in [
+        (&config.binary.include_symbols, "binary.include_symbols"),
+        (&config.binary.exclude_symbols, "binary.exclude_symbols"),
+    ] {
+        for pattern in patterns {
+            if let Err(e) = regex::Regex::new(pattern) {
+                // regex errors span several lines pointing at the problem
+                bail!("invalid symbol pattern, check `{option}`\n{e}");
+            }
+        }
+    }
+
+    if config.binary.stdin != StdinMode::Close && config.binary.pass_style == PassStyle::File {
+        bail!("input is not written to stdin in file pass style, check `binary.stdin`");
+    }
+
+    if config.binary.read_response && config.binary.pass_style == PassStyle::File {
+        bail!("responses are only read in stdin pass style, check `binary.read_response`");
+    }
+
+    if config.binary.stdin == StdinMode::Chunks && config.binary.stdin_chunk == 0 {
+        bail!("stdin chunks are empty, check `binary.stdin_chunk`");
+    }
+
+    if let Some(program) = config.binary.wrapper.first() {
+        if !is_runnable(program) {
+            bail!("wrapper `{program}` is not found, check `binary.wrapper`");
+        }
+    }
+
+    if let Some(user) = &config.binary.run_as {
+        let credentials =
+            Credentials::resolve(user).map_err(|e| anyhow!("{e}, check `binary.run_as`"))?;
+
+        if !credentials.can_switch() {
+            bail!(
+                "running target as `{user}` requires running fuzzer as root, check `binary.run_as`"
+            );
+        }
+
+        // target can not open file descriptor of fuzzer running as another user
+        if config.binary.pass_style == PassStyle::File {
+            bail!("file pass style does not work with `binary.run_as`, use stdin");
+        }
+    }
+
+    Ok(())
+}
+
+/// whether program is an existing path or found in PATH
+fn is_runnable(program: &str) -> bool {
+    if program.contains('/') {
+        return Path::new(program).is_file();
+    }
+
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+fn check_detector(config: &FuzzConfig) -> ValidateResult {
+    if let Some(detector) = &config.binary.detector {
+        check_executable(&detector.path, "binary.detector.path")?;
+    }
+
+    match &config.binary.confirmation {
+        Some(confirmation) => check_executable(&confirmation.path, "binary.confirmation.path"),
+        None => Ok(()),
+    }
+}
+
+fn check_executable(path: &str, option: &str) -> ValidateResult {
+    let path = Path::new(path);
+
+    let metadata = std::fs::metadata(path).map_err(|e| {
+        anyhow!(
+            "binary `{}` is not accessible ({e}), check `{option}`",
+            path.display()
+        )
+    })?;
+
+    if !metadata.is_file() {
+        bail!(
+            "binary `{}` is not a regular file, check `{option}`",
+            path.display()
+        );
+    }
+
+    #[cfg(unix)]
+    if metadata.permissions().mode() & 0o111 == 0 {
+        bail!(
+            "binary `{}` is not executable, try `chmod +x {}`",
+            path.display(),
+            path.display()
+        );
+    }
+
+    Ok(())
+}
+
+fn check_input(config: &FuzzConfig) -> ValidateResult {
+    match &config.input {
+        InputOptions::Grammar { grammar } => {
+            if !Path::new(grammar).is_file() {
+                bail!("grammar file `{grammar}` does not exist, check `input.grammar`");
+            }
+        }
+        InputOptions::Seeds { seeds } => {
+            if !Path::new(seeds).is_dir() {
+                bail!(
+                    "seeds directory `{seeds}` does not exist, check `input.seeds` or create it and put some examples inside"
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn check_output(config: &FuzzConfig) -> ValidateResult {
+    let directory = Path::new(&config.output.directory);
+
+    std::fs::create_dir_all(directory).map_err(|e| {
+        anyhow!(
+            "failed to create output directory `{}` ({e}), check `output.directory`",
+            directory.display()
+        )
+    })?;
+
+    let probe = directory.join(".write-test");
+
+    std::fs::write(&probe, []).map_err(|e| {
+        anyhow!(
+            "output directory `{}` is not writable ({e}), check permissions or `output.directory`",
+            directory.display()
+        )
+    })?;
+
+    let _ = std::fs::remove_file(probe);
+
+    if config.output.snapshot_interval == Some(0) {
+        bail!("snapshot interval is zero, check `output.snapshot_interval`");
+    }
+
+    if cfg!(not(unix)) && config.output.core_dumps {
+        bail!("core dumps are only collected on Unix, check `output.core_dumps`");
+    }
+
+    if config.output.snapshot_keep == 0 {
+        bail!("no snapshots would be kept, check `output.snapshot_keep`");
+    }
+
+    let window = config.output.speed_window;
+
+    if !(window.is_finite() && window > 0.0) {
+        bail!("speed window {window} is not a positive number, check `output.speed_window`");
+    }
+
+    if let Some(file) = &config.output.function_stats {
+        if config.binary.evaluator == EvaluatorKind::ExitCode {
+            bail!("function stats `{file}` need trace evaluator, check `output.function_stats`");
+        }
+    }
+
+    Ok(())
+}
+
+fn check_fuzzing(config: &FuzzConfig) -> ValidateResult {
+    let rate = config.fuzzing.fresh_samples;
+
+    if !(0.0..=1.0).contains(&rate) {
+        bail!("fresh sample rate {rate} is not between 0 and 1, check `fuzzing.fresh_samples`");
+    }
+
+    let weight = config.fuzzing.size_weight;
+
+    if !weight.is_finite() || weight < 0.0 {
+        bail!("size weight {weight} is not a non-negative number, check `fuzzing.size_weight`");
+    }
+
+    let rate = config.fuzzing.max_error_rate;
+
+    if !(0.0..=1.0).contains(&rate) {
+        bail!("error rate {rate} is not between 0 and 1, check `fuzzing.max_error_rate`");
+    }
+
+    if config.fuzzing.error_window == 0 {
+        bail!("error window is empty, check `fuzzing.error_window`");
+    }
+
+    if cfg!(not(unix)) && config.fuzzing.nice.is_some() {
+        bail!("niceness is only set on Unix, check `fuzzing.nice`");
+    }
+
+    if cfg!(not(target_os = "linux")) && config.fuzzing.cpu.is_some() {
+        bail!("fuzzer is only pinned to CPU core on Linux, check `fuzzing.cpu`");
+    }
+
+    if let Some(nice) = config.fuzzing.nice {
+        if !(-20..=19).contains(&nice) {
+            bail!("niceness {nice} is not between -20 and 19, check `fuzzing.nice`");
+        }
+    }
+
+    if let Some(cpu) = config.fuzzing.cpu {
+        let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get());
+
+        if cpu >= cores {
+            bail!("CPU core {cpu} is not available (found {cores} cores), check `fuzzing.cpu`");
+        }
+    }
+
+    if config.fuzzing.minimize_crashes > 0 && matches!(config.input, InputOptions::Grammar { .. }) {
+        bail!("crashes are only minimized in binary mode, check `fuzzing.minimize_crashes`");
+    }
+
+    if config.fuzzing.memory_budget == Some(0) {
+        bail!("memory budget is empty, check `fuzzing.memory_budget`");
+    }
+
+    if let Some(throttle) = config.fuzzing.throttle {
+        if throttle.execs.is_none() && throttle.cpu.is_none() {
+            bail!("throttle sets no limit, check `fuzzing.throttle`");
+        }
+
+        if let Some(rate) = throttle
+            .execs
+            .filter(|rate| !(rate.is_finite() && *rate > 0.0))
+        {
+            bail!("execution rate {rate} is not a positive number, check `fuzzing.throttle.execs`");
+        }
+
+        if cfg!(not(unix)) && throttle.cpu.is_some() {
+            bail!("CPU time is only measured on Unix, check `fuzzing.throttle.cpu`");
+        }
+
+        if let Some(share) = throttle
+            .cpu
+            .filter(|share| !(*share > 0.0 && *share <= 100.0))
+        {
+            bail!("CPU share {share} is not in (0, 100] percent, check `fuzzing.throttle.cpu`");
+        }
+    }
+
+    let focus = &config.fuzzing.focus;
+
+    for (option, ranges) in [("protect", &focus.protect), ("hot", &focus.hot)] {
+        if let Some((start, end)) = ranges.iter().find(|(start, end)| start >= end) {
+            bail!("byte range {start}..{end} is empty, check `fuzzing.focus.{option}`");
+        }
+    }
+
+    let rate = focus.hot_rate;
+
+    if !(0.0..=1.0).contains(&rate) {
+        bail!("hot rate {rate} is not between 0 and 1, check `fuzzing.focus.hot_rate`");
+    }
+
+    if matches!(config.input, InputOptions::Seeds { .. }) {
+        if let Some(rule) = focus.rules().next() {
+            bail!("rule `{rule}` is given but fuzzing does not use grammar, check `fuzzing.focus`");
+        }
+    }
+
+    Ok(())
 }
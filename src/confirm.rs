@@ -0,0 +1,182 @@
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{mpsc, Arc},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use serde_derive::Serialize;
+
+use crate::{
+    configuration::{DetectorConfig, FuzzConfig, PassStyle},
+    execution::{self, target_command},
+};
+
+/// Directory inside output directory receiving findings confirmation
+/// build was run on together with its stderr.
+pub const CONFIRM_DIR: &str = "confirmed";
+
+/// how often running confirmation build is checked for exit
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Verdict of confirmation build on finding.
+#[derive(Clone, Debug, Serialize)]
+pub struct Confirmation {
+    /// build was killed by signal or exited with one of its crash codes
+    pub crashed: bool,
+    /// how build exited: `code 1`, `signal 6` or `timeout`
+    pub status: String,
+    /// last `SUMMARY:` line of sanitizer report
+    pub summary: Option<String>,
+    /// stderr of build, kept if either build crashed
+    pub report: Option<PathBuf>,
+}
+
+struct Job {
+    name: String,
+    content: Vec<u8>,
+    /// finding crashed primary build
+    crashed: bool,
+}
+
+/// name of finding, whether it crashed primary build and verdict of confirmation build
+pub type ConfirmResult = (String, bool, Result<Confirmation, anyhow::Error>);
+
+/// Runs findings on confirmation build one at a time in background
+/// thread, so that execution loop does not wait for slow sanitized build.
+pub struct Confirmer {
+    jobs: mpsc::Sender<Job>,
+    results: mpsc::Receiver<ConfirmResult>,
+}
+
+impl Confirmer {
+    /// start confirmation thread, none if confirmation build is not configured
+    pub fn spawn(config: Arc<FuzzConfig>) -> Option<Self> {
+        config.binary.confirmation.as_ref()?;
+
+        let (jobs, received) = mpsc::channel::<Job>();
+        let (sender, results) = mpsc::channel();
+
+        thread::spawn(move || {
+            let build = config.binary.confirmation.as_ref().unwrap();
+
+            for job in received {
+                let verdict = confirm(&config, build, &job);
+
+                if sender.send((job.name, job.crashed, verdict)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Some(Confirmer { jobs, results })
+    }
+
+    pub fn submit(&self, name: &str, content: &[u8], crashed: bool) {
+        let _ = self.jobs.send(Job {
+            name: name.to_string(),
+            content: content.to_vec(),
+            crashed,
+        });
+    }
+
+    /// verdicts of finished confirmation runs
+    pub fn results(&self) -> impl Iterator<Item = ConfirmResult> + '_ {
+        self.results.try_iter()
+    }
+}
+
+/// last summary line sanitizers end their reports with
+fn sanitizer_summary(report: &Path) -> Option<String> {
+    let content = std::fs::read(report).ok()?;
+
+    String::from_utf8_lossy(&content)
+        .lines()
+        .rev()
+        .find(|line| line.starts_with("SUMMARY: "))
+        .map(|line| line.trim().to_string())
+}
+
+/// Run finding on confirmation build with stderr going to report file.
+/// Input and report are removed unless one of builds crashed.
+fn confirm(
+    config: &FuzzConfig,
+    build: &DetectorConfig,
+    job: &Job,
+) -> Result<Confirmation, anyhow::Error> {
+    let directory = Path::new(&config.output.directory).join(CONFIRM_DIR);
+    std::fs::create_dir_all(&directory)
+        .with_context(|| format!("creating {}", directory.display()))?;
+
+    let input = directory.join(&job.name);
+    let report = directory.join(format!("{}.stderr", job.name));
+
+    std::fs::write(&input, &job.content).with_context(|| format!("writing {}", input.display()))?;
+
+    let mut command = target_command(&config.binary.wrapper, build.path.as_ref());
+
+    command.stdout(Stdio::null()).stderr(File::create(&report)?);
+    execution::isolate(&mut command);
+
+    match config.binary.pass_style {
+        PassStyle::Stdin => command.stdin(File::open(&input)?),
+        PassStyle::File => command.arg(&input).stdin(Stdio::null()),
+    };
+
+    if let Some(credentials) = config.binary.credentials() {
+        credentials.apply(&mut command);
+    }
+
+    let mut child = command
+        .spawn()
+        .with_context(|| format!("running {}", build.path))?;
+
+    let deadline = config
+        .binary
+        .timeout
+        .map(|timeout| Instant::now() + Duration::from_millis(timeout));
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            // build runs in its own process group, its children are killed as well
+            execution::kill_process(child.id(), true);
+            child.wait()?;
+            break None;
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let status = status.map(|status| (status.code(), execution::exit_signal(&status)));
+
+    let (crashed, status) = match status {
+        Some((Some(code), _)) => (build.crash_codes.contains(&code), format!("code {code}")),
+        Some((None, Some(signal))) => (true, format!("signal {signal}")),
+        Some((None, None)) => (true, "killed".to_string()),
+        None => (false, "timeout".to_string()),
+    };
+
+    let summary = sanitizer_summary(&report);
+
+    let report = if crashed || job.crashed {
+        Some(report)
+    } else {
+        let _ = std::fs::remove_file(&input);
+        let _ = std::fs::remove_file(&report);
+        None
+    };
+
+    Ok(Confirmation {
+        crashed,
+        status,
+        summary,
+        report,
+    })
+}
@@ -0,0 +1,120 @@
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+    process::Command,
+    time::SystemTime,
+};
+
+/// Directory inside output directory targets run in when core dumps are
+/// collected, cores are written there by kernel.
+pub const CORE_DIR: &str = ".cores";
+
+/// cores taken from last executions wait here until bookkeeping attaches them to crashes
+const PENDING_DIR: &str = "pending";
+
+const ET_CORE: u16 = 4;
+
+pub fn core_dir(output: &str) -> PathBuf {
+    Path::new(output).join(CORE_DIR)
+}
+
+/// Explain why cores would not appear in working directory of target,
+/// none if kernel writes them there.
+pub fn check_core_pattern() -> Option<String> {
+    let pattern = std::fs::read_to_string("/proc/sys/kernel/core_pattern").ok()?;
+    let pattern = pattern.trim();
+
+    if let Some(handler) = pattern.strip_prefix('|') {
+        return Some(format!(
+            "core dumps are piped to `{handler}`, they can not be collected (see core(5))"
+        ));
+    }
+
+    if pattern.contains('/') {
+        return Some(format!(
+            "core dumps are written to `{pattern}` instead of working directory of target, they can not be collected"
+        ));
+    }
+
+    None
+}
+
+/// Let target dump core into `dir`: its core size limit is raised to hard
+/// limit and it runs inside `dir`. Core dumps are only collected on Unix.
+pub fn enable(command: &mut Command, dir: &Path) {
+    command.current_dir(dir);
+
+    #[cfg(unix)]
+    unsafe {
+        std::os::unix::process::CommandExt::pre_exec(command, || {
+            let mut limit = libc::rlimit {
+                rlim_cur: 0,
+                rlim_max: 0,
+            };
+
+            if libc::getrlimit(libc::RLIMIT_CORE, &mut limit) == 0 {
+                limit.rlim_cur = limit.rlim_max;
+                libc::setrlimit(libc::RLIMIT_CORE, &limit);
+            }
+
+            Ok(())
+        });
+    }
+}
+
+/// whether file starts with header of ELF core file
+fn is_core(path: &Path) -> bool {
+    let mut header = [0; 18];
+
+    let read = std::fs::File::open(path).and_then(|mut file| file.read_exact(&mut header));
+
+    read.is_ok()
+        && header.starts_with(b"\x7fELF")
+        && u16::from_le_bytes([header[16], header[17]]) == ET_CORE
+}
+
+/// Take core dumped by last crash out of `dir`, older cores left by
+/// crashes nobody took them for are removed. Returned core is moved out
+/// of the way of later executions.
+pub fn take_core(dir: &Path) -> Option<PathBuf> {
+    let mut cores = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && is_core(path))
+        .map(|path| {
+            let modified = path
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            (modified, path)
+        })
+        .collect::<Vec<_>>();
+
+    cores.sort();
+
+    let (_modified, latest) = cores.pop()?;
+
+    for (_modified, path) in cores {
+        let _ = std::fs::remove_file(path);
+    }
+
+    let pending = dir.join(PENDING_DIR);
+    std::fs::create_dir_all(&pending).ok()?;
+
+    let stamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos());
+
+    let taken = pending.join(format!("core-{stamp}"));
+    std::fs::rename(&latest, &taken).ok()?;
+
+    Some(taken)
+}
+
+/// path core dump of crash saved at given path is kept at
+pub fn crash_core_path(crash_path: &Path) -> PathBuf {
+    let mut path = crash_path.as_os_str().to_owned();
+    path.push(".core");
+    PathBuf::from(path)
+}
@@ -1,19 +1,34 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt::Display,
     io::Write,
-    os::fd::AsRawFd,
-    path::PathBuf,
-    process::{self, Child, Command, Stdio},
+    path::{Path, PathBuf},
+    process::{self, Child, Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
+#[cfg(target_os = "linux")]
 use memfile::MemFile;
+#[cfg(target_os = "linux")]
 use ptracer::{nix::sys::wait::WaitStatus, Ptracer};
+use serde_derive::{Deserialize, Serialize};
+#[cfg(target_os = "linux")]
+use std::os::fd::AsRawFd;
+#[cfg(unix)]
+use std::os::unix::process::{CommandExt, ExitStatusExt};
 
 use crate::{
     analysys::ElfInfo,
+    child_io::{ChildIo, Pumped, StdinFeed},
     configuration::PassStyle as PassStyleCfg,
+    coredump,
     fuzzing::{Evaluator, TestedSample},
+    privileges::Credentials,
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -22,24 +37,490 @@ pub enum ExecutionError {
     SpawnError(std::io::Error),
     #[error("error communicating with child child: {0}")]
     StdinError(std::io::Error),
+    #[error("target timed out")]
+    Timeout,
+}
+
+/// Target did not finish in time and was killed.
+pub fn is_timeout(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<ExecutionError>(),
+        Some(ExecutionError::Timeout)
+    ) || matches!(
+        error.downcast_ref::<TraceError>(),
+        Some(TraceError::Timeout)
+    )
+}
+
+/// Input could not be written to target.
+pub fn is_input_failure(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<ExecutionError>(),
+        Some(ExecutionError::StdinError(_))
+    ) || matches!(
+        error.downcast_ref::<TraceError>(),
+        Some(TraceError::Stdin(_))
+    )
+}
+
+/// Target process could not be started.
+pub fn is_spawn_failure(error: &anyhow::Error) -> bool {
+    #[cfg(target_os = "linux")]
+    if matches!(
+        error.downcast_ref::<TraceError>(),
+        Some(TraceError::Spawn(_))
+    ) {
+        return true;
+    }
+
+    matches!(
+        error.downcast_ref::<ExecutionError>(),
+        Some(ExecutionError::SpawnError(_))
+    )
+}
+
+/// Targets run in process group of their own so that Ctrl+C pressed in
+/// terminal stops only fuzzer and is not mistaken for crash of target.
+pub const TARGET_PROCESS_GROUP: i32 = 0;
+
+/// Start process of command in process group of its own, see `TARGET_PROCESS_GROUP`.
+pub fn isolate(command: &mut Command) {
+    #[cfg(unix)]
+    command.process_group(TARGET_PROCESS_GROUP);
+
+    // CREATE_NEW_PROCESS_GROUP, console does not pass Ctrl+C to new group
+    #[cfg(windows)]
+    std::os::windows::process::CommandExt::creation_flags(command, 0x0000_0200);
+}
+
+/// Kill process right away, together with its process group if `group`
+/// is set and process leads one.
+pub fn kill_process(pid: u32, group: bool) {
+    #[cfg(unix)]
+    unsafe {
+        let pid = pid as libc::pid_t;
+        libc::kill(if group { -pid } else { pid }, libc::SIGKILL);
+    }
+
+    // there are no signals to send elsewhere, `taskkill /T` takes children along
+    #[cfg(not(unix))]
+    {
+        let mut command = Command::new("taskkill");
+        command.arg("/F").arg("/PID").arg(pid.to_string());
+
+        if group {
+            command.arg("/T");
+        }
+
+        let _ = command.stdout(Stdio::null()).stderr(Stdio::null()).status();
+    }
+}
+
+/// Signal process was killed by.
+#[cfg(unix)]
+pub fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    status.signal()
+}
+
+/// processes are only killed by signals on Unix
+#[cfg(not(unix))]
+pub fn exit_signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+/// Kills target process that runs longer than timeout. Single background
+/// thread serves every execution, it exits once watchdog is dropped.
+#[derive(Clone)]
+pub struct Watchdog {
+    timeout: Duration,
+    /// pid of running target and its deadline
+    armed: Arc<Mutex<Option<(u32, Instant)>>>,
+    fired: Arc<AtomicBool>,
+    /// number of times program counter of target is sampled before it is killed
+    samples: Arc<AtomicUsize>,
+    /// program counters sampled from last killed target, relative to its executable
+    stuck: Arc<Mutex<Vec<usize>>>,
+}
+
+/// polls of stopped target state before program counter sample is given up
+const STOP_POLLS: usize = 50;
+
+/// State letter of process from `/proc/<pid>/stat`.
+#[cfg(target_os = "linux")]
+fn process_state(pid: u32) -> Option<char> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+
+    // command name may contain spaces and parentheses, state follows the last one
+    stat[stat.rfind(')')? + 1..].trim_start().chars().next()
+}
+
+/// Stop target for a moment and read its program counter from
+/// `/proc/<pid>/syscall`, relative to start of first mapping like breakpoints.
+#[cfg(target_os = "linux")]
+fn sample_program_counter(pid: u32) -> Option<usize> {
+    let base = proc_maps::get_process_maps(pid as proc_maps::linux_maps::Pid)
+        .ok()?
+        .first()?
+        .start();
+
+    unsafe { libc::kill(pid as libc::pid_t, libc::SIGSTOP) };
+
+    let stopped = (0..STOP_POLLS).any(|_| {
+        let stopped = matches!(process_state(pid), Some('T' | 't'));
+        if !stopped {
+            thread::sleep(Duration::from_micros(100));
+        }
+        stopped
+    });
+
+    // last field is program counter unless task is running ("running")
+    let counter = stopped
+        .then(|| std::fs::read_to_string(format!("/proc/{pid}/syscall")).ok())
+        .flatten()
+        .and_then(|syscall| {
+            let counter = syscall.split_whitespace().last()?.strip_prefix("0x")?;
+            usize::from_str_radix(counter, 16).ok()
+        });
+
+    unsafe { libc::kill(pid as libc::pid_t, libc::SIGCONT) };
+
+    counter?.checked_sub(base)
+}
+
+/// program counter of running process can only be read on Linux
+#[cfg(not(target_os = "linux"))]
+fn sample_program_counter(_pid: u32) -> Option<usize> {
+    None
+}
+
+/// Turn off address randomization for targets started from now on, so that
+/// breakpoints land at the same offsets in every execution. Targets are
+/// traced only on Linux, elsewhere there is nothing to do.
+pub fn disable_aslr() {
+    #[cfg(target_os = "linux")]
+    unsafe {
+        ptracer::disable_aslr();
+    }
+}
+
+/// File input is passed to target in. It is kept in memory on Linux and
+/// written to temporary directory elsewhere, it has to outlive target.
+pub struct InputFile {
+    #[cfg(target_os = "linux")]
+    _file: MemFile,
+    path: PathBuf,
+}
+
+impl InputFile {
+    #[cfg(target_os = "linux")]
+    pub fn create(input: &[u8]) -> std::io::Result<Self> {
+        let mut file = MemFile::create_default("stdin")?;
+        file.write_all(input)?;
+
+        let path = PathBuf::from(format!("/proc/{}/fd/{}", process::id(), file.as_raw_fd()));
+
+        Ok(InputFile { _file: file, path })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn create(input: &[u8]) -> std::io::Result<Self> {
+        // evaluators of different threads run at once, each file gets own name
+        static FILES: AtomicUsize = AtomicUsize::new(0);
+        let file = FILES.fetch_add(1, Ordering::Relaxed);
+
+        let path = std::env::temp_dir().join(format!("bocchifuzz-{}-{file}.input", process::id()));
+        std::fs::write(&path, input)?;
+
+        Ok(InputFile { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl Drop for InputFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl Watchdog {
+    pub fn new(timeout: Duration) -> Self {
+        let armed = Arc::new(Mutex::new(None));
+        let fired = Arc::new(AtomicBool::new(false));
+
+        let samples = Arc::new(AtomicUsize::new(0));
+        let stuck = Arc::new(Mutex::new(vec![]));
+
+        let target = Arc::downgrade(&armed);
+        let killed = fired.clone();
+        let sample_count = samples.clone();
+        let sampled = stuck.clone();
+        let period = (timeout / 10).clamp(Duration::from_millis(1), Duration::from_millis(10));
+
+        thread::spawn(move || {
+            while let Some(armed) = target.upgrade() {
+                {
+                    let mut armed = armed.lock().unwrap();
+
+                    if let Some((pid, deadline)) = *armed {
+                        if Instant::now() >= deadline {
+                            *sampled.lock().unwrap() = (0..sample_count.load(Ordering::SeqCst))
+                                .filter_map(|_| {
+                                    let counter = sample_program_counter(pid);
+                                    thread::sleep(Duration::from_millis(1));
+                                    counter
+                                })
+                                .collect();
+
+                            kill_process(pid, false);
+                            killed.store(true, Ordering::SeqCst);
+                            *armed = None;
+                        }
+                    }
+                }
+
+                thread::sleep(period);
+            }
+        });
+
+        Watchdog {
+            timeout,
+            armed,
+            fired,
+            samples,
+            stuck,
+        }
+    }
+
+    /// sample program counter of target given number of times before killing it
+    pub fn set_stuck_samples(&self, samples: usize) {
+        self.samples.store(samples, Ordering::SeqCst);
+    }
+
+    /// program counters sampled before last timed out target was killed
+    pub fn take_stuck(&self) -> Vec<usize> {
+        std::mem::take(&mut self.stuck.lock().unwrap())
+    }
+
+    /// start counting time of process with given pid until returned guard is dropped
+    pub fn arm(&self, pid: u32) -> ArmedWatchdog {
+        self.fired.store(false, Ordering::SeqCst);
+        *self.armed.lock().unwrap() = Some((pid, Instant::now() + self.timeout));
+
+        ArmedWatchdog {
+            watchdog: self.clone(),
+        }
+    }
+
+    fn disarm(&self) {
+        *self.armed.lock().unwrap() = None;
+    }
+}
+
+pub struct ArmedWatchdog {
+    watchdog: Watchdog,
+}
+
+impl ArmedWatchdog {
+    /// stop counting time, returns true if process was killed
+    pub fn timed_out(self) -> bool {
+        self.watchdog.disarm();
+        self.watchdog.fired.swap(false, Ordering::SeqCst)
+    }
+}
+
+impl Drop for ArmedWatchdog {
+    fn drop(&mut self) {
+        self.watchdog.disarm();
+    }
 }
 
 pub struct ExitCodeEvaluator {
     binary: String,
+    pass_style: PassStyleCfg,
+    watchdog: Option<Watchdog>,
+    credentials: Option<Credentials>,
+    core_dir: Option<PathBuf>,
+    crash_signals: Option<HashSet<i32>>,
+    wrapper: Vec<String>,
+    stdin_feed: StdinFeed,
+    /// grammar rule splitting samples into messages written one at a time
+    message_rule: Option<String>,
+    /// class of responses of last execution
+    response: Option<u64>,
+    /// target of last execution closed stdin before reading whole input
+    input_cut_short: bool,
+    /// sample last execution timed out on
+    timed_out: Option<crate::sample::Sample>,
+}
+
+/// Command running target, through wrapper program if one is given.
+pub fn target_command(wrapper: &[String], binary: &std::ffi::OsStr) -> Command {
+    match wrapper.split_first() {
+        Some((program, args)) => {
+            let mut command = Command::new(program);
+            command.args(args).arg(binary);
+            command
+        }
+        None => Command::new(binary),
+    }
+}
+
+/// offsets of messages of sample written separately, none without message rule
+fn message_starts(sample: &crate::sample::Sample, message_rule: Option<&str>) -> Vec<usize> {
+    message_rule
+        .map(|rule| sample.message_starts(rule))
+        .unwrap_or_default()
+}
+
+/// Absolute path of target, relative one would be resolved against
+/// working directory of target when it runs elsewhere.
+fn absolute(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
 }
 
 impl ExitCodeEvaluator {
-    pub fn new(binary: String) -> Self {
-        ExitCodeEvaluator { binary }
+    pub fn new(binary: String, pass_style: PassStyleCfg) -> Self {
+        ExitCodeEvaluator {
+            binary,
+            pass_style,
+            watchdog: None,
+            credentials: None,
+            core_dir: None,
+            crash_signals: None,
+            wrapper: vec![],
+            stdin_feed: StdinFeed::default(),
+            message_rule: None,
+            response: None,
+            input_cut_short: false,
+            timed_out: None,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.watchdog = timeout.map(Watchdog::new);
+        self
+    }
+
+    /// run target through wrapper command (eg. `taskset -c 3`, `qemu-aarch64`)
+    pub fn with_wrapper(mut self, wrapper: Vec<String>) -> Self {
+        self.wrapper = wrapper;
+        self
+    }
+
+    /// whether stdin is closed after input is written and whether input is written in chunks
+    pub fn with_stdin_feed(mut self, stdin_feed: StdinFeed) -> Self {
+        self.stdin_feed = stdin_feed;
+        self
+    }
+
+    /// write every application of grammar rule in sample to stdin separately
+    pub fn with_message_rule(mut self, message_rule: Option<String>) -> Self {
+        self.message_rule = message_rule;
+        self
+    }
+
+    /// class of responses of last execution, if they are read
+    pub fn take_response(&mut self) -> Option<u64> {
+        self.response.take()
+    }
+
+    /// count target as crashed only when killed by one of given signals, by any signal if not set
+    pub fn with_crash_signals(mut self, crash_signals: Option<HashSet<i32>>) -> Self {
+        self.crash_signals = crash_signals;
+        self
+    }
+
+    /// sample program counter of target timing out before it is killed, needs timeout set
+    pub fn with_stuck_samples(self, samples: usize) -> Self {
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.set_stuck_samples(samples);
+        }
+        self
+    }
+
+    /// run target as given user instead of user running fuzzer
+    pub fn with_credentials(mut self, credentials: Option<Credentials>) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// run target inside given directory with core dumps enabled
+    pub fn with_core_dumps(mut self, core_dir: Option<PathBuf>) -> Self {
+        if core_dir.is_some() {
+            self.binary = absolute(Path::new(&self.binary))
+                .to_string_lossy()
+                .to_string();
+        }
+
+        self.core_dir = core_dir;
+        self
     }
 }
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ExecResult {
     Code(i32),
     Signal,
 }
 
+/// Numbers of signals that may be named in configuration, with or
+/// without `SIG` prefix.
+#[cfg(unix)]
+const SIGNALS: &[(&str, i32)] = &[
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("ILL", libc::SIGILL),
+    ("TRAP", libc::SIGTRAP),
+    ("ABRT", libc::SIGABRT),
+    ("BUS", libc::SIGBUS),
+    ("FPE", libc::SIGFPE),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("SEGV", libc::SIGSEGV),
+    ("USR2", libc::SIGUSR2),
+    ("PIPE", libc::SIGPIPE),
+    ("ALRM", libc::SIGALRM),
+    ("TERM", libc::SIGTERM),
+    ("XCPU", libc::SIGXCPU),
+    ("XFSZ", libc::SIGXFSZ),
+    ("VTALRM", libc::SIGVTALRM),
+    ("PROF", libc::SIGPROF),
+    ("SYS", libc::SIGSYS),
+];
+
+/// there are no signals outside of Unix, crash signals can not be set there
+#[cfg(not(unix))]
+const SIGNALS: &[(&str, i32)] = &[];
+
+/// Number of signal given by name (`SIGSEGV`, `SEGV`) or number.
+pub fn signal_number(signal: &str) -> Option<i32> {
+    if let Ok(number) = signal.parse() {
+        return Some(number);
+    }
+
+    let name = signal.to_ascii_uppercase();
+    let name = name.strip_prefix("SIG").unwrap_or(&name);
+
+    SIGNALS
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, number)| *number)
+}
+
+/// Result of target killed by signal: crash if signal is one of crash
+/// signals (any signal if not set), shell-like code 128 + signal otherwise.
+fn killed_by(signal: i32, crash_signals: Option<&HashSet<i32>>) -> ExecResult {
+    match crash_signals {
+        Some(crash_signals) if !crash_signals.contains(&signal) => ExecResult::Code(128 + signal),
+        _ => ExecResult::Signal,
+    }
+}
+
 impl Display for ExecResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -50,7 +531,7 @@ impl Display for ExecResult {
 }
 
 impl Evaluator for ExitCodeEvaluator {
-    type Item = Vec<u8>;
+    type Item = crate::sample::Sample;
 
     type EvalResult = ExecResult;
 
@@ -58,28 +539,83 @@ impl Evaluator for ExitCodeEvaluator {
         &mut self,
         sample: Self::Item,
     ) -> Result<crate::fuzzing::TestedSample<Self::Item, Self::EvalResult>, anyhow::Error> {
-        let mut process = std::process::Command::new(&self.binary)
-            .stderr(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stdin(Stdio::piped())
-            .spawn()
-            .map_err(ExecutionError::SpawnError)?;
+        let mut command = target_command(&self.wrapper, self.binary.as_ref());
 
-        {
-            let mut child_stdin = process.stdin.take().unwrap();
+        command
+            .stderr(Stdio::null())
+            .stdout(if self.stdin_feed.reads_stdout() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            });
+
+        isolate(&mut command);
+
+        if let Some(credentials) = &self.credentials {
+            credentials.apply(&mut command);
+        }
 
-            child_stdin
-                .write_all(&sample)
-                .map_err(ExecutionError::StdinError)?;
+        if let Some(dir) = &self.core_dir {
+            coredump::enable(&mut command, dir);
         }
 
-        let exec_result = process.wait_with_output().unwrap();
+        // input file has to outlive child
+        let _input_file = match self.pass_style {
+            PassStyleCfg::Stdin => {
+                command.stdin(Stdio::piped());
+                None
+            }
+            PassStyleCfg::File => {
+                let file =
+                    InputFile::create(sample.get_folded()).map_err(ExecutionError::StdinError)?;
 
-        let result = exec_result
-            .status
-            .code()
-            .map(ExecResult::Code)
-            .unwrap_or(ExecResult::Signal);
+                command.arg(file.path()).stdin(Stdio::null());
+
+                Some(file)
+            }
+        };
+
+        let mut process = command.spawn().map_err(ExecutionError::SpawnError)?;
+
+        let armed = self
+            .watchdog
+            .as_ref()
+            .map(|watchdog| watchdog.arm(process.id()));
+
+        let messages = message_starts(&sample, self.message_rule.as_deref());
+
+        let Pumped {
+            held_stdin,
+            response,
+            unsent,
+        } = ChildIo::new(
+            sample.get_folded(),
+            &messages,
+            &mut process,
+            self.stdin_feed,
+        )
+        .and_then(|io| io.pump(&AtomicBool::new(false)))
+        .map_err(ExecutionError::StdinError)?;
+
+        self.response = response;
+        self.input_cut_short = unsent > 0;
+
+        let status = process.wait().map_err(ExecutionError::SpawnError)?;
+        drop(held_stdin);
+
+        if armed.is_some_and(ArmedWatchdog::timed_out) {
+            self.timed_out = Some(sample);
+            return Err(ExecutionError::Timeout.into());
+        }
+
+        let result = match (status.code(), exit_signal(&status)) {
+            // crashed Windows processes exit with NTSTATUS error, eg. 0xC0000005
+            #[cfg(not(unix))]
+            (Some(code), _) if code as u32 >= 0xC000_0000 => ExecResult::Signal,
+            (Some(code), _) => ExecResult::Code(code),
+            (None, Some(signal)) => killed_by(signal, self.crash_signals.as_ref()),
+            (None, None) => ExecResult::Signal,
+        };
 
         Ok(TestedSample { sample, result })
     }
@@ -87,14 +623,41 @@ impl Evaluator for ExitCodeEvaluator {
     fn trace_detailed(&mut self, sample: Self::Item) -> Result<self::DetailedTrace, anyhow::Error> {
         Ok(vec![])
     }
+
+    fn stuck_points(&mut self) -> Vec<usize> {
+        self.watchdog
+            .as_ref()
+            .map(Watchdog::take_stuck)
+            .unwrap_or_default()
+    }
+
+    fn input_cut_short(&mut self) -> bool {
+        self.input_cut_short
+    }
+
+    fn take_timed_out(&mut self) -> Option<Self::Item> {
+        self.timed_out.take()
+    }
 }
 
+#[cfg(target_os = "linux")]
 pub struct FunctionTracer {
     binary: ElfInfo,
     pass_style: InputPassStyle,
+    watchdog: Option<Watchdog>,
+    /// functions without breakpoints and hit count reported for them instead
+    pruned: HashMap<usize, Hits>,
+    credentials: Option<Credentials>,
+    core_dir: Option<PathBuf>,
+    crash_signals: Option<HashSet<i32>>,
+    wrapper: Vec<String>,
+    stdin_feed: StdinFeed,
+    message_rule: Option<String>,
+    /// target of last execution closed stdin before reading whole input
+    input_cut_short: bool,
 }
 
-#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Hits {
     #[default]
     Once,
@@ -112,43 +675,80 @@ impl Hits {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+impl Display for Hits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Hits::Once => write!(f, "once"),
+            Hits::Twice => write!(f, "twice"),
+            Hits::Many => write!(f, "many times"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RunTrace {
     pub result: ExecResult,
     pub trajectory: HashMap<usize, Hits>,
+    /// class of responses target wrote to stdout, if they are read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<u64>,
 }
 
 pub type DetailedTrace = Vec<usize>;
 
 impl crate::sample_library::CoverageScore for RunTrace {
-    fn get_score(&self) -> f64 {
-        self.trajectory.len() as f64 + 0.1
+    fn covered_functions(&self) -> Vec<usize> {
+        self.trajectory.keys().copied().collect()
+    }
+}
+
+impl crate::sample_library::Footprint for RunTrace {
+    fn footprint(&self) -> usize {
+        // hash map stores control byte along with every slot
+        std::mem::size_of::<RunTrace>()
+            + self.trajectory.capacity() * (std::mem::size_of::<(usize, Hits)>() + 1)
     }
 }
 
 #[derive(Debug, thiserror::Error)]
+// only produced by tracing, which is not available outside of Linux
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
 pub enum TraceError {
+    #[cfg(target_os = "linux")]
     #[error(transparent)]
     Spawn(#[from] ptracer::TracerError),
 
     #[error("error accessing child process: {0}")]
     IO(#[from] std::io::Error),
 
+    #[error("error writing input to child: {0}")]
+    Stdin(std::io::Error),
+
+    #[cfg(target_os = "linux")]
     #[error("error working with breakpoints: {0}")]
     Nix(#[from] ptracer::nix::Error),
+
+    #[error("target timed out")]
+    Timeout,
+
+    #[error("wrapper did not execute target")]
+    Wrapper,
 }
 
+#[cfg(target_os = "linux")]
 fn determine_offset(child: &Child) -> std::io::Result<usize> {
     let pid = child.id();
     let maps = proc_maps::get_process_maps(pid as proc_maps::linux_maps::Pid)?;
     Ok(maps[0].start())
 }
 
+#[cfg(target_os = "linux")]
 pub enum InputPassStyle {
     File(Option<MemFile>),
     StdIn,
 }
 
+#[cfg(target_os = "linux")]
 impl FunctionTracer {
     pub fn new(binary: ElfInfo, pass_style: PassStyleCfg) -> Self {
         Self {
@@ -158,15 +758,30 @@ impl FunctionTracer {
             } else {
                 InputPassStyle::File(None)
             },
+            watchdog: None,
+            pruned: HashMap::new(),
+            credentials: None,
+            core_dir: None,
+            crash_signals: None,
+            wrapper: vec![],
+            stdin_feed: StdinFeed::default(),
+            message_rule: None,
+            input_cut_short: false,
         }
     }
 }
 
+/// wrapper executing other programs before target (eg. shell script) is given up after this many execs
+#[cfg(target_os = "linux")]
+const MAX_WRAPPER_EXECS: usize = 16;
+
 pub trait TraceRecorder: Default {
     /// add point to trace, indicate with bool if we want to get more of this point
     fn add_point(&mut self, point: usize) -> bool;
 
     fn add_exit(&mut self, exit: ExecResult);
+
+    fn add_response(&mut self, _response: u64) {}
 }
 
 impl TraceRecorder for RunTrace {
@@ -183,6 +798,10 @@ impl TraceRecorder for RunTrace {
     fn add_exit(&mut self, exit: ExecResult) {
         self.result = exit;
     }
+
+    fn add_response(&mut self, response: u64) {
+        self.response = Some(response);
+    }
 }
 
 impl Default for RunTrace {
@@ -190,6 +809,7 @@ impl Default for RunTrace {
         Self {
             result: ExecResult::Code(0),
             trajectory: Default::default(),
+            response: None,
         }
     }
 }
@@ -205,19 +825,33 @@ impl TraceRecorder for DetailedTrace {
     }
 }
 
+#[cfg(target_os = "linux")]
 impl FunctionTracer {
     fn set_breakpoints(&self, tracer: &mut Ptracer) -> Result<(), TraceError> {
         for function in &self.binary.functions {
+            if self.pruned.contains_key(&function.offset) {
+                continue;
+            }
+
             tracer.insert_breakpoint(self.binary.base_offset.unwrap() + function.offset)?;
         }
         Ok(())
     }
 
     fn make_command(&mut self, path: PathBuf) -> Command {
+        let mut command = target_command(&self.wrapper, path.as_os_str());
+        isolate(&mut command);
+
+        if let Some(credentials) = &self.credentials {
+            credentials.apply(&mut command);
+        }
+
+        if let Some(dir) = &self.core_dir {
+            coredump::enable(&mut command, dir);
+        }
+
         match &mut self.pass_style {
             InputPassStyle::StdIn => {
-                let mut command = Command::new(path);
-
                 command
                     .stdin(Stdio::piped())
                     .stdout(Stdio::piped())
@@ -225,8 +859,6 @@ impl FunctionTracer {
                 command
             }
             InputPassStyle::File(ref mut handle) => {
-                let mut command = Command::new(path);
-
                 let file =
                     Some(MemFile::create_default("stdin").expect("failure creating memfile"));
 
@@ -247,45 +879,49 @@ impl FunctionTracer {
         }
     }
 
-    fn pass_input(
-        &mut self,
-        tracer: &mut Ptracer,
-        input: &[u8],
-    ) -> Result<Option<MemFile>, std::io::Error> {
-        match &mut self.pass_style {
-            InputPassStyle::File(f) => {
-                let mut memfile = f.take().unwrap();
+    /// Continue traced wrapper until it executes target. Without exec
+    /// tracing option every exec stops tracee with SIGTRAP.
+    fn follow_exec(&self, tracer: &mut Ptracer) -> Result<(), TraceError> {
+        let target = absolute(&self.binary.path);
+        let pid = tracer.child().id();
 
-                memfile.write_all(input)?;
-                memfile.flush()?;
+        for _ in 0..MAX_WRAPPER_EXECS {
+            let executable = std::fs::read_link(format!("/proc/{pid}/exe"))?;
 
-                Ok(Some(memfile))
+            if executable == target {
+                return Ok(());
             }
-            InputPassStyle::StdIn => {
-                let mut stdin = tracer.child_mut().stdin.take().unwrap();
 
-                stdin.write_all(input)?;
-                stdin.flush()?;
+            tracer.cont(ptracer::ContinueMode::Default)?;
 
-                Ok(None)
+            if matches!(
+                tracer.event(),
+                WaitStatus::Exited(..) | WaitStatus::Signaled(..)
+            ) {
+                return Err(TraceError::Wrapper);
             }
         }
+
+        Err(TraceError::Wrapper)
     }
 
-    pub fn run<R: TraceRecorder>(&mut self, input: &[u8]) -> Result<R, TraceError> {
-        let path = self.binary.path.clone();
-        let cmd = self.make_command(path);
+    /// write input file, stdin is fed by `ChildIo` while target runs
+    fn pass_input(&mut self, input: &[u8]) -> Result<Option<MemFile>, std::io::Error> {
+        match &mut self.pass_style {
+            InputPassStyle::File(f) => {
+                let mut memfile = f.take().unwrap();
 
-        let mut tracer = Ptracer::spawn(cmd, None)?;
+                memfile.write_all(input)?;
+                memfile.flush()?;
 
-        if self.binary.base_offset.is_none() {
-            self.binary.base_offset = Some(determine_offset(tracer.child())?);
+                Ok(Some(memfile))
+            }
+            InputPassStyle::StdIn => Ok(None),
         }
+    }
 
-        self.set_breakpoints(&mut tracer)?;
-
-        let _maybe_needs_hold = self.pass_input(&mut tracer, input)?;
-
+    /// continue child until it exits, recording breakpoints it hits
+    fn follow<R: TraceRecorder>(&self, tracer: &mut Ptracer) -> R {
         let mut trajectory: R = R::default();
 
         while tracer.cont(ptracer::ContinueMode::Default).is_ok() {
@@ -293,8 +929,8 @@ impl FunctionTracer {
                 WaitStatus::Exited(_pid, code) => {
                     trajectory.add_exit(ExecResult::Code(*code));
                 }
-                WaitStatus::Signaled(_pid, _signal, _coredump) => {
-                    trajectory.add_exit(ExecResult::Signal);
+                WaitStatus::Signaled(_pid, signal, _coredump) => {
+                    trajectory.add_exit(killed_by(*signal as i32, self.crash_signals.as_ref()));
                 }
                 e => {}
             }
@@ -309,22 +945,270 @@ impl FunctionTracer {
             }
         }
 
-        Ok(trajectory)
+        trajectory
+    }
+
+    pub fn run<R: TraceRecorder>(
+        &mut self,
+        sample: &crate::sample::Sample,
+    ) -> Result<R, TraceError> {
+        match self.run_partial(sample)? {
+            (_trajectory, true) => Err(TraceError::Timeout),
+            (trajectory, false) => Ok(trajectory),
+        }
+    }
+
+    /// Run target keeping trajectory recorded before timeout, returns
+    /// whether target was killed by watchdog along with it.
+    fn run_partial<R: TraceRecorder>(
+        &mut self,
+        sample: &crate::sample::Sample,
+    ) -> Result<(R, bool), TraceError> {
+        let input = sample.get_folded();
+        let messages = message_starts(sample, self.message_rule.as_deref());
+
+        let path = self.binary.path.clone();
+        let cmd = self.make_command(path);
+
+        let mut tracer = Ptracer::spawn(cmd, None)?;
+
+        let armed = self
+            .watchdog
+            .as_ref()
+            .map(|watchdog| watchdog.arm(tracer.child().id()));
+
+        if !self.wrapper.is_empty() {
+            self.follow_exec(&mut tracer)?;
+        }
+
+        if self.binary.base_offset.is_none() {
+            self.binary.base_offset = Some(determine_offset(tracer.child())?);
+        }
+
+        self.set_breakpoints(&mut tracer)?;
+
+        let _maybe_needs_hold = self.pass_input(input)?;
+
+        let io = ChildIo::new(input, &messages, tracer.child_mut(), self.stdin_feed)?;
+        let child_exited = AtomicBool::new(false);
+
+        // ptrace requests have to come from this thread, so streams are served by another one
+        let (mut trajectory, pumped) = thread::scope(|scope| {
+            let stop = &child_exited;
+            let pump = scope.spawn(move || io.pump(stop));
+
+            let trajectory = self.follow::<R>(&mut tracer);
+            child_exited.store(true, Ordering::SeqCst);
+
+            (trajectory, pump.join().expect("child I/O thread panicked"))
+        });
+
+        let pumped = pumped.map_err(TraceError::Stdin)?;
+
+        if let Some(response) = pumped.response {
+            trajectory.add_response(response);
+        }
+        self.input_cut_short = pumped.unsent > 0;
+
+        Ok((trajectory, armed.is_some_and(ArmedWatchdog::timed_out)))
+    }
+}
+
+/// Hits of functions during warmup executions, used to find functions that
+/// are hit the same way by nearly every input.
+pub struct HitProfile {
+    warmup: usize,
+    threshold: f64,
+    runs: usize,
+    /// number of runs hitting function and its hit count, `None` if it varied
+    hits: HashMap<usize, (usize, Option<Hits>)>,
+}
+
+impl HitProfile {
+    pub fn new(warmup: usize, threshold: f64) -> Self {
+        HitProfile {
+            warmup,
+            threshold,
+            runs: 0,
+            hits: HashMap::new(),
+        }
+    }
+
+    fn record(&mut self, trace: &RunTrace) {
+        self.runs += 1;
+
+        for (&point, &hits) in &trace.trajectory {
+            let (count, seen) = self.hits.entry(point).or_insert((0, Some(hits)));
+
+            *count += 1;
+            if *seen != Some(hits) {
+                *seen = None;
+            }
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.runs >= self.warmup
+    }
+
+    /// functions hit by at least `threshold` of runs with the same hit count
+    fn hot_functions(&self) -> HashMap<usize, Hits> {
+        let required = self.threshold * self.runs as f64;
+
+        self.hits
+            .iter()
+            .filter(|(_, (count, _))| *count as f64 >= required)
+            .filter_map(|(&point, &(_, hits))| Some((point, hits?)))
+            .collect()
     }
 }
 
+#[cfg(target_os = "linux")]
 pub struct TraceEvaluator {
     tracer: FunctionTracer,
+    profile: Option<HitProfile>,
+    /// functions were pruned since it was last reported
+    pruned_since_taken: bool,
+    /// sample last execution timed out on
+    timed_out: Option<crate::sample::Sample>,
 }
 
+#[cfg(target_os = "linux")]
 impl TraceEvaluator {
     pub fn new(info: ElfInfo, pass_style: PassStyleCfg) -> Self {
         Self {
             tracer: FunctionTracer::new(info, pass_style),
+            profile: None,
+            pruned_since_taken: false,
+            timed_out: None,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.tracer.watchdog = timeout.map(Watchdog::new);
+        self
+    }
+
+    /// sample program counter of target timing out before it is killed, needs timeout set
+    pub fn with_stuck_samples(self, samples: usize) -> Self {
+        if let Some(watchdog) = &self.tracer.watchdog {
+            watchdog.set_stuck_samples(samples);
+        }
+        self
+    }
+
+    /// count target as crashed only when killed by one of given signals, by any signal if not set
+    pub fn with_crash_signals(mut self, crash_signals: Option<HashSet<i32>>) -> Self {
+        self.tracer.crash_signals = crash_signals;
+        self
+    }
+
+    /// Run target through wrapper command that executes it (eg. `taskset -c 3`),
+    /// tracing starts once wrapper executes target.
+    pub fn with_wrapper(mut self, wrapper: Vec<String>) -> Self {
+        self.tracer.wrapper = wrapper;
+        self
+    }
+
+    /// whether stdin is closed after input is written and whether input is written in chunks
+    pub fn with_stdin_feed(mut self, stdin_feed: StdinFeed) -> Self {
+        self.tracer.stdin_feed = stdin_feed;
+        self
+    }
+
+    /// write every application of grammar rule in sample to stdin separately
+    pub fn with_message_rule(mut self, message_rule: Option<String>) -> Self {
+        self.tracer.message_rule = message_rule;
+        self
+    }
+
+    /// run target as given user instead of user running fuzzer
+    pub fn with_credentials(mut self, credentials: Option<Credentials>) -> Self {
+        self.tracer.credentials = credentials;
+        self
+    }
+
+    /// run target inside given directory with core dumps enabled
+    pub fn with_core_dumps(mut self, core_dir: Option<PathBuf>) -> Self {
+        if core_dir.is_some() {
+            self.tracer.binary.path = absolute(&self.tracer.binary.path);
+        }
+
+        self.tracer.core_dir = core_dir;
+        self
+    }
+
+    /// stop tracing hot functions once profile is complete
+    pub fn with_pruning(mut self, profile: Option<HitProfile>) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Names of last `count` distinct functions hit before target timed out
+    /// on sample, most recent last. None if target finished in time.
+    pub fn stuck_functions(
+        &mut self,
+        sample: &crate::sample::Sample,
+        count: usize,
+    ) -> Result<Option<Vec<String>>, anyhow::Error> {
+        let (trace, timed_out) = self.tracer.run_partial::<DetailedTrace>(sample)?;
+
+        if !timed_out {
+            return Ok(None);
         }
+
+        let mut last = Vec::with_capacity(count);
+
+        for point in trace.iter().rev() {
+            if last.len() == count {
+                break;
+            }
+
+            if !last.contains(point) {
+                last.push(*point);
+            }
+        }
+
+        let functions = &self.tracer.binary.functions;
+
+        Ok(Some(
+            last.into_iter()
+                .rev()
+                .map(|point| {
+                    functions
+                        .iter()
+                        .find(|function| function.offset == point)
+                        .map_or_else(|| format!("{point:#x}"), |function| function.name.clone())
+                })
+                .collect(),
+        ))
+    }
+
+    fn prune(&mut self, profile: &HitProfile) {
+        self.tracer.pruned = profile.hot_functions();
+        self.pruned_since_taken = true;
+
+        let functions = &self.tracer.binary.functions;
+
+        crate::log!(
+            "pruned {} functions hit by nearly every execution, {} of {} stay instrumented",
+            self.tracer.pruned.len(),
+            functions.len() - self.tracer.pruned.len(),
+            functions.len()
+        );
+
+        let mut names = functions
+            .iter()
+            .filter(|function| self.tracer.pruned.contains_key(&function.offset))
+            .map(|function| function.name.as_str())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+
+        crate::log!(debug: "pruned functions: {}", names.join(", "));
     }
 }
 
+#[cfg(target_os = "linux")]
 impl Evaluator for TraceEvaluator {
     type Item = crate::sample::Sample;
 
@@ -334,14 +1218,230 @@ impl Evaluator for TraceEvaluator {
         &mut self,
         sample: Self::Item,
     ) -> Result<TestedSample<Self::Item, Self::EvalResult>, anyhow::Error> {
-        let result = self.tracer.run::<RunTrace>(sample.get_folded())?;
+        let mut result = match self.tracer.run::<RunTrace>(&sample) {
+            Ok(result) => result,
+            Err(TraceError::Timeout) => {
+                self.timed_out = Some(sample);
+                return Err(TraceError::Timeout.into());
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        if let Some(mut profile) = self.profile.take() {
+            profile.record(&result);
+
+            if profile.is_complete() {
+                self.prune(&profile);
+            } else {
+                self.profile = Some(profile);
+            }
+        }
+
+        result.trajectory.extend(&self.tracer.pruned);
 
         Ok(TestedSample { sample, result })
     }
 
     fn trace_detailed(&mut self, sample: Self::Item) -> Result<self::DetailedTrace, anyhow::Error> {
         self.tracer
-            .run::<DetailedTrace>(sample.get_folded())
+            .run::<DetailedTrace>(&sample)
             .map_err(|e| e.into())
     }
+
+    fn stuck_points(&mut self) -> Vec<usize> {
+        self.tracer
+            .watchdog
+            .as_ref()
+            .map(Watchdog::take_stuck)
+            .unwrap_or_default()
+    }
+
+    fn take_instrumentation_change(&mut self) -> bool {
+        std::mem::take(&mut self.pruned_since_taken)
+    }
+
+    fn input_cut_short(&mut self) -> bool {
+        self.tracer.input_cut_short
+    }
+
+    fn take_timed_out(&mut self) -> Option<Self::Item> {
+        self.timed_out.take()
+    }
+}
+
+/// Exit status of target without any coverage, for targets that are too
+/// big or can not be traced with ptrace.
+pub struct UntracedEvaluator {
+    runner: ExitCodeEvaluator,
+}
+
+impl UntracedEvaluator {
+    pub fn new(binary: String, pass_style: PassStyleCfg) -> Self {
+        Self {
+            runner: ExitCodeEvaluator::new(binary, pass_style),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.runner = self.runner.with_timeout(timeout);
+        self
+    }
+
+    /// sample program counter of target timing out before it is killed, needs timeout set
+    pub fn with_stuck_samples(mut self, samples: usize) -> Self {
+        self.runner = self.runner.with_stuck_samples(samples);
+        self
+    }
+
+    /// count target as crashed only when killed by one of given signals, by any signal if not set
+    pub fn with_crash_signals(mut self, crash_signals: Option<HashSet<i32>>) -> Self {
+        self.runner = self.runner.with_crash_signals(crash_signals);
+        self
+    }
+
+    /// run target through wrapper command (eg. `taskset -c 3`, `qemu-aarch64`)
+    pub fn with_wrapper(mut self, wrapper: Vec<String>) -> Self {
+        self.runner = self.runner.with_wrapper(wrapper);
+        self
+    }
+
+    /// whether stdin is closed after input is written and whether input is written in chunks
+    pub fn with_stdin_feed(mut self, stdin_feed: StdinFeed) -> Self {
+        self.runner = self.runner.with_stdin_feed(stdin_feed);
+        self
+    }
+
+    /// write every application of grammar rule in sample to stdin separately
+    pub fn with_message_rule(mut self, message_rule: Option<String>) -> Self {
+        self.runner = self.runner.with_message_rule(message_rule);
+        self
+    }
+
+    /// run target as given user instead of user running fuzzer
+    pub fn with_credentials(mut self, credentials: Option<Credentials>) -> Self {
+        self.runner = self.runner.with_credentials(credentials);
+        self
+    }
+
+    /// run target inside given directory with core dumps enabled
+    pub fn with_core_dumps(mut self, core_dir: Option<PathBuf>) -> Self {
+        self.runner = self.runner.with_core_dumps(core_dir);
+        self
+    }
+}
+
+impl Evaluator for UntracedEvaluator {
+    type Item = crate::sample::Sample;
+
+    type EvalResult = RunTrace;
+
+    fn score(
+        &mut self,
+        sample: Self::Item,
+    ) -> Result<TestedSample<Self::Item, Self::EvalResult>, anyhow::Error> {
+        let TestedSample { sample, result } = self.runner.score(sample)?;
+
+        Ok(TestedSample {
+            sample,
+            result: RunTrace {
+                result,
+                trajectory: HashMap::new(),
+                response: self.runner.take_response(),
+            },
+        })
+    }
+
+    fn trace_detailed(
+        &mut self,
+        _sample: Self::Item,
+    ) -> Result<self::DetailedTrace, anyhow::Error> {
+        Ok(vec![])
+    }
+
+    fn stuck_points(&mut self) -> Vec<usize> {
+        self.runner.stuck_points()
+    }
+
+    fn input_cut_short(&mut self) -> bool {
+        self.runner.input_cut_short()
+    }
+
+    fn take_timed_out(&mut self) -> Option<Self::Item> {
+        self.runner.take_timed_out()
+    }
+}
+
+/// Coverage of one build combined with crash verdict of another, usually
+/// built with sanitizers. Sample is considered crashing if either build
+/// crashed or detector exited with one of crash codes.
+pub struct MergedEvaluator<Coverage, Detector> {
+    coverage: Coverage,
+    detector: Detector,
+    crash_codes: HashSet<i32>,
+}
+
+impl<Coverage, Detector> MergedEvaluator<Coverage, Detector> {
+    pub fn new(coverage: Coverage, detector: Detector, crash_codes: HashSet<i32>) -> Self {
+        Self {
+            coverage,
+            detector,
+            crash_codes,
+        }
+    }
+}
+
+impl<Coverage, Detector> Evaluator for MergedEvaluator<Coverage, Detector>
+where
+    Coverage: Evaluator<Item = crate::sample::Sample, EvalResult = RunTrace>,
+    Detector: Evaluator<Item = crate::sample::Sample, EvalResult = ExecResult>,
+{
+    type Item = crate::sample::Sample;
+
+    type EvalResult = RunTrace;
+
+    fn score(
+        &mut self,
+        sample: Self::Item,
+    ) -> Result<TestedSample<Self::Item, Self::EvalResult>, anyhow::Error> {
+        let TestedSample {
+            sample,
+            result: verdict,
+        } = self.detector.score(sample)?;
+
+        let mut tested = self.coverage.score(sample)?;
+
+        let detected = match verdict {
+            ExecResult::Signal => true,
+            ExecResult::Code(code) => self.crash_codes.contains(&code),
+        };
+
+        if detected {
+            tested.result.result = ExecResult::Signal;
+        }
+
+        Ok(tested)
+    }
+
+    fn trace_detailed(&mut self, sample: Self::Item) -> Result<self::DetailedTrace, anyhow::Error> {
+        self.coverage.trace_detailed(sample)
+    }
+
+    /// only coverage build is symbolized, detector runs another executable
+    fn stuck_points(&mut self) -> Vec<usize> {
+        self.coverage.stuck_points()
+    }
+
+    fn take_instrumentation_change(&mut self) -> bool {
+        self.coverage.take_instrumentation_change()
+    }
+
+    fn input_cut_short(&mut self) -> bool {
+        self.coverage.input_cut_short() || self.detector.input_cut_short()
+    }
+
+    fn take_timed_out(&mut self) -> Option<Self::Item> {
+        self.coverage
+            .take_timed_out()
+            .or_else(|| self.detector.take_timed_out())
+    }
 }
@@ -0,0 +1,197 @@
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+    process,
+};
+
+use itertools::Itertools;
+
+use crate::configuration::{FuzzConfig, PassStyle};
+
+/// Crash files are named by `fuzz_thread::get_unique_name`, which only uses hex digits.
+fn is_crash_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// crash files saved in directory, in name order
+pub fn list_crashes(directory: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut crashes = vec![];
+
+    for entry in std::fs::read_dir(directory)? {
+        let entry = entry?;
+
+        let is_crash = entry.file_type()?.is_file()
+            && entry
+                .file_name()
+                .to_str()
+                .map(is_crash_name)
+                .unwrap_or(false);
+
+        if is_crash {
+            crashes.push(entry.path());
+        }
+    }
+
+    crashes.sort();
+    Ok(crashes)
+}
+
+fn run_target_stdin(target: &str) -> String {
+    format!(
+        r#"fn run_target(input: &[u8]) {{
+    let mut child = Command::new({target:?})
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to start target");
+
+    // target may exit before reading whole input
+    let _ = child.stdin.take().unwrap().write_all(input);
+
+    check_status(child.wait().expect("failed to wait for target"));
+}}
+"#
+    )
+}
+
+fn run_target_file(target: &str) -> String {
+    format!(
+        r#"fn run_target(input: &[u8]) {{
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let path = std::env::temp_dir().join(format!(
+        "bocchi-regression-{{}}-{{}}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    std::fs::write(&path, input).expect("failed to write input file");
+
+    let status = Command::new({target:?})
+        .arg(&path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .expect("failed to start target");
+
+    let _ = std::fs::remove_file(&path);
+
+    check_status(status);
+}}
+"#
+    )
+}
+
+/// Render crashes as rust test module running target on every crash input
+/// and asserting it is not killed by a signal.
+fn render_tests(config: &FuzzConfig, crashes: &[(String, Vec<u8>)]) -> String {
+    let mut out = String::new();
+
+    writeln!(
+        out,
+        "//! Regression tests generated by bocchifuzz from crashes of `{}`.",
+        config.binary.path
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "//! Adjust `run_target` if target is built or invoked differently in your project.\n"
+    )
+    .unwrap();
+
+    // imports needed by one of `run_target` variants, kept in sorted order
+    let (first_import, last_import) = match config.binary.pass_style {
+        PassStyle::Stdin => ("\n    io::Write,", ""),
+        PassStyle::File => ("", "\n    sync::atomic::{AtomicUsize, Ordering},"),
+    };
+
+    writeln!(
+        out,
+        "use std::{{{first_import}\n    os::unix::process::ExitStatusExt,\n    process::{{Command, ExitStatus, Stdio}},{last_import}\n}};\n"
+    )
+    .unwrap();
+
+    out.push_str(
+        r#"fn check_status(status: ExitStatus) {
+    assert!(
+        status.signal().is_none(),
+        "target was killed by signal {:?}",
+        status.signal()
+    );
+}
+
+"#,
+    );
+
+    out.push_str(&match config.binary.pass_style {
+        PassStyle::Stdin => run_target_stdin(&config.binary.path),
+        PassStyle::File => run_target_file(&config.binary.path),
+    });
+
+    for (name, content) in crashes {
+        write!(
+            out,
+            "\n#[test]\nfn crash_{name}() {{\n    run_target(b\"{}\");\n}}\n",
+            content.escape_ascii()
+        )
+        .unwrap();
+    }
+
+    out
+}
+
+/// Write selected crashes (every crash if none selected) as rust regression tests.
+pub fn export_tests(config: &FuzzConfig, names: &[String], file: &Path) {
+    let directory = Path::new(&config.output.directory);
+
+    let available = match list_crashes(directory) {
+        Ok(crashes) => crashes,
+        Err(e) => {
+            eprintln!("failed to list crashes in {}: {e}", directory.display());
+            process::exit(exitcode::IOERR)
+        }
+    };
+
+    let selected = if names.is_empty() {
+        available
+    } else {
+        let missing = names
+            .iter()
+            .filter(|name| !available.iter().any(|path| path.ends_with(name)))
+            .collect_vec();
+
+        if !missing.is_empty() {
+            eprintln!(
+                "crashes not found in {}: {}",
+                directory.display(),
+                missing.iter().join(", ")
+            );
+            process::exit(exitcode::NOINPUT)
+        }
+
+        names.iter().map(|name| directory.join(name)).collect()
+    };
+
+    let mut crashes = vec![];
+
+    for path in selected {
+        match std::fs::read(&path) {
+            Ok(content) => crashes.push((
+                path.file_name().unwrap().to_string_lossy().to_string(),
+                content,
+            )),
+            Err(e) => {
+                eprintln!("failed to read {}: {e}", path.display());
+                process::exit(exitcode::IOERR)
+            }
+        }
+    }
+
+    if let Err(e) = std::fs::write(file, render_tests(config, &crashes)) {
+        eprintln!("failed to write {}: {e}", file.display());
+        process::exit(exitcode::IOERR)
+    }
+
+    println!("exported {} crash(es) to {}", crashes.len(), file.display());
+}
@@ -1,39 +1,79 @@
 use std::{
-    io::Write,
-    path::PathBuf,
+    path::{Path, PathBuf},
     process,
+    sync::{mpsc, Arc},
     thread::{self, JoinHandle},
-    time::{Instant, SystemTime},
+    time::{Duration, Instant},
 };
 
 use anyhow::{anyhow, Context};
+use chrono::Local;
+use itertools::Itertools;
 use rand::Rng;
-use ringbuffer::RingBufferWrite;
 
 use crate::{
-    analysys,
-    configuration::FuzzConfig,
-    execution::{self},
-    fuzzing::Fuzzer,
+    analysys::{self, ElfInfo},
+    campaign::{self, StateFiles},
+    configuration::{EvaluatorKind, FuzzConfig},
+    confirm::Confirmer,
+    coredump,
+    execution::{self, RunTrace},
+    fuzzing::{Evaluator, Fuzzer, Reproduction, RunResult},
     grammar::Grammar,
-    log::{log, FuzzingEvent, FuzzingEventKind, NewPathKind},
+    hit_stats::HitStats,
+    libafl,
+    log::{log, EventLog, FuzzingEventKind, HitChange, NewPathKind, SampleInfo},
     mutation::build_mutator,
-    sample::{TreeNode, TreeNodeItem},
+    provenance::{self, Lineage, Step},
+    sample::{Sample, TreeNode, TreeNodeItem},
     sample_library::Library as LibT,
-    state::{Library, State, AM, FUZZER_RUNNNIG},
+    session::{self, TargetWatch},
+    snapshot,
+    state::{ErrorBudget, FuzzerStatus, Library, ShutdownToken, State, Throttler, AM},
+    storage::write_atomic,
+    triage::Triager,
 };
 
+/// Fuzzing runs in single thread executing target one sample at a time.
+const WORKERS: usize = 1;
+
+/// Describe setup of campaign in messages, so that its logs and
+/// screenshots tell what was fuzzed and how.
+fn log_banner(config: &FuzzConfig, functions_total: usize) {
+    log!(
+        "bocchifuzz {} fuzzing {}",
+        env!("CARGO_PKG_VERSION"),
+        config.binary.path
+    );
+
+    log!(
+        "{} evaluator, {functions_total} instrumented functions, {} pass style, {WORKERS} worker(s)",
+        config.binary.evaluator.name(),
+        config.binary.pass_style.name()
+    );
+
+    match serde_json::to_string(config) {
+        Ok(config) => log!("effective configuration: {config}"),
+        Err(e) => log!(warning: "failed to serialize configuration: {e}"),
+    }
+}
+
 fn get_unique_name() -> String {
     let mut rng = rand::thread_rng();
 
     (0..8).map(|_| format!("{:x}", rng.gen::<u8>())).collect()
 }
 
-fn get_crash_path(config: &'static FuzzConfig, name: &str) -> PathBuf {
+fn get_crash_path(config: &FuzzConfig, name: &str) -> PathBuf {
     PathBuf::from(&config.output.directory).join(name)
 }
 
-fn save_crash(sample: &crate::sample::Sample, path: PathBuf) -> Result<(), std::io::Error> {
+/// save crashing sample, with derivation tree if `with_structure` is set
+fn save_crash(
+    sample: &crate::sample::Sample,
+    path: PathBuf,
+    with_structure: bool,
+) -> Result<(), std::io::Error> {
     let dir = {
         let mut path = path.clone();
 
@@ -45,29 +85,1046 @@ fn save_crash(sample: &crate::sample::Sample, path: PathBuf) -> Result<(), std::
     if !dir.exists() {
         std::fs::create_dir_all(dir)?;
     }
-    std::fs::write(path, sample.get_folded())
+    write_atomic(&path, sample.get_folded())?;
+
+    if with_structure {
+        sample.save_structure(&path)?;
+    }
+
+    Ok(())
+}
+
+/// Keep core dumped by crash next to it, replacing core of previous sample.
+fn attach_core(
+    log: &mut EventLog,
+    core: &Path,
+    name: &str,
+    crash_path: &Path,
+) -> Result<(), anyhow::Error> {
+    let path = coredump::crash_core_path(crash_path);
+
+    if let Err(e) = std::fs::rename(core, &path) {
+        log!(warning: "failed to save core dump of crash {name}: {e}");
+        return Ok(());
+    }
+
+    log!("saved core dump of crash {name} as {}", path.display());
+
+    record_event(
+        log,
+        FuzzingEventKind::CoreDump {
+            trace_id: name.to_string(),
+            path: path.to_string_lossy().to_string(),
+        },
+    )
+}
+
+fn sample_info(library: &Library, result: &RunResult) -> SampleInfo {
+    SampleInfo {
+        sample_size: result.sample.get_folded().len(),
+        trajectory_size: result.trace.trajectory.len(),
+        parent: result
+            .parent
+            .as_ref()
+            .and_then(|parent| library.find_existing(parent))
+            .and_then(|(entry, _sample)| entry.unique_name.clone()),
+    }
+}
+
+/// Functions hit by sample of run different number of times than by
+/// sample it was mutated from, none for samples that were not mutated.
+fn hit_changes(result: &RunResult, hit_stats: &HitStats) -> Vec<HitChange> {
+    // parent key holds its trajectory even if parent was evicted since
+    let Some(parent) = &result.parent else {
+        return vec![];
+    };
+
+    result
+        .trace
+        .trajectory
+        .iter()
+        .filter(|(function, hits)| parent.trajectory.get(function) != Some(hits))
+        .sorted_by_key(|(function, _hits)| **function)
+        .map(|(function, hits)| HitChange {
+            function: hit_stats.function_name(*function),
+            before: parent.trajectory.get(function).copied(),
+            after: *hits,
+        })
+        .collect()
+}
+
+/// Describe what new path changed in coverage, so that reaching new code
+/// is told apart from another hit count of known one. None if coverage is
+/// the same (path differs by exit status or response).
+fn describe_new_path(new_functions: &[String], changes: &[HitChange]) -> Option<String> {
+    const SHOWN: usize = 5;
+
+    let (what, items) = if !new_functions.is_empty() {
+        ("covered", new_functions.to_vec())
+    } else if !changes.is_empty() {
+        let changes = changes
+            .iter()
+            .map(|change| {
+                let before = change
+                    .before
+                    .map_or_else(|| "not hit".to_string(), |before| before.to_string());
+                format!("{} ({before} -> {})", change.function, change.after)
+            })
+            .collect_vec();
+
+        ("hit counts changed in", changes)
+    } else {
+        return None;
+    };
+
+    let more = match items.len().saturating_sub(SHOWN) {
+        0 => String::new(),
+        more => format!(" and {more} more"),
+    };
+
+    Some(format!(
+        "{what} {}{more}",
+        items.iter().take(SHOWN).join(", ")
+    ))
+}
+
+/// mutation that produced sample of run, none for samples that were not mutated
+fn mutation_step(library: &Library, result: &RunResult) -> Option<Step> {
+    Some(Step {
+        mutation: result.mutation?,
+        level: result.level.unwrap_or_default(),
+        parent_hash: format!("{:016x}", result.parent_hash?),
+        parent: sample_info(library, result).parent,
+    })
+}
+
+/// Keep mutations that produced crash next to it.
+fn write_provenance(lineage: &Lineage, name: &str, path: &Path, minimized_from: Option<usize>) {
+    if let Err(e) = provenance::save_provenance(path, lineage, name, minimized_from) {
+        log!(warning: "failed to save provenance of crash {name}: {e}");
+    }
+}
+
+fn record_event(log: &mut EventLog, event: FuzzingEventKind) -> Result<(), anyhow::Error> {
+    log.record(event).map_err(|e| {
+        let message = format!("error writing to log file: {e}");
+        log!(error: "{}", message);
+        anyhow!(message)
+    })
+}
+
+/// export library in LibAFL layout, returns number of exported testcases
+fn export_libafl_corpus(config: &FuzzConfig, library: &Library) -> Option<usize> {
+    let directory = PathBuf::from(&config.output.directory).join(
+        config
+            .output
+            .libafl_corpus
+            .as_deref()
+            .unwrap_or(libafl::DEFAULT_EXPORT_DIR),
+    );
+
+    libafl::export_corpus(library, &directory)
+        .map_err(|e| log!(warning: "failed to export corpus to {}: {e}", directory.display()))
+        .ok()
+}
+
+type DynEvaluator = Box<dyn Evaluator<Item = Sample, EvalResult = RunTrace>>;
+
+/// name of sample written to output directory by self-test
+const SELF_TEST_SAMPLE: &str = ".self-test";
+
+/// run sample for self-test, None if it timed out
+fn run_self_test(
+    config: &FuzzConfig,
+    evaluator: &mut DynEvaluator,
+    seed: &Sample,
+) -> Result<Option<RunTrace>, anyhow::Error> {
+    match evaluator.score(seed.clone()) {
+        Ok(tested) => Ok(Some(tested.result)),
+        Err(e) if execution::is_timeout(&e) => {
+            log!(warning: "self-test: first seed timed out, consider raising `binary.timeout`");
+            Ok(None)
+        }
+        Err(e) => Err(e.context(format!(
+            "self-test: failed to run {} on first seed, check `binary.path` and `binary.pass_style`",
+            config.binary.path
+        ))),
+    }
+}
+
+/// Check execution pipeline before fuzzing: first seed is run twice to see
+/// that target is traced and behaves the same both times, then it is saved
+/// the way crashes are to make sure they can be written to output directory.
+fn self_test(
+    config: &FuzzConfig,
+    evaluator: &mut DynEvaluator,
+    seed: &Sample,
+    grammar_mode: bool,
+) -> Result<(), anyhow::Error> {
+    if let Some(first) = run_self_test(config, evaluator, seed)? {
+        if config.binary.evaluator == EvaluatorKind::Trace && first.trajectory.is_empty() {
+            return Err(anyhow!(
+                "self-test: no function of {} was hit on first seed, make sure `binary.path` is the program itself and not a script launching it",
+                config.binary.path
+            ));
+        }
+
+        if let execution::ExecResult::Signal = first.result {
+            log!(warning: "self-test: first seed already crashes target");
+        }
+
+        if let Some(second) = run_self_test(config, evaluator, seed)? {
+            if second.result != first.result {
+                log!(
+                    warning: "self-test: target returned {} and then {} on the same seed, it is not deterministic and some new paths will be spurious",
+                    first.result,
+                    second.result
+                );
+            } else if second.trajectory.len() != first.trajectory.len()
+                || first
+                    .trajectory
+                    .keys()
+                    .any(|function| !second.trajectory.contains_key(function))
+            {
+                log!(
+                    warning: "self-test: target hit {} and then {} functions on the same seed, it is not deterministic and some new paths will be spurious",
+                    first.trajectory.len(),
+                    second.trajectory.len()
+                );
+            }
+        }
+    }
+
+    let probe = get_crash_path(config, SELF_TEST_SAMPLE);
+
+    save_crash(seed, probe.clone(), grammar_mode).with_context(|| {
+        format!(
+            "self-test: failed to save sample in `{}`, check permissions of `output.directory`",
+            config.output.directory
+        )
+    })?;
+
+    let _ = std::fs::remove_file(&probe);
+    if grammar_mode {
+        let _ = std::fs::remove_file(crate::sample::structure_path(&probe));
+    }
+
+    log!(debug: "self-test passed");
+
+    Ok(())
+}
+
+/// Tracing evaluator configured for target, `mapping` is its analysis.
+#[cfg(target_os = "linux")]
+pub fn trace_evaluator(
+    config: &FuzzConfig,
+    grammar: &Grammar,
+    mapping: ElfInfo,
+) -> execution::TraceEvaluator {
+    execution::TraceEvaluator::new(mapping, config.binary.pass_style)
+        .with_timeout(config.binary.timeout.map(Duration::from_millis))
+        .with_stuck_samples(config.binary.stuck_samples)
+        .with_crash_signals(config.binary.crash_signals())
+        .with_wrapper(config.binary.wrapper.clone())
+        .with_stdin_feed(config.binary.stdin_feed())
+        .with_message_rule(grammar.message_rule())
+        .with_credentials(config.binary.credentials())
+        .with_core_dumps(
+            config
+                .output
+                .core_dumps
+                .then(|| coredump::core_dir(&config.output.directory)),
+        )
+        .with_pruning(
+            config
+                .binary
+                .prune_warmup
+                .map(|warmup| execution::HitProfile::new(warmup, config.binary.prune_threshold)),
+        )
+}
+
+/// Build evaluator configured for target, `mapping` is analysis of traced target.
+fn build_evaluator(
+    config: &FuzzConfig,
+    grammar: &Grammar,
+    mapping: Option<ElfInfo>,
+) -> DynEvaluator {
+    let timeout = config.binary.timeout.map(Duration::from_millis);
+    let credentials = config.binary.credentials();
+    let crash_signals = config.binary.crash_signals();
+    let core_dir = config
+        .output
+        .core_dumps
+        .then(|| coredump::core_dir(&config.output.directory));
+
+    let scorer: DynEvaluator = match mapping {
+        #[cfg(not(target_os = "linux"))]
+        Some(_mapping) => unreachable!("tracing is rejected when configuration is checked"),
+        #[cfg(target_os = "linux")]
+        Some(mapping) => Box::new(trace_evaluator(config, grammar, mapping)),
+        None => Box::new(
+            execution::UntracedEvaluator::new(config.binary.path.clone(), config.binary.pass_style)
+                .with_timeout(timeout)
+                .with_stuck_samples(config.binary.stuck_samples)
+                .with_crash_signals(crash_signals.clone())
+                .with_wrapper(config.binary.wrapper.clone())
+                .with_stdin_feed(config.binary.stdin_feed())
+                .with_message_rule(grammar.message_rule())
+                .with_credentials(credentials)
+                .with_core_dumps(core_dir.clone()),
+        ),
+    };
+
+    match &config.binary.detector {
+        Some(detector) => Box::new(execution::MergedEvaluator::new(
+            scorer,
+            execution::ExitCodeEvaluator::new(detector.path.clone(), config.binary.pass_style)
+                .with_timeout(timeout)
+                .with_crash_signals(crash_signals)
+                .with_wrapper(config.binary.wrapper.clone())
+                .with_stdin_feed(config.binary.stdin_feed())
+                .with_message_rule(grammar.message_rule())
+                .with_credentials(credentials)
+                .with_core_dumps(core_dir),
+            detector.crash_codes.clone(),
+        )),
+        None => scorer,
+    }
+}
+
+/// Log where timed out target was stuck. Samples landing in one function
+/// point at infinite loop, scattered ones at slow input.
+fn report_stuck(points: &[usize], symbols: Option<&ElfInfo>, state: &mut State) {
+    if points.is_empty() {
+        return;
+    }
+
+    let mut functions: Vec<(String, usize)> = vec![];
+
+    for point in points {
+        let name = symbols
+            .and_then(|symbols| symbols.function_at(*point))
+            .map_or_else(|| format!("{point:#x}"), |function| function.name.clone());
+
+        match functions.iter_mut().find(|(known, _)| *known == name) {
+            Some((_, count)) => *count += 1,
+            None => functions.push((name, 1)),
+        }
+    }
+
+    let location = functions
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let message = match functions.as_slice() {
+        [(name, count)] => format!("target timed out spinning in {name} ({count} samples)"),
+        _ => format!("target timed out in {location} (slow input rather than loop)"),
+    };
+
+    let seen = state.stuck_locations.entry(location).or_default();
+    *seen += 1;
+
+    if *seen == 1 {
+        log!("{message}");
+    } else {
+        log!(debug: "{message}");
+    }
+}
+
+/// Analyze changed target again so breakpoints match new build, function
+/// stats of new build start empty.
+fn reanalyze_target(
+    config: &FuzzConfig,
+    grammar: &Grammar,
+    state: &AM<State>,
+) -> Option<(DynEvaluator, HitStats)> {
+    match analysys::analyze_binary(&config.binary.path) {
+        Ok(mapping) => {
+            let mapping = mapping.with_symbol_filter(&config.binary.symbol_filter());
+
+            log!(
+                "extracted {} functions from rebuilt executable",
+                mapping.functions.len()
+            );
+
+            // state is locked only once binary is parsed, so that ui is not held up
+            {
+                let mut state = state.lock().unwrap();
+                state.functions_total = mapping.functions.len();
+                state.covered_functions.clear();
+            }
+            let hit_stats = HitStats::new(&mapping);
+
+            Some((build_evaluator(config, grammar, Some(mapping)), hit_stats))
+        }
+        Err(e) => {
+            log!(error: "failed to analyze rebuilt target: {e}");
+            None
+        }
+    }
+}
+
+/// Grammar of configuration, empty one in binary mode.
+pub fn load_grammar(config: &FuzzConfig) -> Result<Grammar, anyhow::Error> {
+    match &config.input {
+        crate::configuration::InputOptions::Grammar { grammar } => {
+            let content = std::fs::read_to_string(grammar)
+                .with_context(|| format!("reading grammar file {grammar}"))?;
+            crate::grammar::parse_grammar(&content).context("parsing grammar")
+        }
+        crate::configuration::InputOptions::Seeds { .. } => Ok(Grammar::empty()),
+    }
+}
+
+/// Build evaluator the same way fuzzing does for running saved samples
+/// outside of fuzzing loop.
+pub fn target_evaluator(config: &FuzzConfig) -> Result<DynEvaluator, anyhow::Error> {
+    let grammar = load_grammar(config)?;
+
+    let mapping = match config.binary.evaluator {
+        EvaluatorKind::Trace => Some(
+            analysys::analyze_binary(&config.binary.path)
+                .context("analyzing binary for trace evaluator")?
+                .with_symbol_filter(&config.binary.symbol_filter()),
+        ),
+        EvaluatorKind::ExitCode => None,
+    };
+
+    Ok(build_evaluator(config, &grammar, mapping))
+}
+
+fn write_hit_stats(path: Option<&Path>, stats: &HitStats) {
+    if let Some(path) = path {
+        if let Err(e) = stats.write(path) {
+            log!(warning: "failed to write function stats to {}: {e:#}", path.display());
+        }
+    }
+}
+
+/// Renice and pin calling thread, settings are inherited by every target process it spawns.
+#[cfg_attr(not(unix), allow(unused_variables))]
+fn apply_scheduling(config: &FuzzConfig) {
+    // niceness is rejected when configuration is checked elsewhere
+    #[cfg(unix)]
+    if let Some(nice) = config.fuzzing.nice {
+        // PRIO_PROCESS with zero id changes only calling thread on linux
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) } != 0 {
+            log!(
+                warning: "failed to set niceness {nice}: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+
+    // pinning is rejected when configuration is checked elsewhere
+    #[cfg(target_os = "linux")]
+    if let Some(cpu) = config.fuzzing.cpu {
+        let result = unsafe {
+            let mut set: libc::cpu_set_t = std::mem::zeroed();
+            libc::CPU_SET(cpu, &mut set);
+            libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+        };
+
+        if result == 0 {
+            log!("pinned to CPU core {cpu}");
+        } else {
+            log!(
+                warning: "failed to pin to CPU core {cpu}: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+}
+
+/// Run new crash again if configured and hand it to bookkeeping, cores of
+/// repeated runs are removed. Returns false if bookkeeping stopped.
+fn submit_crash(
+    mut result: RunResult,
+    config: &FuzzConfig,
+    core_dir: Option<&Path>,
+    reproduce: impl FnOnce(&crate::sample::Sample) -> Reproduction,
+    records: &mpsc::Sender<Record>,
+) -> bool {
+    if config.fuzzing.confirm_crashes > 0 {
+        result.reproduction = Some(reproduce(&result.sample));
+    }
+
+    // repeated runs leave cores of their own
+    discard_core(core_dir);
+
+    records.send(Record::Run(Box::new(result))).is_ok()
+}
+
+/// Remove core dumped by last crash, it belongs to no crash being saved.
+fn discard_core(core_dir: Option<&Path>) {
+    if let Some(core) = core_dir.and_then(coredump::take_core) {
+        let _ = std::fs::remove_file(core);
+    }
+}
+
+/// Core of original crash does not match its minimized sample, it is
+/// replaced with one dumped by running minimized sample once more.
+fn recapture_core(
+    mut result: RunResult,
+    core_dir: Option<&Path>,
+    rerun: impl FnOnce(&crate::sample::Sample),
+) -> RunResult {
+    let Some(dir) = core_dir.filter(|_| result.minimized_from.is_some()) else {
+        return result;
+    };
+
+    if let Some(core) = result.core.take() {
+        let _ = std::fs::remove_file(core);
+    }
+
+    rerun(&result.sample);
+    result.core = coredump::take_core(dir);
+
+    result
+}
+
+/// Directory inside output directory watched for samples to add to running campaign.
+const INJECT_DIR: &str = "inject";
+const INJECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Read and remove files dropped into inject directory. Hidden files are
+/// skipped, so inputs can be written under dot-prefixed name and renamed.
+fn take_injected(directory: &Path) -> Vec<(PathBuf, Sample)> {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return vec![];
+    };
+
+    let mut samples = vec![];
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if hidden || !path.is_file() {
+            continue;
+        }
+
+        let content = match std::fs::read(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                log!(warning: "failed to read injected {}: {e}", path.display());
+                continue;
+            }
+        };
+
+        if let Err(e) = std::fs::remove_file(&path) {
+            log!(warning: "failed to remove injected {}: {e}", path.display());
+            continue;
+        }
+
+        let tree: TreeNode = TreeNodeItem::Data(content, None).into();
+        samples.push((path, tree.fold_into_sample()));
+    }
+
+    samples
+}
+
+/// Messages from execution loop to bookkeeping thread.
+enum Record {
+    /// result of successful execution
+    Run(Box<RunResult>),
+    /// target was analyzed again, function stats start over
+    Reanalyzed(HitStats),
+    /// sample target timed out on
+    Hang(crate::sample::Sample),
+}
+
+/// Everything done with execution results that does not need evaluator:
+/// stats, saved crashes, event log and exports. Runs in its own thread so
+/// that execution loop does not wait for disk.
+struct Bookkeeper {
+    config: Arc<FuzzConfig>,
+    library: AM<Library>,
+    state: AM<State>,
+    output_file: EventLog,
+    grammar_mode: bool,
+    triager: Option<Triager>,
+    hit_stats: HitStats,
+    hit_stats_path: Option<PathBuf>,
+    state_export: Option<PathBuf>,
+    stats_interval: Duration,
+    last_stats: Instant,
+    last_snapshot: Instant,
+    lineage: Lineage,
+    confirmer: Option<Confirmer>,
+}
+
+impl Bookkeeper {
+    /// process records until execution loop hangs up
+    fn run(mut self, records: mpsc::Receiver<Record>) -> Result<(), anyhow::Error> {
+        for record in records {
+            match record {
+                Record::Run(result) => self.record_run(*result)?,
+                Record::Reanalyzed(hit_stats) => self.hit_stats = hit_stats,
+                Record::Hang(sample) => {
+                    let output = Path::new(&self.config.output.directory);
+
+                    match crate::hang::save_hang(output, &sample) {
+                        Ok(Some(path)) => log!("saved hang as {}", path.display()),
+                        Ok(None) => {}
+                        Err(e) => log!(warning: "failed to save hang: {e}"),
+                    }
+                }
+            }
+        }
+
+        write_hit_stats(self.hit_stats_path.as_deref(), &self.hit_stats);
+
+        self.export_state(&self.library.lock().unwrap(), &self.state.lock().unwrap());
+
+        Ok(())
+    }
+
+    fn export_state(&self, library: &Library, state: &State) {
+        let Some(path) = &self.state_export else {
+            return;
+        };
+
+        if let Err(e) = campaign::export_state(path, &self.config, library, state) {
+            log!(warning: "failed to export campaign state to {}: {e:#}", path.display());
+        }
+    }
+
+    fn record_run(&mut self, mut result: RunResult) -> Result<(), anyhow::Error> {
+        let config = &self.config;
+        let mut core = result.core.take();
+        let mut library = self.library.lock().unwrap();
+        let mut state = self.state.lock().unwrap();
+
+        state.tested_samples += 1;
+        state.speed.record();
+
+        if self.hit_stats_path.is_some() {
+            self.hit_stats.record(&result.trace);
+        }
+
+        // untraced targets never have functions to hit
+        if state.functions_total > 0 && result.trace.trajectory.is_empty() {
+            state.empty_traces += 1;
+        }
+
+        if result.input_cut_short {
+            state.input_cut_short += 1;
+        }
+
+        if let Some(mutation) = result.mutation {
+            let stats = state.mutators.entry(mutation).or_default();
+            stats.level = result.level.unwrap_or_default();
+            stats.applied += 1;
+            match result.status {
+                crate::fuzzing::RunResultStatus::Nothing => {}
+                crate::fuzzing::RunResultStatus::New => stats.new_paths += 1,
+                crate::fuzzing::RunResultStatus::SizeImprovement(_) => stats.improvements += 1,
+            }
+        }
+
+        if crate::log::is_verbose() {
+            log!(
+                debug: "got {:?} after running {}",
+                result.status,
+                String::from_utf8_lossy(result.sample.get_folded())
+            );
+        }
+
+        if let Some(memory) = result.memory {
+            state.memory = memory;
+
+            if memory.evicted > 0 {
+                state.evicted_entries += memory.evicted;
+                log!(
+                    "evicted {} library entries to stay within memory budget",
+                    memory.evicted
+                );
+            }
+        }
+
+        let step = match result.status {
+            crate::fuzzing::RunResultStatus::Nothing => None,
+            _ => mutation_step(&library, &result),
+        };
+
+        match result.status {
+            crate::fuzzing::RunResultStatus::Nothing => {}
+            crate::fuzzing::RunResultStatus::New => {
+                state.last_new_path = Some(Instant::now());
+
+                let new_functions = result
+                    .trace
+                    .trajectory
+                    .keys()
+                    .filter(|function| !state.covered_functions.contains(function))
+                    .sorted()
+                    .map(|function| self.hit_stats.function_name(*function))
+                    .collect_vec();
+
+                let hit_changes = hit_changes(&result, &self.hit_stats);
+
+                state
+                    .covered_functions
+                    .extend(result.trace.trajectory.keys());
+                state.record_rules(result.sample.tree());
+
+                let name = get_unique_name();
+
+                library.add_name(&result.trace, name.clone());
+
+                if let Some(step) = step {
+                    self.lineage.record(&name, step);
+                }
+
+                if let execution::ExecResult::Signal = result.trace.result {
+                    state.last_unique_crash = Some(Instant::now());
+
+                    let path = get_crash_path(config, &name);
+
+                    save_crash(&result.sample, path.clone(), self.grammar_mode)?;
+                    write_provenance(&self.lineage, &name, &path, result.minimized_from);
+
+                    let mut notes = vec![];
+
+                    if let Some(changes) = describe_new_path(&new_functions, &hit_changes) {
+                        notes.push(changes);
+                    }
+
+                    if let Some(original) = result.minimized_from {
+                        notes.push(format!("minimized from {original} bytes"));
+                    }
+
+                    if let Some(reproduction) = result.reproduction {
+                        notes.push(format!("reproduced {reproduction}"));
+
+                        state.reproductions.insert(name.clone(), reproduction);
+
+                        record_event(
+                            &mut self.output_file,
+                            FuzzingEventKind::CrashReproduction {
+                                trace_id: name.clone(),
+                                runs: reproduction.runs,
+                                crashes: reproduction.crashes,
+                                flaky: reproduction.is_flaky(),
+                            },
+                        )?;
+                    }
+
+                    let notes = match notes.is_empty() {
+                        true => String::new(),
+                        false => format!(" ({})", notes.join(", ")),
+                    };
+
+                    crate::log!(
+                        crash: "found new crash and saved it as {}{notes}",
+                        path.to_string_lossy()
+                    );
+
+                    if let Some(core) = core.take() {
+                        attach_core(&mut self.output_file, &core, &name, &path)?;
+                    }
+
+                    if let Some(triager) = &self.triager {
+                        triager.submit(&name, &path);
+                    }
+                }
+
+                if let execution::ExecResult::Code(code) = result.exit {
+                    match describe_new_path(&new_functions, &hit_changes) {
+                        Some(changes) => log!("new path {name} (code {code}): {changes}"),
+                        None => log!("new path {name} (code {code})"),
+                    }
+                }
+
+                if let Some(confirmer) = &self.confirmer {
+                    confirmer.submit(
+                        &name,
+                        result.sample.get_folded(),
+                        matches!(result.trace.result, execution::ExecResult::Signal),
+                    );
+                }
+
+                record_event(
+                    &mut self.output_file,
+                    FuzzingEventKind::NewPath {
+                        kind: match result.exit {
+                            execution::ExecResult::Code(code) => NewPathKind::ExitCode { code },
+                            execution::ExecResult::Signal => NewPathKind::Crash,
+                        },
+                        trace_id: name,
+                        sample: sample_info(&library, &result),
+                        new_functions,
+                        hit_changes,
+                    },
+                )?;
+            }
+            crate::fuzzing::RunResultStatus::SizeImprovement(change) => {
+                state.improvements += 1;
+
+                // entries put into library outside of fuzzing loop may have no name yet
+                let name = match library
+                    .find_existing(&result.trace)
+                    .and_then(|(entry, _sample)| entry.unique_name.clone())
+                {
+                    Some(name) => name,
+                    None => {
+                        let name = get_unique_name();
+                        library.add_name(&result.trace, name.clone());
+                        name
+                    }
+                };
+
+                if let Some(step) = step {
+                    self.lineage.record(&name, step);
+                }
+
+                if let execution::ExecResult::Signal = result.trace.result {
+                    let path = get_crash_path(config, &name);
+
+                    save_crash(&result.sample, path.clone(), self.grammar_mode)?;
+                    write_provenance(&self.lineage, &name, &path, None);
+                    crate::log!(crash: "found smaller example for crash {name} (-{change})");
+
+                    if let Some(core) = core.take() {
+                        attach_core(&mut self.output_file, &core, &name, &path)?;
+                    }
+
+                    record_event(
+                        &mut self.output_file,
+                        FuzzingEventKind::SizeImprovement {
+                            trace_id: name,
+                            delta: change,
+                            sample: sample_info(&library, &result),
+                        },
+                    )?;
+                }
+            }
+        }
+
+        // core of already known crash
+        if let Some(core) = core {
+            let _ = std::fs::remove_file(core);
+        }
+
+        if let execution::ExecResult::Code(code) = result.exit {
+            state.exit_codes_seen.insert(code, Instant::now());
+        }
+
+        match result.exit {
+            execution::ExecResult::Code(0) => state.total_working += 1,
+            execution::ExecResult::Code(_) => state.total_nonzero += 1,
+            execution::ExecResult::Signal => {
+                state.total_crashes += 1;
+            }
+        }
+
+        for (name, analysis) in self.triager.iter().flat_map(Triager::results) {
+            match analysis {
+                Ok(report) => {
+                    // crashes casr considers the same are reported once
+                    let duplicate_of = state
+                        .casr_reports
+                        .iter()
+                        .filter(|(known, _)| !state.casr_duplicates.contains_key(*known))
+                        .find(|(_, known)| known.dedup_key() == report.dedup_key())
+                        .map(|(known, _)| known.clone());
+
+                    match &duplicate_of {
+                        Some(original) => {
+                            log!(
+                                "casr: crash {name} is the same as {original} ({})",
+                                report.classification
+                            );
+                            state.casr_duplicates.insert(name.clone(), original.clone());
+                        }
+                        None => log!(
+                            crash: "casr: crash {name} is {} ({})",
+                            report.severity,
+                            report.classification
+                        ),
+                    }
+
+                    state.casr_reports.insert(name.clone(), report.clone());
+
+                    record_event(
+                        &mut self.output_file,
+                        FuzzingEventKind::CrashTriage {
+                            trace_id: name,
+                            report,
+                            duplicate_of,
+                        },
+                    )?;
+                }
+                Err(e) => log!(warning: "casr failed to analyze crash {name}: {e:#}"),
+            }
+        }
+
+        for (name, crashed, verdict) in self.confirmer.iter().flat_map(Confirmer::results) {
+            match verdict {
+                Ok(confirmation) => {
+                    let details = confirmation
+                        .summary
+                        .as_deref()
+                        .unwrap_or(&confirmation.status);
+
+                    match (crashed, confirmation.crashed) {
+                        (_, true) => {
+                            log!(crash: "confirmation build crashed on {name} ({details})")
+                        }
+                        (true, false) => {
+                            log!(warning: "crash {name} is not confirmed by confirmation build ({details})")
+                        }
+                        (false, false) => log!(debug: "confirmation build ran {name} ({details})"),
+                    }
+
+                    record_event(
+                        &mut self.output_file,
+                        FuzzingEventKind::Confirmation {
+                            trace_id: name,
+                            confirmation,
+                        },
+                    )?;
+                }
+                Err(e) => log!(warning: "failed to run {name} on confirmation build: {e:#}"),
+            }
+        }
+
+        if std::mem::take(&mut state.export_requested) {
+            if let Some(count) = export_libafl_corpus(config, &library) {
+                log!("exported {count} testcase(s) in LibAFL layout");
+            }
+        }
+
+        let snapshot_interval = config.output.snapshot_interval.map(Duration::from_secs);
+
+        // entries are copied here and written once locks are released
+        let mut snapshot = None;
+
+        if snapshot_interval.is_some_and(|interval| self.last_snapshot.elapsed() >= interval) {
+            self.last_snapshot = Instant::now();
+            snapshot = Some(snapshot::snapshot_entries(&library));
+        }
+
+        if self.last_stats.elapsed() >= self.stats_interval {
+            self.last_stats = Instant::now();
+
+            if config.output.libafl_corpus.is_some() {
+                export_libafl_corpus(config, &library);
+            }
+
+            write_hit_stats(self.hit_stats_path.as_deref(), &self.hit_stats);
+            self.export_state(&library, &state);
+
+            let (functions_covered, functions_total) = state.coverage();
+
+            record_event(
+                &mut self.output_file,
+                FuzzingEventKind::Stats {
+                    executions: state.tested_samples,
+                    duplicates: state.duplicates,
+                    failed: state.failed,
+                    spawn_failures: state.spawn_failures,
+                    input_failures: state.input_failures,
+                    input_cut_short: state.input_cut_short,
+                    timeouts: state.timeouts,
+                    empty_traces: state.empty_traces,
+                    unique_paths: library.len(),
+                    unique_crashes: library
+                        .iter()
+                        .filter(|(trace, _entry, _sample)| {
+                            matches!(trace.result, execution::ExecResult::Signal)
+                        })
+                        .count(),
+                    functions_covered,
+                    functions_total,
+                },
+            )?;
+        }
+
+        drop(state);
+        drop(library);
+
+        if let Some(entries) = snapshot {
+            match snapshot::write_snapshot(
+                &entries,
+                Path::new(&config.output.directory),
+                config.output.snapshot_keep,
+            ) {
+                Ok(path) => log!(debug: "wrote snapshot {}", path.display()),
+                Err(e) => log!(warning: "failed to write snapshot: {e}"),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Block until user resumes fuzzing or stops it altogether.
+fn wait_for_retry(state: &AM<State>, shutdown: &ShutdownToken, deadline: Option<Instant>) {
+    while !shutdown.is_cancelled()
+        && deadline.is_none_or(|deadline| Instant::now() < deadline)
+        && matches!(state.lock().unwrap().status, FuzzerStatus::Paused(_))
+    {
+        thread::sleep(Duration::from_millis(100));
+    }
 }
 
 pub fn spawn_fuzzer(
-    config: &'static FuzzConfig,
+    config: Arc<FuzzConfig>,
     library: AM<Library>,
     state: AM<State>,
+    shutdown: ShutdownToken,
+    state_files: StateFiles,
 ) -> Result<JoinHandle<Result<(), anyhow::Error>>, anyhow::Error> {
     let path = config.binary.path.clone();
 
-    let mapping = match analysys::analyze_binary(path) {
-        Ok(m) => m,
-        Err(e) => {
-            eprintln!("error analyzing binary for trace evaluator");
-            eprintln!("error: {e}");
-            process::exit(exitcode::DATAERR)
+    // untraced targets are not analyzed at all, they may not even be ELF
+    let mapping = match config.binary.evaluator {
+        EvaluatorKind::Trace => {
+            let mapping = match analysys::analyze_binary(path) {
+                Ok(m) => m,
+                Err(e) => {
+                    eprintln!("error analyzing binary for trace evaluator");
+                    eprintln!("error: {e}");
+                    process::exit(exitcode::DATAERR)
+                }
+            };
+
+            let extracted = mapping.functions.len();
+            let mapping = mapping.with_symbol_filter(&config.binary.symbol_filter());
+
+            crate::log!(
+                "extracted {} functions from executable",
+                mapping.functions.len()
+            );
+
+            if mapping.functions.len() < extracted {
+                crate::log!(
+                    "{} functions are not traced due to `include_symbols` and `exclude_symbols`",
+                    extracted - mapping.functions.len()
+                );
+            }
+
+            state.lock().unwrap().functions_total = mapping.functions.len();
+
+            Some(mapping)
+        }
+        EvaluatorKind::ExitCode => {
+            crate::log!(warning: "fuzzing without coverage feedback, only exit status is observed");
+            None
         }
     };
 
-    crate::log!(
-        "extracted {} functions from executable",
-        mapping.functions.len()
-    );
+    let casr = config.triage.casr.filter(|tool| {
+        let installed = tool.is_installed();
+        if !installed {
+            crate::log!(warning: "casr is not installed, crashes will not be analyzed");
+        }
+        installed
+    });
 
     let (seeds, grammar) = match &config.input {
         crate::configuration::InputOptions::Grammar { grammar } => {
@@ -90,6 +1147,14 @@ pub fn spawn_fuzzer(
                 }
             };
 
+            state.lock().unwrap().rules_total = grammar.productions.len();
+
+            for rule in config.fuzzing.focus.rules() {
+                if !grammar.productions.contains_key(rule) {
+                    crate::log!(warning: "rule `{rule}` of `fuzzing.focus` is not defined in grammar");
+                }
+            }
+
             let depth_limit = 30;
 
             let generator =
@@ -102,12 +1167,10 @@ pub fn spawn_fuzzer(
                 initial.get_folded().len()
             );
 
-            if config.output.debug {
-                println!(
-                    "initial sample: {}",
-                    String::from_utf8_lossy(initial.get_folded())
-                );
-            }
+            crate::log!(
+                debug: "initial sample: {}",
+                String::from_utf8_lossy(initial.get_folded())
+            );
 
             (vec![initial], grammar)
         }
@@ -126,7 +1189,7 @@ pub fn spawn_fuzzer(
                     )
                 })?;
 
-                let root = TreeNodeItem::Data(content);
+                let root = TreeNodeItem::Data(content, None);
                 let tree: TreeNode = root.into();
                 let folded_tree = tree.fold_into_sample();
 
@@ -145,158 +1208,450 @@ pub fn spawn_fuzzer(
         }
     };
 
-    let closure = move || {
-        let mutator = build_mutator(config, &grammar);
+    let thread_state = state.clone();
 
-        let evaluator = execution::TraceEvaluator::new(mapping, config.binary.pass_style);
-        let mut fuzzer = Fuzzer::new(mutator, library.clone(), evaluator);
+    let hit_stats = mapping.as_ref().map(HitStats::new).unwrap_or_default();
+    let hit_stats_path = config
+        .output
+        .function_stats
+        .as_ref()
+        .map(|file| PathBuf::from(&config.output.directory).join(file));
+
+    let closure = move || -> Result<(), anyhow::Error> {
+        apply_scheduling(&config);
+
+        let mutator = build_mutator(&config, &grammar);
+        let mutator_names = mutator.names();
 
-        for seed in seeds {
-            fuzzer.put_seed(seed).unwrap();
+        if let Some(detector) = &config.binary.detector {
+            log!("crashes are detected by {}", detector.path);
         }
 
-        let mut output_file = match std::fs::File::create("fuzzing.log") {
-            Ok(f) => f,
+        if let Some(confirmation) = &config.binary.confirmation {
+            log!(
+                "new paths and crashes are confirmed by {}",
+                confirmation.path
+            );
+        }
+
+        let core_dir = config
+            .output
+            .core_dumps
+            .then(|| coredump::core_dir(&config.output.directory));
+
+        if let Some(dir) = &core_dir {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("creating core dump directory {}", dir.display()))?;
+
+            if let Some(warning) = coredump::check_core_pattern() {
+                log!(warning: "{warning}");
+            }
+        }
+
+        let grammar_mode = matches!(
+            config.input,
+            crate::configuration::InputOptions::Grammar { .. }
+        );
+
+        // functions timed out target is stuck in are named even without tracing
+        let mut symbols = match &config.binary.timeout {
+            Some(_) if config.binary.stuck_samples > 0 => {
+                analysys::analyze_binary(&config.binary.path).ok()
+            }
+            _ => None,
+        };
+
+        let mut evaluator = build_evaluator(&config, &grammar, mapping);
+
+        self_test(&config, &mut evaluator, &seeds[0], grammar_mode)?;
+
+        let mut fuzzer = Fuzzer::new(mutator, library.clone(), evaluator);
+
+        let mut target = match TargetWatch::new(&config.binary.path) {
+            Ok(watch) => Some(watch),
             Err(e) => {
-                log!("failure opening event log file: {}", e);
-                panic!("failure opening event log file: {}", e);
+                log!(warning: "failed to hash target, its changes will not be detected: {e}");
+                None
             }
         };
 
-        while unsafe { FUZZER_RUNNNIG.load(std::sync::atomic::Ordering::SeqCst) } {
-            let result = match fuzzer.run_once() {
-                Ok(s) => s,
-                Err(e) => {
-                    let message = format!("error executing : {e:?}");
-                    log!("{}", message);
-                    anyhow::bail!(message)
+        if config.binary.evaluator == EvaluatorKind::ExitCode {
+            fuzzer.keep_seeds(&seeds);
+        }
+
+        if config.fuzzing.colorize {
+            fuzzer.enable_colorization();
+        }
+
+        if config.output.save_hangs {
+            fuzzer.keep_hangs();
+        }
+
+        fuzzer.set_trace_identity(config.binary.trace_identity);
+
+        fuzzer.set_memory_budget(
+            config
+                .fuzzing
+                .memory_budget
+                .map(|megabytes| megabytes * 1024 * 1024),
+        );
+
+        let imported = match &state_files.import {
+            Some(path) => {
+                let imported = campaign::import_state(
+                    path,
+                    &config,
+                    &mut library.lock().unwrap(),
+                    &mut state.lock().unwrap(),
+                    &mutator_names,
+                )
+                .with_context(|| format!("importing campaign state from {}", path.display()))?;
+
+                log!(
+                    "imported {imported} library entries from {}",
+                    path.display()
+                );
+                true
+            }
+            None => false,
+        };
+
+        // imported library already contains seeds
+        for seed in seeds.into_iter().filter(|_| !imported) {
+            let result = match fuzzer.put_seed(seed) {
+                Ok(result) => result,
+                Err(e) if execution::is_timeout(&e) => {
+                    log!(warning: "seed timed out, skipping it");
+                    state.lock().unwrap().timeouts += 1;
+                    continue;
                 }
+                Err(e) => return Err(e.context("running seed")),
             };
 
-            let mut library = library.lock().unwrap();
             let mut state = state.lock().unwrap();
+            state
+                .covered_functions
+                .extend(result.trace.trajectory.keys());
+            state.record_rules(result.sample.tree());
+        }
 
-            state.tested_samples += 1;
-            state.executions.push(Instant::now());
+        if library.lock().unwrap().len() == 0 {
+            return Err(anyhow!("every seed timed out, check `binary.timeout`"));
+        }
 
-            if config.output.debug {
-                println!(
-                    "got {:?} after runnning {}",
-                    result.status,
-                    String::from_utf8_lossy(result.sample.get_folded())
-                );
+        let event_log_path = PathBuf::from(&config.output.directory).join(&config.log.events);
+
+        let campaign = get_unique_name();
+
+        let mut output_file = match EventLog::open(
+            event_log_path,
+            config.log.max_size,
+            config.log.keep,
+            campaign.clone(),
+        ) {
+            Ok(f) => f,
+            Err(e) => {
+                log!(error: "failure opening event log file: {}", e);
+                panic!("failure opening event log file: {}", e);
             }
+        };
 
-            match result.status {
-                crate::fuzzing::RunResultStatus::Nothing => {}
-                crate::fuzzing::RunResultStatus::New => {
-                    state.last_new_path = Some(Instant::now());
+        let functions_total = state.lock().unwrap().functions_total;
 
-                    let name = get_unique_name();
+        record_event(
+            &mut output_file,
+            FuzzingEventKind::CampaignStart {
+                config_hash: config.hash.clone(),
+                target: config.binary.path.clone(),
+                start_time: Local::now().to_rfc3339(),
+                functions_total,
+                workers: WORKERS,
+                config: serde_json::to_value(&*config)?,
+            },
+        )?;
 
-                    library.add_name(&result.trace, name.clone());
+        log!("started campaign {campaign}");
+        log_banner(&config, functions_total);
 
-                    if let execution::ExecResult::Signal = result.trace.result {
-                        state.last_unique_crash = Some(Instant::now());
+        match session::write_session(&config, &campaign) {
+            Ok(path) => log!(debug: "session described in {}", path.display()),
+            Err(e) => log!(warning: "failed to write session file: {e:#}"),
+        }
 
-                        let path = get_crash_path(config, &name);
+        let bookkeeper = Bookkeeper {
+            config: config.clone(),
+            library: library.clone(),
+            state: state.clone(),
+            output_file,
+            grammar_mode,
+            // casr runs in background, results are collected by bookkeeping
+            triager: casr.map(|tool| Triager::spawn(tool, config.clone())),
+            hit_stats,
+            hit_stats_path,
+            state_export: state_files.export.clone(),
+            stats_interval: Duration::from_secs(config.log.stats_interval),
+            last_stats: Instant::now(),
+            last_snapshot: Instant::now(),
+            lineage: Lineage::default(),
+            confirmer: Confirmer::spawn(config.clone()),
+        };
 
-                        save_crash(&result.sample, path.clone())?;
-                        crate::log!(
-                            "found new crash and saved it as {}",
-                            path.into_os_string().into_string().unwrap()
-                        );
-                    }
+        let (records, received) = mpsc::channel();
+        let bookkeeping = thread::spawn(move || bookkeeper.run(received));
 
-                    let event = FuzzingEvent {
-                        time_as_seconds: SystemTime::now()
-                            .duration_since(SystemTime::UNIX_EPOCH)
-                            .unwrap()
-                            .as_secs_f64(),
-                        kind: FuzzingEventKind::NewPath {
-                            kind: match result.trace.result {
-                                execution::ExecResult::Code(code) => NewPathKind::ExitCode { code },
-                                execution::ExecResult::Signal => NewPathKind::Crash,
-                            },
-                            trace_id: name,
-                        },
-                    };
+        let started = Instant::now();
+        let max_time = config.fuzzing.max_time.map(Duration::from_secs);
+
+        let mut errors =
+            ErrorBudget::new(config.fuzzing.error_window, config.fuzzing.max_error_rate);
+
+        let mut throttler = config.fuzzing.throttle.map(Throttler::new);
+
+        let inject_dir = PathBuf::from(&config.output.directory).join(INJECT_DIR);
+        if let Err(e) = std::fs::create_dir_all(&inject_dir) {
+            log!(warning: "failed to create {}: {e}", inject_dir.display());
+        }
+        let mut last_inject = Instant::now();
+        let mut paced = fuzzer.executions();
+
+        while !shutdown.is_cancelled() {
+            // every execution counts, including failed ones and ones spent
+            // on colorization, minimization or running entries again
+            let executions = fuzzer.executions();
+
+            let limit = if config
+                .fuzzing
+                .max_runs
+                .is_some_and(|runs| executions >= runs)
+            {
+                Some(format!("done {executions} runs"))
+            } else if max_time.is_some_and(|time| started.elapsed() >= time) {
+                Some(format!("done {} seconds", started.elapsed().as_secs()))
+            } else {
+                None
+            };
+
+            if let Some(reason) = limit {
+                log!("stopping fuzzing: {reason}");
+                state.lock().unwrap().status = FuzzerStatus::Finished(reason);
+                break;
+            }
+
+            // executions of last iteration, whatever they were for (duplicates do not run target)
+            if let Some(throttler) = throttler.as_mut().filter(|_| executions > paced) {
+                throttler.pace(executions - paced, &shutdown);
+            }
+            paced = executions;
 
-                    match writeln!(
-                        &mut output_file,
-                        "{}",
-                        serde_json::to_string(&event).unwrap()
-                    ) {
-                        Ok(_) => {}
-                        Err(e) => {
-                            let message = format!("error writing to log file: {e}");
-                            log!("{}", message);
-                            anyhow::bail!(message);
+            if last_inject.elapsed() >= INJECT_INTERVAL {
+                last_inject = Instant::now();
+
+                for (path, sample) in take_injected(&inject_dir) {
+                    match fuzzer.put_seed(sample) {
+                        Ok(result) => {
+                            log!(
+                                "injected {}: {}",
+                                path.display(),
+                                match result.status {
+                                    crate::fuzzing::RunResultStatus::New => "new path",
+                                    crate::fuzzing::RunResultStatus::SizeImprovement(_) =>
+                                        "smaller sample for known path",
+                                    crate::fuzzing::RunResultStatus::Nothing => "nothing new",
+                                }
+                            );
+
+                            // named, saved and recorded like any other run
+                            let _ = records.send(Record::Run(Box::new(result)));
                         }
+                        Err(e) => log!(warning: "failed to run injected {}: {e}", path.display()),
                     }
                 }
-                crate::fuzzing::RunResultStatus::SizeImprovement(change) => {
-                    state.improvements += 1;
+            }
 
-                    if let execution::ExecResult::Signal = result.trace.result {
-                        let name = library
-                            .find_existing(&result.trace)
-                            .as_ref()
-                            .unwrap()
-                            .unique_name
-                            .as_ref()
+            if fuzzer.take_instrumentation_change() {
+                fuzzer.invalidate_library();
+                log!(
+                    "traced functions changed, running {} library entries again",
+                    fuzzer.stale_entries()
+                );
+            }
+
+            // one entry per iteration, so that shutdown and injection are not held up
+            if fuzzer.stale_entries() > 0 {
+                match fuzzer.reevaluate_next() {
+                    Ok(Some(trace)) => {
+                        state
+                            .lock()
                             .unwrap()
-                            .clone();
+                            .covered_functions
+                            .extend(trace.trajectory.keys());
+                    }
+                    Ok(None) => {}
+                    Err(e) => log!(warning: "failed to run library entry again: {e}"),
+                }
 
-                        let path = get_crash_path(config, &name);
+                if fuzzer.stale_entries() == 0 {
+                    log!(
+                        "library entries run again, {} distinct paths remain",
+                        library.lock().unwrap().len()
+                    );
+                }
+                continue;
+            }
 
-                        save_crash(&result.sample, path.clone())?;
-                        crate::log!("found smaller example for crash {name} (-{change})");
+            // one execution per iteration, so that shutdown and injection are not held up
+            if fuzzer.is_minimizing() {
+                let finished = fuzzer.minimize_step();
 
-                        let event = FuzzingEvent {
-                            time_as_seconds: SystemTime::now()
-                                .duration_since(SystemTime::UNIX_EPOCH)
-                                .unwrap()
-                                .as_secs_f64(),
-                            kind: FuzzingEventKind::SizeImprovement {
-                                trace_id: name,
-                                delta: change,
-                            },
-                        };
-
-                        match writeln!(
-                            &mut output_file,
-                            "{}",
-                            serde_json::to_string(&event).unwrap()
-                        ) {
-                            Ok(_) => {}
-                            Err(e) => {
-                                let message = format!("error writing to log file: {e}");
-                                log!("{}", message);
-                                anyhow::bail!(message);
-                            }
+                // candidates crash as well, their cores are not of crash being shrunk
+                discard_core(core_dir.as_deref());
+
+                if let Some(result) = finished {
+                    let result = recapture_core(result, core_dir.as_deref(), |sample| {
+                        fuzzer.reproduce(sample, 1);
+                    });
+                    let reproduce =
+                        |sample: &_| fuzzer.reproduce(sample, config.fuzzing.confirm_crashes);
+
+                    if !submit_crash(result, &config, core_dir.as_deref(), reproduce, &records) {
+                        break;
+                    }
+                }
+                continue;
+            }
+
+            let mut result = match fuzzer.run_once() {
+                Ok(Some(s)) => s,
+                Ok(None) => {
+                    state.lock().unwrap().duplicates += 1;
+                    continue;
+                }
+                Err(e) if execution::is_timeout(&e) => {
+                    state.lock().unwrap().timeouts += 1;
+                    errors.record(false);
+
+                    report_stuck(
+                        &fuzzer.stuck_points(),
+                        symbols.as_ref(),
+                        &mut state.lock().unwrap(),
+                    );
+
+                    if let Some(sample) = fuzzer.take_hang() {
+                        let _ = records.send(Record::Hang(sample));
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    let message = format!("error executing : {e:?}");
+                    log!(error: "{}", message);
+
+                    {
+                        let mut state = state.lock().unwrap();
+                        state.failed += 1;
+                        if execution::is_spawn_failure(&e) {
+                            state.spawn_failures += 1;
+                        } else if execution::is_input_failure(&e) {
+                            state.input_failures += 1;
                         }
                     }
+
+                    if !errors.record(true) {
+                        log!(error: "too many failed executions, pausing");
+                        state.lock().unwrap().status = FuzzerStatus::Paused(message);
+
+                        wait_for_retry(&state, &shutdown, max_time.map(|time| started + time));
+                        errors.reset();
+                    }
+                    continue;
+                }
+            };
+
+            errors.record(false);
+
+            if let (Some(dir), execution::ExecResult::Signal) = (&core_dir, &result.trace.result) {
+                result.core = coredump::take_core(dir);
+            }
+
+            let new_crash = matches!(result.status, crate::fuzzing::RunResultStatus::New)
+                && matches!(result.trace.result, execution::ExecResult::Signal);
+
+            // crash is recorded once it is minimized
+            if new_crash && config.fuzzing.minimize_crashes > 0 {
+                fuzzer.start_minimization(result, config.fuzzing.minimize_crashes);
+                continue;
+            }
+
+            let submitted = match new_crash {
+                true => {
+                    let reproduce =
+                        |sample: &_| fuzzer.reproduce(sample, config.fuzzing.confirm_crashes);
+
+                    submit_crash(result, &config, core_dir.as_deref(), reproduce, &records)
                 }
+                false => records.send(Record::Run(Box::new(result))).is_ok(),
+            };
+
+            // bookkeeping stopped on error, it is returned once thread is joined
+            if !submitted {
+                break;
             }
 
-            match result.trace.result {
-                execution::ExecResult::Code(0) => state.total_working += 1,
-                execution::ExecResult::Code(_) => state.total_nonzero += 1,
-                execution::ExecResult::Signal => {
-                    state.total_crashes += 1;
+            let exploit_crashes = state.lock().unwrap().exploit_crashes;
+            fuzzer.set_crash_exploitation(exploit_crashes);
+
+            if let Some(hash) = target.as_mut().and_then(TargetWatch::check) {
+                log!(
+                    error: "target {} changed on disk (sha256 {hash}), coverage of old build is stale",
+                    config.binary.path
+                );
+
+                if config.binary.reanalyze_on_change
+                    && config.binary.evaluator == EvaluatorKind::Trace
+                {
+                    if let Some((evaluator, hit_stats)) =
+                        reanalyze_target(&config, &grammar, &state)
+                    {
+                        if symbols.is_some() {
+                            symbols = analysys::analyze_binary(&config.binary.path).ok();
+                        }
+
+                        fuzzer.replace_evaluator(evaluator);
+                        fuzzer.invalidate_library();
+                        log!(
+                            "running {} library entries on new build",
+                            fuzzer.stale_entries()
+                        );
+                        let _ = records.send(Record::Reanalyzed(hit_stats));
+                    }
                 }
             }
         }
 
-        Ok(())
+        // crash interrupted while being minimized is recorded as it is
+        if let Some(result) = fuzzer.finish_minimization() {
+            discard_core(core_dir.as_deref());
+            let result = recapture_core(result, core_dir.as_deref(), |sample| {
+                fuzzer.reproduce(sample, 1);
+            });
+            let _ = records.send(Record::Run(Box::new(result)));
+        }
+
+        drop(records);
+
+        bookkeeping
+            .join()
+            .map_err(|_| anyhow!("bookkeeping thread panicked"))?
     };
 
-    if config.output.debug {
-        closure().unwrap();
+    Ok(thread::spawn(move || {
+        let result = closure();
 
-        Ok(thread::spawn(|| Ok(())))
-    } else {
-        Ok(thread::spawn(closure))
-    }
+        if let Err(e) = &result {
+            // logged as well so that error is seen in headless mode
+            log!(error: "{e:#}");
+            thread_state.lock().unwrap().status = FuzzerStatus::Stopped(format!("{e:#}"));
+        }
+
+        result
+    }))
 }
@@ -1,8 +1,17 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+use serde_derive::{Deserialize, Serialize};
 
 use crate::{
+    colorization::{self, EffectiveBytes},
+    configuration::TraceIdentity,
     execution::{self},
-    sample_library::{CoverageScore, Library, SizeScore},
+    sample_library::{CoverageScore, Footprint, Library, SizeScore},
 };
 
 pub trait Mutator {
@@ -16,6 +25,16 @@ pub trait Mutator {
     ) -> (Self::Item, Self::MutInfo);
 
     fn update_scores(&mut self, index: Self::MutInfo, result: RunResult);
+
+    /// restrict mutations to small local changes
+    fn set_conservative(&mut self, conservative: bool);
+
+    fn describe(&self, index: &Self::MutInfo) -> &'static str;
+
+    fn level(&self, index: &Self::MutInfo) -> MutationLevel;
+
+    /// bytes of sample being mutated that target inspects, if known
+    fn set_effective_bytes(&mut self, effective: Option<EffectiveBytes>);
 }
 
 pub trait Evaluator {
@@ -31,6 +50,108 @@ pub trait Evaluator {
         &mut self,
         sample: Self::Item,
     ) -> Result<execution::DetailedTrace, anyhow::Error>;
+
+    /// program counters sampled from target before it was killed by last
+    /// timeout, relative to executable like function offsets
+    fn stuck_points(&mut self) -> Vec<usize> {
+        vec![]
+    }
+
+    /// Whether set of traced functions changed since last call (eg. hot
+    /// functions were pruned), traces produced before are not comparable
+    /// with new ones then.
+    fn take_instrumentation_change(&mut self) -> bool {
+        false
+    }
+
+    /// whether target closed stdin before reading whole input of last
+    /// execution, eg. it exited early or reads only part of input
+    fn input_cut_short(&mut self) -> bool {
+        false
+    }
+
+    /// sample last execution timed out on, it is consumed by failed `score`
+    fn take_timed_out(&mut self) -> Option<Self::Item> {
+        None
+    }
+}
+
+impl<E: Evaluator + ?Sized> Evaluator for Box<E> {
+    type Item = E::Item;
+    type EvalResult = E::EvalResult;
+
+    fn score(
+        &mut self,
+        sample: Self::Item,
+    ) -> Result<TestedSample<Self::Item, Self::EvalResult>, anyhow::Error> {
+        (**self).score(sample)
+    }
+
+    fn trace_detailed(
+        &mut self,
+        sample: Self::Item,
+    ) -> Result<execution::DetailedTrace, anyhow::Error> {
+        (**self).trace_detailed(sample)
+    }
+
+    fn stuck_points(&mut self) -> Vec<usize> {
+        (**self).stuck_points()
+    }
+
+    fn take_instrumentation_change(&mut self) -> bool {
+        (**self).take_instrumentation_change()
+    }
+
+    fn input_cut_short(&mut self) -> bool {
+        (**self).input_cut_short()
+    }
+
+    fn take_timed_out(&mut self) -> Option<Self::Item> {
+        (**self).take_timed_out()
+    }
+}
+
+/// Evaluator counting executions of target, including failed ones.
+struct Counted<E> {
+    evaluator: E,
+    executions: usize,
+}
+
+impl<E: Evaluator> Evaluator for Counted<E> {
+    type Item = E::Item;
+    type EvalResult = E::EvalResult;
+
+    fn score(
+        &mut self,
+        sample: Self::Item,
+    ) -> Result<TestedSample<Self::Item, Self::EvalResult>, anyhow::Error> {
+        self.executions += 1;
+        self.evaluator.score(sample)
+    }
+
+    fn trace_detailed(
+        &mut self,
+        sample: Self::Item,
+    ) -> Result<execution::DetailedTrace, anyhow::Error> {
+        self.executions += 1;
+        self.evaluator.trace_detailed(sample)
+    }
+
+    fn stuck_points(&mut self) -> Vec<usize> {
+        self.evaluator.stuck_points()
+    }
+
+    fn take_instrumentation_change(&mut self) -> bool {
+        self.evaluator.take_instrumentation_change()
+    }
+
+    fn input_cut_short(&mut self) -> bool {
+        self.evaluator.input_cut_short()
+    }
+
+    fn take_timed_out(&mut self) -> Option<Self::Item> {
+        self.evaluator.take_timed_out()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -40,12 +161,123 @@ pub struct TestedSample<Sample, EvalResult> {
 }
 
 impl<S, E: CoverageScore> CoverageScore for TestedSample<S, E> {
-    fn get_score(&self) -> f64 {
-        self.result.get_score()
+    fn covered_functions(&self) -> Vec<usize> {
+        self.result.covered_functions()
     }
 }
 type AM<T> = Arc<Mutex<T>>;
 
+/// Bounded set of hashes of recently executed samples.
+pub struct RecentSamples {
+    capacity: usize,
+    order: VecDeque<u64>,
+    hashes: HashSet<u64>,
+}
+
+impl RecentSamples {
+    pub fn new(capacity: usize) -> Self {
+        RecentSamples {
+            capacity,
+            order: VecDeque::with_capacity(capacity),
+            hashes: HashSet::with_capacity(capacity),
+        }
+    }
+
+    /// remember sample returning false if it was seen recently
+    pub fn insert(&mut self, sample: &crate::sample::Sample) -> bool {
+        let hash = sample.content_hash();
+
+        if !self.hashes.insert(hash) {
+            return false;
+        }
+
+        self.order.push_back(hash);
+
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.hashes.remove(&oldest);
+            }
+        }
+
+        true
+    }
+
+    /// approximate number of bytes held
+    pub fn footprint(&self) -> usize {
+        // every hash is stored in queue and in set
+        self.capacity * 2 * std::mem::size_of::<u64>()
+    }
+}
+
+/// Approximate memory held by fuzzer, reported whenever library changes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MemoryUsage {
+    pub library: usize,
+    /// samples being mutated and hashes of recently executed ones
+    pub buffers: usize,
+    /// library entries evicted to stay within memory budget
+    pub evicted: usize,
+}
+
+/// sample made of raw bytes without derivation tree
+fn bytes_sample(content: Vec<u8>) -> crate::sample::Sample {
+    let tree: crate::sample::TreeNode = crate::sample::TreeNodeItem::Data(content, None).into();
+    tree.fold_into_sample()
+}
+
+/// New crash shrunk by removing chunks of halving size as long as trace
+/// stays the same, one execution at a time.
+struct Minimization {
+    result: RunResult,
+    /// smallest content with trace of crash so far
+    content: Vec<u8>,
+    /// execution time of `content`, none until crash got smaller
+    exec_time: Option<Duration>,
+    runs: usize,
+    budget: usize,
+    chunk: usize,
+    /// offset of next chunk to remove
+    start: usize,
+}
+
+/// Exit code library keys of every nonzero exit carry when only zero and
+/// nonzero exit codes are told apart.
+pub const COLLAPSED_EXIT_CODE: i32 = 1;
+
+/// How many of repeated runs of crashing sample crashed again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Reproduction {
+    pub runs: usize,
+    pub crashes: usize,
+}
+
+impl Reproduction {
+    pub fn is_flaky(&self) -> bool {
+        self.crashes < self.runs
+    }
+}
+
+impl std::fmt::Display for Reproduction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.crashes, self.runs)?;
+
+        if self.is_flaky() {
+            write!(f, " flaky")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Library item (or seed kept outside of library) being mutated with
+/// number of mutations left to try on it.
+struct Burst {
+    /// library key of item, none for seeds
+    parent: Option<execution::RunTrace>,
+    sample: crate::sample::Sample,
+    energy: usize,
+}
+
 pub struct Fuzzer<Lib, Mut, Eval, MutInfo>
 where
     Lib: Library,
@@ -54,14 +286,72 @@ where
 {
     pub library: AM<Lib>,
     mutator: Mut,
-    evaluator: Eval,
+    evaluator: Counted<Eval>,
+    recent: RecentSamples,
+    burst: Option<Burst>,
+    exploit_crashes: bool,
+    /// seeds kept for mutation when library can not tell them apart
+    seeds: Vec<crate::sample::Sample>,
+    colorize: bool,
+    /// bytes library and buffers may take, library entries are evicted past it
+    memory_budget: Option<usize>,
+    /// part of exit status kept in library keys
+    identity: TraceIdentity,
+    /// keep samples target timed out on until they are taken
+    keep_hangs: bool,
+    hang: Option<crate::sample::Sample>,
+    /// new crash being shrunk, see [`Fuzzer::minimize_step`]
+    minimizing: Option<Minimization>,
+    /// keys of library entries stored before instrumentation changed, they
+    /// are run again with current evaluator
+    stale: Vec<execution::RunTrace>,
 }
 
 #[derive(Clone, Debug)]
 pub struct RunResult {
     pub sample: crate::sample::Sample,
     pub trace: crate::execution::RunTrace,
+    /// exit status of target, `trace` has collapsed one if exit codes are collapsed
+    pub exit: execution::ExecResult,
     pub status: RunResultStatus,
+    pub mutation: Option<&'static str>,
+    /// level of mutation that produced sample
+    pub level: Option<MutationLevel>,
+    /// library key of sample that was mutated, kept valid when library
+    /// indices change after eviction
+    pub parent: Option<execution::RunTrace>,
+    /// content hash of sample that was mutated
+    pub parent_hash: Option<u64>,
+    /// memory usage after library was changed by this run
+    pub memory: Option<MemoryUsage>,
+    /// core dumped by crashing target, waiting to be attached to crash
+    pub core: Option<std::path::PathBuf>,
+    /// repeated runs of new crash
+    pub reproduction: Option<Reproduction>,
+    /// size of new crash before it was minimized
+    pub minimized_from: Option<usize>,
+    /// target did not read whole input
+    pub input_cut_short: bool,
+}
+
+/// What mutation operated on: raw bytes, derivation tree or nothing
+/// (freshly generated sample).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MutationLevel {
+    #[default]
+    Binary,
+    Tree,
+    Fresh,
+}
+
+impl MutationLevel {
+    pub fn name(self) -> &'static str {
+        match self {
+            MutationLevel::Binary => "binary",
+            MutationLevel::Tree => "tree",
+            MutationLevel::Fresh => "fresh",
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -77,66 +367,505 @@ where
     Mut: Mutator<Item = crate::sample::Sample, MutInfo = MutInfo>,
     Eval: Evaluator<Item = crate::sample::Sample, EvalResult = crate::execution::RunTrace>,
 {
+    /// number of recently executed samples remembered to skip duplicates
+    const RECENT_SAMPLES: usize = 4096;
+
     pub fn new(mutator: Mut, library: AM<Lib>, evaluator: Eval) -> Self {
         Fuzzer {
             mutator,
             library,
-            evaluator,
+            evaluator: Counted {
+                evaluator,
+                executions: 0,
+            },
+            recent: RecentSamples::new(Self::RECENT_SAMPLES),
+            burst: None,
+            exploit_crashes: false,
+            seeds: vec![],
+            colorize: false,
+            memory_budget: None,
+            identity: TraceIdentity::Status,
+            keep_hangs: false,
+            hang: None,
+            minimizing: None,
+            stale: vec![],
         }
     }
 
+    /// run samples with another evaluator from now on
+    pub fn replace_evaluator(&mut self, evaluator: Eval) {
+        self.evaluator.evaluator = evaluator;
+    }
+
+    /// number of times target was executed, whatever for and however it ended
+    pub fn executions(&self) -> usize {
+        self.evaluator.executions
+    }
+
+    /// mutations applied to kept seed in a row
+    const SEED_ENERGY: usize = 16;
+
+    /// Without coverage feedback seeds with the same exit status collapse
+    /// into one library entry, keep all of them in rotation instead.
+    pub fn keep_seeds(&mut self, seeds: &[crate::sample::Sample]) {
+        self.seeds = seeds.to_vec();
+    }
+
+    /// Find bytes target inspects in every library entry before mutating
+    /// it, binary mutations then mostly hit those bytes.
+    pub fn enable_colorization(&mut self) {
+        self.colorize = true;
+    }
+
+    /// Keep library and buffers within given number of bytes by evicting
+    /// library entries, crashing ones are never evicted.
+    pub fn set_memory_budget(&mut self, budget: Option<usize>) {
+        self.memory_budget = budget;
+    }
+
+    /// Choose what besides coverage tells library entries apart. Exit codes
+    /// left out of identity are replaced in library keys: nonzero ones by
+    /// [`COLLAPSED_EXIT_CODE`], every one by 0 when only crashes count.
+    pub fn set_trace_identity(&mut self, identity: TraceIdentity) {
+        self.identity = identity;
+    }
+
+    /// where target was sampled before last timeout killed it
+    pub fn stuck_points(&mut self) -> Vec<usize> {
+        self.evaluator.stuck_points()
+    }
+
+    /// whether evaluator changed set of traced functions since last call
+    pub fn take_instrumentation_change(&mut self) -> bool {
+        self.evaluator.take_instrumentation_change()
+    }
+
+    /// Mark every library entry stale, its trace was produced by previous
+    /// instrumentation and can not be compared with new ones.
+    pub fn invalidate_library(&mut self) {
+        self.stale = self.library.lock().unwrap().keys();
+    }
+
+    /// number of library entries waiting to be run again
+    pub fn stale_entries(&self) -> usize {
+        self.stale.len()
+    }
+
+    /// Run next stale library entry with current evaluator and store it
+    /// under new trace keeping its name. Entries that now share trace are
+    /// merged into the one with smaller sample. Returns new trace, none
+    /// if there was nothing to run or entry was evicted meanwhile.
+    pub fn reevaluate_next(&mut self) -> Result<Option<execution::RunTrace>, anyhow::Error> {
+        let Some(key) = self.stale.pop() else {
+            return Ok(None);
+        };
+
+        let Some(sample) = self
+            .library
+            .lock()
+            .unwrap()
+            .find_existing(&key)
+            .map(|(_entry, sample)| sample.clone())
+        else {
+            return Ok(None);
+        };
+
+        let started = Instant::now();
+        // entry keeps old trace if it fails to run
+        let mut tested = self.evaluator.score(sample)?;
+        let exec_time = started.elapsed();
+
+        self.apply_identity(&mut tested.result);
+
+        if tested.result == key {
+            return Ok(Some(tested.result));
+        }
+
+        let library = self.library.clone();
+        let mut library = library.lock().unwrap();
+
+        let Some(entry) = library.remove(&key) else {
+            return Ok(None);
+        };
+
+        let merged = match library.find_existing(&tested.result) {
+            Some((_entry, existing)) => {
+                if existing.get_size_score() > tested.sample.get_size_score() {
+                    library.upsert(tested.result.clone(), tested.sample, exec_time);
+                }
+                true
+            }
+            None => {
+                library.upsert(tested.result.clone(), tested.sample, exec_time);
+                false
+            }
+        };
+
+        if let (false, Some(name)) = (merged, entry.unique_name) {
+            library.add_name(&tested.result, name);
+        }
+
+        // picked entry may be gone
+        self.burst = None;
+
+        Ok(Some(tested.result))
+    }
+
+    /// Remember sample of last execution that timed out, see [`Fuzzer::take_hang`].
+    pub fn keep_hangs(&mut self) {
+        self.keep_hangs = true;
+    }
+
+    /// sample target timed out on last if hangs are kept
+    pub fn take_hang(&mut self) -> Option<crate::sample::Sample> {
+        self.hang.take()
+    }
+
+    /// approximate number of bytes held by samples being mutated and recent sample hashes
+    fn buffers_footprint(&self) -> usize {
+        self.recent.footprint()
+            + self
+                .burst
+                .as_ref()
+                .map_or(0, |burst| burst.sample.footprint())
+            + self.seeds.iter().map(Footprint::footprint).sum::<usize>()
+    }
+
+    /// Evict library entries if memory budget is exceeded, entry that was
+    /// just stored is kept.
+    fn enforce_memory_budget(
+        &mut self,
+        library: &mut Lib,
+        stored: &execution::RunTrace,
+    ) -> MemoryUsage {
+        let buffers = self.buffers_footprint();
+        let mut evicted = 0;
+
+        if let Some(budget) = self.memory_budget {
+            if library.footprint() + buffers > budget {
+                evicted = library.evict(budget.saturating_sub(buffers), |trace| {
+                    trace == stored || matches!(trace.result, execution::ExecResult::Signal)
+                });
+            }
+        }
+
+        if evicted > 0 {
+            // parent of current burst may be gone
+            self.burst = None;
+        }
+
+        MemoryUsage {
+            library: library.footprint(),
+            buffers,
+            evicted,
+        }
+    }
+
+    /// share of picks going to crashing entries in crash exploitation mode
+    const CRASH_PICK_PROBA: f64 = 0.9;
+
+    /// Switch crash exploitation mode: crashing library entries are picked
+    /// most of the time and mutated conservatively.
+    pub fn set_crash_exploitation(&mut self, enabled: bool) {
+        if self.exploit_crashes != enabled {
+            self.exploit_crashes = enabled;
+            self.mutator.set_conservative(enabled);
+            self.burst = None;
+        }
+    }
+
+    /// pick sample to mutate with its library index and energy
+    fn pick_parent(&self, library: &mut Lib) -> (Option<usize>, crate::sample::Sample, usize) {
+        let mut rng = rand::thread_rng();
+
+        if self.exploit_crashes && rng.gen_bool(Self::CRASH_PICK_PROBA) {
+            if let Some((parent, sample)) = library
+                .pick_random_matching(|trace| matches!(trace.result, execution::ExecResult::Signal))
+            {
+                return (Some(parent), sample, library.energy(parent));
+            }
+        }
+
+        let seeds = self.seeds.len() as u32;
+        let entries = library.linearize().len() as u32;
+
+        if seeds > 0 && rng.gen_ratio(seeds, seeds + entries) {
+            let seed = self.seeds[rng.gen_range(0..self.seeds.len())].clone();
+            return (None, seed, Self::SEED_ENERGY);
+        }
+
+        let (parent, sample) = library.pick_random();
+        (Some(parent), sample, library.energy(parent))
+    }
+
     fn put_in_library(
         &mut self,
-        tested: TestedSample<crate::sample::Sample, crate::execution::RunTrace>,
+        mut tested: TestedSample<crate::sample::Sample, crate::execution::RunTrace>,
+        exec_time: Duration,
     ) -> Result<RunResult, anyhow::Error> {
-        let status = {
-            let mut library = self.library.lock().unwrap();
+        let exit = tested.result.result.clone();
+        let input_cut_short = self.evaluator.input_cut_short();
+        self.apply_identity(&mut tested.result);
+
+        let (status, memory) = {
+            let library = self.library.clone();
+            let mut library = library.lock().unwrap();
+
+            library.record_hits(&tested.result);
 
-            if let Some(existing) = library.find_existing(&tested.result) {
-                if existing.item.get_size_score() > tested.sample.get_size_score() {
-                    let improvement =
-                        existing.item.get_size_score() - tested.sample.get_size_score();
-                    library.upsert(tested.result.clone(), tested.sample.clone());
+            let status = if let Some((_entry, existing)) = library.find_existing(&tested.result) {
+                if existing.get_size_score() > tested.sample.get_size_score() {
+                    let improvement = existing.get_size_score() - tested.sample.get_size_score();
+                    library.upsert(tested.result.clone(), tested.sample.clone(), exec_time);
                     RunResultStatus::SizeImprovement(improvement)
                 } else {
                     RunResultStatus::Nothing
                 }
             } else {
-                library.upsert(tested.result.clone(), tested.sample.clone());
+                library.upsert(tested.result.clone(), tested.sample.clone(), exec_time);
 
                 RunResultStatus::New
-            }
+            };
+
+            let memory = match status {
+                RunResultStatus::Nothing => None,
+                _ => Some(self.enforce_memory_budget(&mut library, &tested.result)),
+            };
+
+            (status, memory)
         };
 
         Ok(RunResult {
             sample: tested.sample,
             trace: tested.result,
+            exit,
             status,
+            mutation: None,
+            level: None,
+            parent: None,
+            parent_hash: None,
+            memory,
+            core: None,
+            reproduction: None,
+            minimized_from: None,
+            input_cut_short,
         })
     }
 
-    pub fn run_once(&mut self) -> Result<RunResult, anyhow::Error> {
-        let (mutated, mut_info) = {
+    /// Mutate sample and run it. Returns None without running target
+    /// if mutated sample is identical to one executed recently.
+    ///
+    /// Random sample is picked from library once per burst, it is then
+    /// mutated as many times as library assigns energy to it.
+    pub fn run_once(&mut self) -> Result<Option<RunResult>, anyhow::Error> {
+        let mut burst = match self.burst.take() {
+            Some(burst) if burst.energy > 0 => burst,
+            _ => self.start_burst()?,
+        };
+
+        let (mutated, mut_info, parent, parent_hash) = {
             let mut library = self.library.lock().unwrap();
 
-            let sample = library.pick_random();
+            burst.energy -= 1;
+
+            let (mutated, mut_info) = self
+                .mutator
+                .mutate_sample(burst.sample.clone(), library.linearize());
+
+            let parent = burst.parent.clone();
+            let parent_hash = burst.sample.content_hash();
+            self.burst = Some(burst);
 
-            self.mutator.mutate_sample(sample, library.linearize())
+            (mutated, mut_info, parent, parent_hash)
         };
 
-        let traced = self.evaluator.score(mutated)?;
+        if !self.recent.insert(&mutated) {
+            return Ok(None);
+        }
 
-        let result = self.put_in_library(traced)?;
+        let started = Instant::now();
+        let traced = match self.evaluator.score(mutated) {
+            Ok(traced) => traced,
+            Err(e) => {
+                if self.keep_hangs && execution::is_timeout(&e) {
+                    self.hang = self.evaluator.take_timed_out();
+                }
+                return Err(e);
+            }
+        };
+        let exec_time = started.elapsed();
+
+        let mut result = self.put_in_library(traced, exec_time)?;
+
+        result.mutation = Some(self.mutator.describe(&mut_info));
+        result.level = Some(self.mutator.level(&mut_info));
+        result.parent = parent;
+        result.parent_hash = Some(parent_hash);
 
         self.mutator.update_scores(mut_info, result.clone());
 
-        Ok(result)
+        Ok(Some(result))
+    }
+
+    /// Pick sample for next burst. If colorization is enabled, library
+    /// entry is colorized once and result is stored along with it, seeds
+    /// kept outside of library are mutated without it.
+    fn start_burst(&mut self) -> Result<Burst, anyhow::Error> {
+        let (parent, key, sample, energy, known) = {
+            let mut library = self.library.lock().unwrap();
+            let (parent, sample, energy) = self.pick_parent(&mut library);
+            let key = parent.and_then(|parent| library.key(parent));
+            let known = parent.and_then(|parent| library.effective_bytes(parent).cloned());
+            (parent, key, sample, energy, known)
+        };
+
+        if self.colorize {
+            let effective = match (parent, known) {
+                (_, Some(known)) => Some(known),
+                (Some(parent), None) => {
+                    // samples with unstable trace are not colorized again
+                    let effective =
+                        colorization::colorize(&mut self.evaluator, &sample)?.unwrap_or_default();
+
+                    self.library
+                        .lock()
+                        .unwrap()
+                        .set_effective_bytes(parent, effective.clone());
+
+                    Some(effective)
+                }
+                (None, None) => None,
+            };
+
+            self.mutator.set_effective_bytes(effective);
+        }
+
+        Ok(Burst {
+            parent: key,
+            sample,
+            energy,
+        })
+    }
+
+    /// replace parts of exit status left out of trace identity
+    fn apply_identity(&self, trace: &mut execution::RunTrace) {
+        if let execution::ExecResult::Code(code) = &mut trace.result {
+            match self.identity {
+                TraceIdentity::Status => {}
+                TraceIdentity::Nonzero if *code != 0 => *code = COLLAPSED_EXIT_CODE,
+                TraceIdentity::Nonzero => {}
+                TraceIdentity::Coverage => *code = 0,
+            }
+        }
+    }
+
+    /// Start shrinking new crash spending at most `budget` executions, see
+    /// [`Fuzzer::minimize_step`].
+    pub fn start_minimization(&mut self, result: RunResult, budget: usize) {
+        let content = result.sample.get_folded().to_vec();
+
+        self.minimizing = Some(Minimization {
+            chunk: content.len().div_ceil(2),
+            content,
+            exec_time: None,
+            runs: 0,
+            budget,
+            start: 0,
+            result,
+        });
+    }
+
+    /// whether crash is being shrunk
+    pub fn is_minimizing(&self) -> bool {
+        self.minimizing.is_some()
+    }
+
+    /// Run one smaller candidate of crash being shrunk. Returns crash once
+    /// it can not get smaller or budget is spent, see
+    /// [`Fuzzer::finish_minimization`].
+    pub fn minimize_step(&mut self) -> Option<RunResult> {
+        let mut minimization = self.minimizing.take()?;
+
+        while minimization.chunk > 0 && minimization.start >= minimization.content.len() {
+            minimization.chunk /= 2;
+            minimization.start = 0;
+        }
+
+        if minimization.chunk == 0 || minimization.runs == minimization.budget {
+            self.minimizing = Some(minimization);
+            return self.finish_minimization();
+        }
+
+        minimization.runs += 1;
+
+        let content = &minimization.content;
+        let end = (minimization.start + minimization.chunk).min(content.len());
+        let candidate = [&content[..minimization.start], &content[end..]].concat();
+
+        let started = Instant::now();
+        let tested = self.evaluator.score(bytes_sample(candidate.clone()));
+        let elapsed = started.elapsed();
+
+        match tested {
+            Ok(mut tested) => {
+                self.apply_identity(&mut tested.result);
+
+                if tested.result == minimization.result.trace {
+                    minimization.content = candidate;
+                    minimization.exec_time = Some(elapsed);
+                } else {
+                    minimization.start = end;
+                }
+            }
+            Err(_) => minimization.start = end,
+        }
+
+        self.minimizing = Some(minimization);
+        None
+    }
+
+    /// Stop shrinking crash and return it. Smallest sample found so far
+    /// replaces crash in library and in result.
+    pub fn finish_minimization(&mut self) -> Option<RunResult> {
+        let minimization = self.minimizing.take()?;
+        let mut result = minimization.result;
+
+        if let Some(exec_time) = minimization.exec_time {
+            result.minimized_from = Some(result.sample.get_folded().len());
+            result.sample = bytes_sample(minimization.content);
+
+            self.library.lock().unwrap().upsert(
+                result.trace.clone(),
+                result.sample.clone(),
+                exec_time,
+            );
+        }
+
+        Some(result)
+    }
+
+    /// Run crashing sample again given number of times counting runs that
+    /// crashed, failed runs count as not reproduced.
+    pub fn reproduce(&mut self, sample: &crate::sample::Sample, runs: usize) -> Reproduction {
+        let crashes = (0..runs)
+            .filter(|_| {
+                self.evaluator.score(sample.clone()).is_ok_and(|tested| {
+                    matches!(tested.result.result, execution::ExecResult::Signal)
+                })
+            })
+            .count();
+
+        Reproduction { runs, crashes }
     }
 
     pub fn put_seed(&mut self, sample: crate::sample::Sample) -> Result<RunResult, anyhow::Error> {
+        self.recent.insert(&sample);
+
+        let started = Instant::now();
         let traced = self.evaluator.score(sample)?;
+        let exec_time = started.elapsed();
 
-        let result = self.put_in_library(traced)?;
+        let result = self.put_in_library(traced, exec_time)?;
 
         Ok(result)
     }
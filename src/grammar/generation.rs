@@ -1,32 +1,148 @@
-use rand::Rng;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
 use rand_regex::Regex;
 
 use crate::{
     grammar::{Grammar, Token},
-    sample::{GrammarSample, ProductionApplication, TreeNode, TreeNodeItem},
+    sample::{
+        GrammarSample, ProductionApplication, TokenKind, TokenOrigin, TreeNode, TreeNodeItem,
+    },
 };
 
+/// kind of data produced by token, None for nonterminals
+fn token_kind(token: &Token) -> Option<TokenKind> {
+    match token {
+        Token::Identifier(_) => None,
+        Token::String(_) | Token::AnyCase(_) => Some(TokenKind::String),
+        Token::Hex(_) => Some(TokenKind::Hex),
+        Token::Regex { .. } => Some(TokenKind::Regex),
+        Token::Bytes { .. } => Some(TokenKind::Bytes),
+        Token::Bits { .. } => Some(TokenKind::Bits),
+        Token::Float { .. } => Some(TokenKind::Float),
+        Token::Repeat { inner, .. } | Token::Encoded { inner, .. } => token_kind(inner),
+    }
+}
+
+/// float values parsers tend to mishandle: NaN, infinities, signed zeros,
+/// smallest and largest subnormals and limits
+const F32_SPECIALS: &[f32] = &[
+    f32::NAN,
+    f32::INFINITY,
+    f32::NEG_INFINITY,
+    0.0,
+    -0.0,
+    1.0,
+    -1.0,
+    f32::from_bits(1),
+    f32::from_bits(0x007f_ffff),
+    f32::MIN_POSITIVE,
+    f32::EPSILON,
+    f32::MAX,
+    f32::MIN,
+];
+
+const F64_SPECIALS: &[f64] = &[
+    f64::NAN,
+    f64::INFINITY,
+    f64::NEG_INFINITY,
+    0.0,
+    -0.0,
+    1.0,
+    -1.0,
+    f64::from_bits(1),
+    f64::from_bits(0x000f_ffff_ffff_ffff),
+    f64::MIN_POSITIVE,
+    f64::EPSILON,
+    f64::MAX,
+    f64::MIN,
+];
+
+/// number of samples generated in search of one fitting `max_generated_bytes`
+const SIZE_ATTEMPTS: usize = 100;
+
 pub struct Generator {
     grammar: Grammar,
     depth_limit: usize,
+    /// separator inserted between non-empty items of productions
+    joiner: Option<Vec<u8>>,
+    /// how strongly alternatives referencing nonterminals are avoided
+    /// deeper in the tree, 0 picks alternatives uniformly
+    terminal_bias: f64,
+    /// samples over this size are thrown away and generated again
+    max_bytes: Option<usize>,
+    /// whether failure to fit `max_bytes` was already reported
+    size_warned: AtomicBool,
 }
 
 impl Generator {
     pub fn new(grammar: Grammar, depth_limit: usize) -> Generator {
+        let joiner = grammar
+            .options
+            .get("joiner")
+            .filter(|joiner| !joiner.is_empty())
+            .map(String::into_bytes);
+
+        let terminal_bias = grammar
+            .options
+            .get("terminal_bias")
+            .and_then(|bias| bias.parse().ok())
+            .unwrap_or(0.0);
+
+        let max_bytes = grammar
+            .options
+            .get_int("max_generated_bytes")
+            .and_then(Result::ok)
+            .map(|max| max as usize);
+
         Generator {
             grammar,
             depth_limit,
+            joiner,
+            terminal_bias,
+            max_bytes,
+            size_warned: AtomicBool::new(false),
         }
     }
 
-    pub fn generate(&self) -> GrammarSample {
-        let tree = loop {
+    fn generate_tree(&self) -> TreeNode {
+        loop {
             if let Ok(res) = self.generate_production("root", self.depth_limit) {
-                break res;
+                return res;
             }
+        }
+    }
+
+    /// Generate sample from `root`, samples larger than `max_generated_bytes`
+    /// are generated again. When none of `SIZE_ATTEMPTS` fits, the smallest
+    /// one is returned (and reported once).
+    pub fn generate(&self) -> GrammarSample {
+        let Some(max_bytes) = self.max_bytes else {
+            return self.generate_tree().into();
         };
 
-        tree.into()
+        let mut smallest = self.generate_tree();
+
+        for _attempt in 1..SIZE_ATTEMPTS {
+            if smallest.size <= max_bytes {
+                break;
+            }
+
+            let tree = self.generate_tree();
+
+            if tree.size < smallest.size {
+                smallest = tree;
+            }
+        }
+
+        if smallest.size > max_bytes && !self.size_warned.swap(true, Ordering::Relaxed) {
+            crate::log!(
+                warning: "could not generate sample within max_generated_bytes = {max_bytes}, using one of size {}",
+                smallest.size
+            );
+        }
+
+        smallest.into()
     }
 
     pub fn generate_of_type(
@@ -47,7 +163,20 @@ impl Generator {
         Err(())
     }
 
-    fn generate_token(&self, token: &Token, remaining_depth: usize) -> Result<TreeNode, ()> {
+    /// generate new data for regex token at given origin, None if token is not a regex
+    pub fn regenerate_regex(&self, origin: &TokenOrigin) -> Option<Vec<u8>> {
+        match self.grammar.token(origin)? {
+            Token::Regex { regex, .. } => Some(self.generate_regex(regex).into_bytes()),
+            _ => None,
+        }
+    }
+
+    fn generate_token(
+        &self,
+        token: &Token,
+        origin: Option<TokenOrigin>,
+        remaining_depth: usize,
+    ) -> Result<TreeNode, ()> {
         match token {
             Token::Identifier(i) => {
                 if remaining_depth == 0 {
@@ -56,20 +185,74 @@ impl Generator {
                     self.generate_production(i, remaining_depth - 1)
                 }
             }
-            Token::String(s) => Ok(TreeNodeItem::Data(s.clone().into_bytes()).into()),
-            Token::Hex(h) => Ok(TreeNodeItem::Data(h.clone()).into()),
+            Token::String(s) => Ok(TreeNodeItem::Data(s.clone().into_bytes(), origin).into()),
+            Token::AnyCase(s) => Ok(TreeNodeItem::Data(self.generate_any_case(s), origin).into()),
+            Token::Hex(h) => Ok(TreeNodeItem::Data(h.clone(), origin).into()),
 
-            Token::Regex(re) => {
-                let regex_application = self.generate_regex(re);
-                Ok(TreeNodeItem::Data(regex_application.into_bytes()).into())
+            Token::Regex { regex, .. } => {
+                let regex_application = self.generate_regex(regex);
+                Ok(TreeNodeItem::Data(regex_application.into_bytes(), origin).into())
             }
 
             &Token::Bytes { min, max } => {
-                Ok(TreeNodeItem::Data(self.generate_byte_sequence(min, max)).into())
+                Ok(TreeNodeItem::Data(self.generate_byte_sequence(min, max), origin).into())
+            }
+
+            Token::Bits { .. } => Ok(TreeNodeItem::Data(
+                self.generate_bit_fields(std::slice::from_ref(token)),
+                origin,
+            )
+            .into()),
+
+            &Token::Float { double, big_endian } => {
+                Ok(TreeNodeItem::Data(self.generate_float(double, big_endian), origin).into())
+            }
+
+            &Token::Repeat {
+                ref inner,
+                min,
+                max,
+            } => {
+                let count = rand::thread_rng().gen_range(min..=max);
+
+                let mut data = vec![];
+
+                for _ in 0..count {
+                    let TreeNode {
+                        item: TreeNodeItem::Data(copy, _),
+                        ..
+                    } = self.generate_token(inner, None, remaining_depth)?
+                    else {
+                        return Err(());
+                    };
+
+                    data.extend(copy);
+                }
+
+                Ok(TreeNodeItem::Data(data, origin).into())
+            }
+
+            Token::Encoded { encoding, inner } => {
+                let inner = self.generate_token(inner, origin, remaining_depth)?;
+                Ok(TreeNodeItem::Encoded(*encoding, Box::new(inner)).into())
             }
         }
     }
 
+    fn generate_any_case(&self, s: &str) -> Vec<u8> {
+        let mut rng = rand::thread_rng();
+
+        s.bytes()
+            .map(|byte| {
+                if rng.gen_bool(0.5) {
+                    byte.to_ascii_uppercase()
+                } else {
+                    byte.to_ascii_lowercase()
+                }
+            })
+            .collect()
+    }
+
     fn generate_regex(&self, regex: &Regex) -> String {
         let mut rng = rand::thread_rng();
         rng.sample(regex)
@@ -83,6 +266,174 @@ impl Generator {
         (0..size).map(|_| rng.gen()).collect()
     }
 
+    /// Encode random floating point number, half of the time one of special
+    /// values (NaN, infinities, signed zeros, subnormals, limits).
+    fn generate_float(&self, double: bool, big_endian: bool) -> Vec<u8> {
+        let mut rng = rand::thread_rng();
+
+        let special = rng.gen_bool(0.5);
+
+        let mut bytes = if double {
+            let value = if special {
+                F64_SPECIALS[rng.gen_range(0..F64_SPECIALS.len())]
+            } else {
+                f64::from_bits(rng.gen())
+            };
+            value.to_le_bytes().to_vec()
+        } else {
+            let value = if special {
+                F32_SPECIALS[rng.gen_range(0..F32_SPECIALS.len())]
+            } else {
+                f32::from_bits(rng.gen())
+            };
+            value.to_le_bytes().to_vec()
+        };
+
+        if big_endian {
+            bytes.reverse();
+        }
+
+        bytes
+    }
+
+    /// Pack values of consecutive bit fields into bytes, most significant
+    /// bit first, last byte is padded with zero bits.
+    fn generate_bit_fields(&self, fields: &[Token]) -> Vec<u8> {
+        let mut rng = rand::thread_rng();
+
+        let mut bytes = vec![];
+        let mut filled = 0;
+
+        for field in fields {
+            let &Token::Bits { width, value } = field else {
+                continue;
+            };
+
+            let value = value.unwrap_or_else(|| rng.gen::<u64>() & (u64::MAX >> (64 - width)));
+
+            for bit in (0..width).rev() {
+                if filled % 8 == 0 {
+                    bytes.push(0);
+                }
+
+                if (value >> bit) & 1 == 1 {
+                    *bytes.last_mut().unwrap() |= 0x80 >> (filled % 8);
+                }
+
+                filled += 1;
+            }
+        }
+
+        bytes
+    }
+
+    /// Generate items of production alternative, runs of bit fields
+    /// become single data node with origin of first field in run.
+    fn generate_items(
+        &self,
+        production: &[Token],
+        rule: &str,
+        variant: usize,
+        remaining_depth: usize,
+    ) -> Result<Vec<TreeNode>, ()> {
+        let mut items = vec![];
+        let mut position = 0;
+
+        while position < production.len() {
+            let token = &production[position];
+
+            let origin = token_kind(token).map(|kind| TokenOrigin {
+                kind,
+                rule: rule.to_string(),
+                variant,
+                position,
+            });
+
+            let fields = production[position..]
+                .iter()
+                .take_while(|token| matches!(token, Token::Bits { .. }))
+                .count();
+
+            if fields > 0 {
+                let data = self.generate_bit_fields(&production[position..position + fields]);
+                items.push((position, TreeNodeItem::Data(data, origin).into()));
+                position += fields;
+                continue;
+            }
+
+            items.push((
+                position,
+                self.generate_token(token, origin, remaining_depth)?,
+            ));
+            position += 1;
+        }
+
+        Ok(self.join(items, rule, variant))
+    }
+
+    /// Put separator from `joiner` option between non-empty items, items
+    /// come with position of token that produced them.
+    fn join(&self, items: Vec<(usize, TreeNode)>, rule: &str, variant: usize) -> Vec<TreeNode> {
+        let Some(joiner) = &self.joiner else {
+            return items.into_iter().map(|(_, item)| item).collect();
+        };
+
+        let mut joined = Vec::with_capacity(items.len() * 2);
+        let mut after_data = false;
+
+        for (position, item) in items {
+            if item.size > 0 {
+                if after_data {
+                    let origin = TokenOrigin {
+                        kind: TokenKind::Separator,
+                        rule: rule.to_string(),
+                        variant,
+                        position,
+                    };
+
+                    joined.push(TreeNodeItem::Data(joiner.clone(), Some(origin)).into());
+                }
+
+                after_data = true;
+            }
+
+            joined.push(item);
+        }
+
+        joined
+    }
+
+    /// Weights of alternatives at given depth when `terminal_bias` is set,
+    /// alternative with `n` nonterminals gets `(1 + terminal_bias)^-(n * depth)`.
+    fn variant_weights(
+        &self,
+        productions: &[Vec<Token>],
+        depth: usize,
+    ) -> Option<WeightedIndex<f64>> {
+        if self.terminal_bias == 0.0 {
+            return None;
+        }
+
+        let step = (1.0 + self.terminal_bias).ln();
+
+        let exponents: Vec<f64> = productions
+            .iter()
+            .map(|production| {
+                let nonterminals = production
+                    .iter()
+                    .filter(|token| matches!(token.innermost(), Token::Identifier(_)))
+                    .count();
+
+                -step * (nonterminals * depth) as f64
+            })
+            .collect();
+
+        // shift exponents so that the largest weight is 1 and none overflows
+        let max = exponents.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+        WeightedIndex::new(exponents.iter().map(|exponent| (exponent - max).exp())).ok()
+    }
+
     fn generate_production(
         &self,
         current_production: &str,
@@ -92,15 +443,22 @@ impl Generator {
             panic!("could not find production rule with name `{current_production}` in supplied grammar during generation")
         });
 
+        let depth = self.depth_limit.saturating_sub(remaining_depth);
+        let weights = self.variant_weights(productions, depth);
+
         for _ in 0..remaining_depth {
-            let chosen_idx = rand::thread_rng().gen_range(0..productions.len());
+            let chosen_idx = match &weights {
+                Some(weights) => weights.sample(&mut rand::thread_rng()),
+                None => rand::thread_rng().gen_range(0..productions.len()),
+            };
             let production = &productions[chosen_idx];
 
-            if let Ok(sub) = production
-                .iter()
-                .map(|token| self.generate_token(token, remaining_depth - 1))
-                .collect::<Result<Vec<TreeNode>, ()>>()
-            {
+            if let Ok(sub) = self.generate_items(
+                production,
+                current_production,
+                chosen_idx,
+                remaining_depth - 1,
+            ) {
                 return Ok(TreeNodeItem::ProductionApplication(ProductionApplication {
                     rule_name: current_production.to_string(),
                     production_variant: chosen_idx,
@@ -1,16 +1,59 @@
+use indexmap::IndexMap;
 use rand_regex::Regex;
 
-use std::collections::HashMap;
-
-use crate::flags::{FlagValue, Flags};
+use crate::{
+    flags::{FlagValue, Flags},
+    sample::{Encoding, TokenOrigin},
+};
 
 #[derive(Clone, Debug)]
 pub enum Token {
     Identifier(String),
     String(String),
+    /// string with letters in random case
+    AnyCase(String),
     Hex(Vec<u8>),
-    Regex(Regex),
-    Bytes { min: usize, max: usize },
+    /// regex with flag telling whether it matches unicode characters
+    Regex {
+        regex: Regex,
+        unicode: bool,
+    },
+    Bytes {
+        min: usize,
+        max: usize,
+    },
+    /// field of given number of bits, random if value is not set,
+    /// consecutive fields are packed into bytes
+    Bits {
+        width: u32,
+        value: Option<u64>,
+    },
+    /// IEEE-754 number of single or double precision
+    Float {
+        double: bool,
+        big_endian: bool,
+    },
+    /// literal repeated random number of times within bounds
+    Repeat {
+        inner: Box<Token>,
+        min: usize,
+        max: usize,
+    },
+    /// token whose folded content is encoded
+    Encoded {
+        encoding: Encoding,
+        inner: Box<Token>,
+    },
+}
+
+impl Token {
+    /// token inside of encoding wrappers
+    pub fn innermost(&self) -> &Token {
+        match self {
+            Token::Encoded { inner, .. } => inner.innermost(),
+            token => token,
+        }
+    }
 }
 
 pub type ProductionRhs = Vec<Token>;
@@ -25,7 +68,8 @@ pub struct Production {
 pub struct Grammar {
     pub options: Flags,
 
-    pub productions: HashMap<String, Vec<ProductionRhs>>,
+    /// alternatives of every rule in order of definition
+    pub productions: IndexMap<String, Vec<ProductionRhs>>,
 }
 
 fn compile_regex(s: &str, size_limit: u32, unicode: u32) -> Result<Regex, &'static str> {
@@ -92,13 +136,13 @@ peg::parser! {
                 s.parse().unwrap()
             }
 
-        rule regex() -> Regex =
+        rule regex() -> (Regex, bool) =
             "re" _ "(" _ s: string() _ f: flags() _ ")" {?
 
                 let limit = f.get_int("size_limit").unwrap_or(Ok(100)).map_err(|_| "size_limit should be int field")?;
                 let unicode = f.get_int("unicode").unwrap_or(Ok(0)).map_err(|_| "unicode should be int field")?;
 
-                compile_regex(&s, limit, unicode)
+                Ok((compile_regex(&s, limit, unicode)?, unicode != 0))
             }
 
         rule bytes() -> (usize, usize) =
@@ -113,25 +157,97 @@ peg::parser! {
                 }
             }
 
+        rule bits_value() -> u64 =
+            "0b" s:$(['0'|'1']+) {?
+                u64::from_str_radix(s, 2).map_err(|_| "bit field value does not fit into 64 bits")
+            }/
+            "0x" s:$(['0'..='9'|'a'..='f'|'A'..='F']+) {?
+                u64::from_str_radix(s, 16).map_err(|_| "bit field value does not fit into 64 bits")
+            }/
+            s:$(['0'..='9']+) {?
+                s.parse().map_err(|_| "bit field value does not fit into 64 bits")
+            }
+
+        rule bits() -> Token =
+            "bits" _ "(" _ width:number() _ ")" value:(_ "=" _ v:bits_value() {v})? {?
+                if !(1..=64).contains(&width) {
+                    return Err("bit field width should be between 1 and 64");
+                }
+
+                if matches!(value, Some(value) if width < 64 && value >> width != 0) {
+                    return Err("bit field value does not fit into its width");
+                }
+
+                Ok(Token::Bits { width, value })
+            }
+
+        rule float() -> Token =
+            kind:$("f32" / "f64") _ "(" _ f:flags() _ ")" {?
+                let big_endian = f.get_int("big_endian").unwrap_or(Ok(0)).map_err(|_| "big_endian should be int field")?;
+
+                Ok(Token::Float { double: kind == "f64", big_endian: big_endian != 0 })
+            }
+
+        rule literal() -> Token =
+            s:string() "i" !['a'..='z'|'A'..='Z'|'0'..='9'|'_'] {
+                Token::AnyCase(s)
+            }/
+            s: string() {
+                Token::String(s)
+            }/
+            hex: hexstring() {
+                Token::Hex(hex)
+            }
+
+        rule repeated() -> Token =
+            t:literal() "*{" _ min:number() _ max:("," _ n:number() _ {n})? "}" {?
+                let max = max.unwrap_or(min);
+
+                if min > max {
+                    return Err("repetition lower bound must be less or equal to upper bound");
+                }
+
+                Ok(Token::Repeat { inner: Box::new(t), min: min as usize, max: max as usize })
+            }
+
+        rule encoding() -> Encoding =
+            "gzip" { Encoding::Gzip } /
+            "base64" { Encoding::Base64 } /
+            "hexenc" { Encoding::Hex }
+
+        rule encoded() -> Token =
+            e:encoding() _ "(" _ t:token() _ ")" {
+                Token::Encoded { encoding: e, inner: Box::new(t) }
+            }
+
         rule token() -> Token =
+            e: encoded() {
+                e
+            }/
             "Nothing" {
                 Token::String("".to_string())
             }/
             r: regex() {
-                Token::Regex(r)
+                Token::Regex { regex: r.0, unicode: r.1 }
             }/
             b: bytes() {
                 Token::Bytes { min: b.0, max: b.1 }
             }/
+            b: bits() {
+                b
+            }/
+            f: float() {
+                f
+            }/
 
             i:identifier() {
                 Token::Identifier(i.to_string())
             }/
-            s: string() {
-                Token::String(s)
+            r: repeated() {
+                r
             }/
-            hex: hexstring() {
-                Token::Hex(hex)
+            l: literal() {
+                l
             }
 
         rule rhs() -> ProductionRhs =
@@ -151,8 +267,7 @@ peg::parser! {
         pub rule grammar() -> Grammar =
             _ f:flags() _
             prods: production()+ _ {
-                Grammar{ options: f, productions: prods.into_iter().map(|p| (p.lhs, p.rhs)).collect() }
-
+                Grammar::new(f, prods)
             }
 
         rule _() = quiet!{[' ' | '\r' | '\n' | '\t']*}
@@ -161,10 +276,47 @@ peg::parser! {
 }
 
 impl Grammar {
+    /// Build grammar from rules in order of definition, alternatives of rule
+    /// defined several times are joined (`a -> "x"; a -> "y";` is `a -> "x" | "y";`).
+    pub fn new(options: Flags, productions: Vec<Production>) -> Self {
+        let mut merged: IndexMap<String, Vec<ProductionRhs>> = IndexMap::new();
+
+        for production in productions {
+            merged
+                .entry(production.lhs)
+                .or_default()
+                .extend(production.rhs);
+        }
+
+        Self {
+            options,
+            productions: merged,
+        }
+    }
+
     pub fn empty() -> Self {
         Self {
             options: Flags::new(Default::default()),
             productions: Default::default(),
         }
     }
+
+    /// every token of every production
+    pub fn tokens(&self) -> impl Iterator<Item = &Token> {
+        self.productions.values().flatten().flatten()
+    }
+
+    /// rule named by `message` option, its applications are written to target one at a time
+    pub fn message_rule(&self) -> Option<String> {
+        self.options.get("message")
+    }
+
+    /// terminal token at given origin, encoding wrappers around it are skipped
+    pub fn token(&self, origin: &TokenOrigin) -> Option<&Token> {
+        self.productions
+            .get(&origin.rule)?
+            .get(origin.variant)?
+            .get(origin.position)
+            .map(Token::innermost)
+    }
 }
@@ -8,7 +8,13 @@ use super::{Grammar, Token};
 type ValidateResult = Result<(), anyhow::Error>;
 
 pub fn validate_grammar(g: &Grammar) -> ValidateResult {
-    let checks = [find_root, resolve_names];
+    let checks = [
+        find_root,
+        resolve_names,
+        check_bit_fields,
+        check_options,
+        check_repeated_alternatives,
+    ];
 
     let _ = checks
         .into_iter()
@@ -18,6 +24,26 @@ pub fn validate_grammar(g: &Grammar) -> ValidateResult {
     Ok(())
 }
 
+/// Repeated alternatives (also coming from several definitions of rule)
+/// only produce warning as they just make choice of alternative uneven.
+fn check_repeated_alternatives(g: &Grammar) -> ValidateResult {
+    for (rule, productions) in &g.productions {
+        // tokens have no equality (regexes are compiled), so alternatives
+        // are compared by their debug representation
+        let mut alternatives = HashSet::new();
+
+        for (variant, production) in productions.iter().enumerate() {
+            if !alternatives.insert(format!("{production:?}")) {
+                crate::log!(
+                    warning: "variant {variant} of `{rule}` repeats earlier alternative and is chosen more often"
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
 fn find_root(g: &Grammar) -> ValidateResult {
     if !g.productions.contains_key("root") {
         Err(anyhow!("provided grammar does not contain node `root`"))
@@ -26,13 +52,75 @@ fn find_root(g: &Grammar) -> ValidateResult {
     }
 }
 
+/// runs of consecutive bit fields (and fields inside encoding wrappers) must fill whole bytes
+fn check_bit_fields(g: &Grammar) -> ValidateResult {
+    for (rule, productions) in &g.productions {
+        for (variant, production) in productions.iter().enumerate() {
+            let mut widths = vec![];
+            let mut run = 0;
+
+            for token in production {
+                if let Token::Bits { width, .. } = token {
+                    run += width;
+                    continue;
+                }
+
+                widths.push(std::mem::take(&mut run));
+
+                if let Token::Bits { width, .. } = token.innermost() {
+                    widths.push(*width);
+                }
+            }
+
+            widths.push(run);
+
+            if widths.iter().any(|width| width % 8 != 0) {
+                return Err(anyhow!(
+                    "bit fields in variant {variant} of `{rule}` do not fill whole bytes"
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn check_options(g: &Grammar) -> ValidateResult {
+    if let Some(bias) = g.options.get("terminal_bias") {
+        match bias.parse::<f64>() {
+            Ok(bias) if bias.is_finite() && bias >= 0.0 => {}
+            _ => {
+                return Err(anyhow!(
+                    "option `terminal_bias` should be a non-negative number, got `{bias}`"
+                ))
+            }
+        }
+    }
+
+    if let Some(Err(max)) = g.options.get_int("max_generated_bytes") {
+        return Err(anyhow!(
+            "option `max_generated_bytes` should be a non-negative integer, got `{max}`"
+        ));
+    }
+
+    if let Some(rule) = g.message_rule() {
+        if !g.productions.contains_key(&rule) {
+            return Err(anyhow!(
+                "option `message` names rule `{rule}` which is not defined"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
 fn resolve_names(g: &Grammar) -> ValidateResult {
     let mut errors = HashSet::new();
 
     for productions in &g.productions {
         for production in productions.1 {
             for token in production {
-                let Token::Identifier(i) = token else {
+                let Token::Identifier(i) = token.innermost() else {
                     continue;
                 };
 
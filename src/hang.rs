@@ -0,0 +1,218 @@
+use std::{
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, Instant},
+};
+
+use anyhow::bail;
+
+use crate::{
+    analysys, bench,
+    configuration::{EvaluatorKind, FuzzConfig},
+    execution::{self, RunTrace},
+    fuzz_thread,
+    fuzzing::Evaluator,
+    sample::{Sample, TreeNode, TreeNodeItem},
+    storage::write_atomic,
+};
+
+/// Directory inside output directory receiving inputs target timed out on.
+pub const HANG_DIR: &str = "hangs";
+
+/// number of distinct functions reported as the place target was stuck in
+const STUCK_FUNCTIONS: usize = 10;
+
+/// Save input target timed out on under its content hash, returns path
+/// of saved hang or none if the same input is saved already.
+pub fn save_hang(output: &Path, sample: &Sample) -> std::io::Result<Option<PathBuf>> {
+    let directory = output.join(HANG_DIR);
+    std::fs::create_dir_all(&directory)?;
+
+    let path = directory.join(format!("{:016x}", sample.content_hash()));
+
+    if path.exists() {
+        return Ok(None);
+    }
+
+    write_atomic(&path, sample.get_folded())?;
+
+    Ok(Some(path))
+}
+
+/// path report of functions target was stuck in is written to
+fn stuck_report_path(hang: &Path) -> PathBuf {
+    let mut path = hang.as_os_str().to_owned();
+    path.push(".stuck");
+    PathBuf::from(path)
+}
+
+fn sample_of(content: Vec<u8>) -> Sample {
+    let tree: TreeNode = TreeNodeItem::Data(content, None).into();
+    tree.fold_into_sample()
+}
+
+/// Run target on input, returns whether it timed out and how long it ran.
+fn times_out(
+    evaluator: &mut dyn Evaluator<Item = Sample, EvalResult = RunTrace>,
+    content: &[u8],
+) -> Result<(bool, Duration), anyhow::Error> {
+    let started = Instant::now();
+
+    match evaluator.score(sample_of(content.to_vec())) {
+        Ok(_) => Ok((false, started.elapsed())),
+        Err(e) if execution::is_timeout(&e) => Ok((true, started.elapsed())),
+        Err(e) => Err(e),
+    }
+}
+
+struct Minimized {
+    content: Vec<u8>,
+    runs: usize,
+    /// execution time of minimized input until it was killed
+    elapsed: Duration,
+}
+
+/// Remove chunks of halving size from input while target still times out on it.
+fn bisect(
+    evaluator: &mut dyn Evaluator<Item = Sample, EvalResult = RunTrace>,
+    mut content: Vec<u8>,
+    mut elapsed: Duration,
+) -> Result<Minimized, anyhow::Error> {
+    let mut runs = 0;
+    let mut chunk = content.len().div_ceil(2);
+
+    while chunk > 0 {
+        let mut start = 0;
+
+        while start < content.len() {
+            let end = (start + chunk).min(content.len());
+            let candidate = [&content[..start], &content[end..]].concat();
+
+            runs += 1;
+
+            match times_out(evaluator, &candidate)? {
+                (true, candidate_elapsed) => {
+                    content = candidate;
+                    elapsed = candidate_elapsed;
+                }
+                (false, _) => start = end,
+            }
+        }
+
+        println!(
+            "{} bytes left after removing chunks of {chunk}",
+            content.len()
+        );
+
+        chunk /= 2;
+    }
+
+    Ok(Minimized {
+        content,
+        runs,
+        elapsed,
+    })
+}
+
+/// Names of functions traced target was last hitting before it was killed.
+#[cfg(target_os = "linux")]
+fn stuck_functions(
+    config: &FuzzConfig,
+    content: &[u8],
+) -> Result<Option<Vec<String>>, anyhow::Error> {
+    let mapping = analysys::analyze_binary(&config.binary.path)?
+        .with_symbol_filter(&config.binary.symbol_filter());
+
+    let mut evaluator =
+        fuzz_thread::trace_evaluator(config, &fuzz_thread::load_grammar(config)?, mapping);
+
+    evaluator.stuck_functions(&sample_of(content.to_vec()), STUCK_FUNCTIONS)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn stuck_functions(
+    _config: &FuzzConfig,
+    _content: &[u8],
+) -> Result<Option<Vec<String>>, anyhow::Error> {
+    bail!("tracing is only supported on Linux")
+}
+
+fn minimize(config: &FuzzConfig, hang: &Path, output: &Path) -> Result<(), anyhow::Error> {
+    let Some(timeout) = config.binary.timeout else {
+        bail!("hangs can only be minimized with timeout, check `binary.timeout`");
+    };
+
+    let content = std::fs::read(hang)?;
+
+    let mut evaluator =
+        bench::build_evaluator(config, config.binary.evaluator, config.binary.pass_style)?;
+
+    let (timed_out, elapsed) = times_out(evaluator.as_mut(), &content)?;
+
+    if !timed_out {
+        bail!(
+            "target finished in {}ms which is under timeout of {timeout}ms, nothing to minimize",
+            elapsed.as_millis()
+        );
+    }
+
+    println!(
+        "minimizing hang of {} bytes, target runs over timeout of {timeout}ms on it",
+        content.len()
+    );
+
+    let original = content.len();
+    let minimized = bisect(evaluator.as_mut(), content, elapsed)?;
+
+    write_atomic(output, &minimized.content)?;
+
+    println!(
+        "minimized hang from {original} to {} bytes in {} runs, target was killed after {}ms, saved as {}",
+        minimized.content.len(),
+        minimized.runs,
+        minimized.elapsed.as_millis(),
+        output.display()
+    );
+
+    if config.binary.evaluator != EvaluatorKind::Trace {
+        return Ok(());
+    }
+
+    match stuck_functions(config, &minimized.content)? {
+        Some(functions) => {
+            let report = stuck_report_path(output);
+            write_atomic(&report, format!("{}\n", functions.join("\n")).as_bytes())?;
+
+            println!("last functions hit before target was killed (most recent last):");
+            for function in &functions {
+                println!("  {function}");
+            }
+            println!("written to {}", report.display());
+        }
+        None => println!("target finished in time when traced, stuck location is unknown"),
+    }
+
+    Ok(())
+}
+
+/// Shrink saved hang to smaller input target still times out on and
+/// record functions it was stuck in.
+pub fn minimize_hang(config: &FuzzConfig, hang: &Path, output: Option<&Path>) {
+    if !hang.exists() {
+        eprintln!("hang {} does not exist", hang.display());
+        process::exit(exitcode::NOINPUT)
+    }
+
+    let output = output.map(Path::to_path_buf).unwrap_or_else(|| {
+        let mut path = hang.as_os_str().to_owned();
+        path.push(".min");
+        PathBuf::from(path)
+    });
+
+    execution::disable_aslr();
+
+    if let Err(e) = minimize(config, hang, &output) {
+        eprintln!("failed to minimize hang: {e:#}");
+        process::exit(exitcode::SOFTWARE)
+    }
+}
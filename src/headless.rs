@@ -0,0 +1,60 @@
+use std::{
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    execution::ExecResult,
+    state::{FuzzerStatus, Library, State, AM},
+};
+
+const STATS_INTERVAL: Duration = Duration::from_secs(10);
+
+fn report(library: &AM<Library>, state: &AM<State>) {
+    let (paths, crashes) = {
+        let library = library.lock().unwrap();
+
+        let crashes = library
+            .iter()
+            .filter(|(trace, _entry, _sample)| matches!(trace.result, ExecResult::Signal))
+            .count();
+
+        (library.len(), crashes)
+    };
+
+    let state = state.lock().unwrap();
+
+    let (covered, total) = state.coverage();
+
+    eprintln!(
+        "runs: {}, speed: {}, unique paths: {paths}, unique crashes: {crashes}, functions covered: {covered} / {total}",
+        state.tested_samples,
+        state
+            .execution_speed()
+            .map(|execs| format!("{:.1}/s", execs))
+            .unwrap_or_else(|| "n/a".to_string()),
+    );
+}
+
+/// Periodically print stats to stderr until fuzzing thread finishes.
+/// Messages are echoed by logger itself. There is no one to retry
+/// after target error, so fuzzing is stopped as soon as it pauses.
+pub fn serve_headless<T>(library: AM<Library>, state: AM<State>, fuzzer: &JoinHandle<T>) {
+    let mut last_report = Instant::now();
+
+    while !fuzzer.is_finished() {
+        thread::sleep(Duration::from_millis(100));
+
+        if let FuzzerStatus::Paused(_) = state.lock().unwrap().status {
+            eprintln!("stopping after target error");
+            break;
+        }
+
+        if last_report.elapsed() >= STATS_INTERVAL {
+            report(&library, &state);
+            last_report = Instant::now();
+        }
+    }
+
+    report(&library, &state);
+}
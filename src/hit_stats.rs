@@ -0,0 +1,127 @@
+use std::{collections::HashMap, fmt::Write, path::Path};
+
+use serde_derive::Serialize;
+
+use crate::{analysys::ElfInfo, execution::RunTrace, storage::write_atomic};
+
+/// Number of executions hitting every function of target over the whole campaign.
+#[derive(Default)]
+pub struct HitStats {
+    /// name and offset of every analyzed function
+    functions: Vec<(String, usize)>,
+    executions: usize,
+    hits: HashMap<usize, usize>,
+}
+
+#[derive(Serialize)]
+struct FunctionHits<'a> {
+    function: &'a str,
+    offset: usize,
+    executions: usize,
+    share: f64,
+}
+
+#[derive(Serialize)]
+struct HitReport<'a> {
+    executions: usize,
+    functions: Vec<FunctionHits<'a>>,
+}
+
+impl HitStats {
+    pub fn new(mapping: &ElfInfo) -> Self {
+        HitStats {
+            functions: mapping
+                .functions
+                .iter()
+                .map(|function| (function.name.clone(), function.offset))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// name of function at given offset, offset itself if target was not analyzed
+    pub fn function_name(&self, offset: usize) -> String {
+        self.functions
+            .iter()
+            .find(|(_name, known)| *known == offset)
+            .map_or_else(|| format!("{offset:#x}"), |(name, _offset)| name.clone())
+    }
+
+    pub fn record(&mut self, trace: &RunTrace) {
+        self.executions += 1;
+
+        for point in trace.trajectory.keys() {
+            *self.hits.entry(*point).or_default() += 1;
+        }
+    }
+
+    /// functions from hottest to never hit ones
+    fn report(&self) -> HitReport<'_> {
+        let mut functions = self
+            .functions
+            .iter()
+            .map(|(name, offset)| {
+                let executions = self.hits.get(offset).copied().unwrap_or_default();
+
+                FunctionHits {
+                    function: name,
+                    offset: *offset,
+                    executions,
+                    share: executions as f64 / self.executions.max(1) as f64,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        functions.sort_by(|a, b| {
+            b.executions
+                .cmp(&a.executions)
+                .then_with(|| a.function.cmp(b.function))
+        });
+
+        HitReport {
+            executions: self.executions,
+            functions,
+        }
+    }
+
+    /// CSV field quoted when needed, demangled names contain commas and quotes
+    fn csv_field(value: &str) -> String {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+
+    /// Write stats as JSON if path ends with `.json`, as CSV otherwise.
+    pub fn write(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let report = self.report();
+
+        let json = path
+            .extension()
+            .is_some_and(|extension| extension == "json");
+
+        let content = if json {
+            serde_json::to_string_pretty(&report)?
+        } else {
+            let mut content = String::from("function,offset,executions,share\n");
+
+            for function in &report.functions {
+                writeln!(
+                    content,
+                    "{},{:#x},{},{:.6}",
+                    Self::csv_field(function.function),
+                    function.offset,
+                    function.executions,
+                    function.share
+                )?;
+            }
+
+            content
+        };
+
+        write_atomic(path, content.as_bytes())?;
+
+        Ok(())
+    }
+}
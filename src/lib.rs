@@ -0,0 +1,4 @@
+//! Parts of fuzzer shared with its binary: scheduling strategies and
+//! scoring helpers they are built from.
+
+pub mod scheduler;
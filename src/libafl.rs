@@ -0,0 +1,54 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::Path,
+};
+
+use crate::{state::Library, storage::write_atomic};
+
+/// Directory receiving corpus export when `output.libafl_corpus` is not set.
+pub const DEFAULT_EXPORT_DIR: &str = "libafl-corpus";
+
+/// Name of exported testcase, library name if entry has one or hash of content otherwise.
+pub fn testcase_name(name: Option<&String>, content: &[u8]) -> String {
+    if let Some(name) = name {
+        return name.clone();
+    }
+
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Write library in layout of LibAFL on-disk corpus: every testcase is
+/// stored as `<name>` with json metadata in `.<name>.metadata`. Returns
+/// number of exported testcases.
+pub fn export_corpus(library: &Library, directory: &Path) -> std::io::Result<usize> {
+    std::fs::create_dir_all(directory)?;
+
+    for (_trace, entry, sample) in library.iter() {
+        let content = sample.get_folded();
+        let name = testcase_name(entry.unique_name.as_ref(), content);
+        let path = directory.join(&name);
+
+        // testcases exported earlier are kept as is
+        if std::fs::read(&path).is_ok_and(|existing| existing == content) {
+            continue;
+        }
+
+        write_atomic(&path, content)?;
+
+        let metadata = serde_json::json!({
+            "metadata": { "map": {} },
+            "exec_time": entry.exec_time,
+            "executions": 0,
+        });
+
+        write_atomic(
+            &directory.join(format!(".{name}.metadata")),
+            &serde_json::to_vec_pretty(&metadata).map_err(std::io::Error::from)?,
+        )?;
+    }
+
+    Ok(library.len())
+}
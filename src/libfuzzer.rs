@@ -0,0 +1,149 @@
+use std::{
+    path::{Path, PathBuf},
+    process,
+};
+
+use crate::configuration::{
+    BinaryConfig, ExitCodeFilter, FuzzConfig, InputOptions, OutputOptions, PassStyle,
+};
+
+/// Options understood from libFuzzer-style command line.
+#[derive(Debug, Default)]
+struct EngineOptions {
+    runs: Option<usize>,
+    max_total_time: Option<u64>,
+    artifact_prefix: Option<String>,
+    corpus: Vec<String>,
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> T {
+    match value.parse() {
+        Ok(value) => value,
+        Err(_) => {
+            eprintln!("invalid value `{value}` of -{flag}");
+            process::exit(exitcode::USAGE)
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> EngineOptions {
+    let mut options = EngineOptions::default();
+
+    for arg in args {
+        let Some(flag) = arg.strip_prefix('-') else {
+            options.corpus.push(arg.clone());
+            continue;
+        };
+
+        let (name, value) = flag.split_once('=').unwrap_or((flag, ""));
+
+        match name {
+            // libFuzzer treats -runs=-1 as unlimited
+            "runs" if value.starts_with('-') => options.runs = None,
+            "runs" => options.runs = Some(parse_number(name, value)),
+            "max_total_time" => options.max_total_time = Some(parse_number(name, value)),
+            "artifact_prefix" => options.artifact_prefix = Some(value.to_string()),
+            _ => eprintln!("ignoring unsupported flag {arg}"),
+        }
+    }
+
+    options
+}
+
+/// Directory part of libFuzzer artifact prefix, `out/` and `out/crash-` both mean `out`.
+fn artifact_directory(prefix: &str) -> PathBuf {
+    if prefix.ends_with('/') {
+        return PathBuf::from(prefix);
+    }
+
+    match Path::new(prefix).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// libFuzzer starts from empty input when corpus is empty, seed directory
+/// needs at least one file to do the same.
+fn prepare_corpus(corpus: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(corpus)?;
+
+    if std::fs::read_dir(corpus)?.next().is_none() {
+        std::fs::write(corpus.join("empty"), [])?;
+    }
+
+    Ok(())
+}
+
+/// Build configuration from libFuzzer-style arguments and environment
+/// (`CORPUS_DIR`, `ARTIFACT_PREFIX`). Target receives path to input file
+/// and fuzzing runs headless.
+pub fn engine_config(target: &str, args: &[String]) -> FuzzConfig {
+    let options = parse_args(args);
+
+    let Some(corpus) = options
+        .corpus
+        .first()
+        .cloned()
+        .or_else(|| std::env::var("CORPUS_DIR").ok())
+    else {
+        eprintln!("no corpus directory given, pass it as argument or set CORPUS_DIR");
+        process::exit(exitcode::USAGE)
+    };
+
+    if options.corpus.len() > 1 {
+        eprintln!("using only first corpus directory {corpus}");
+    }
+
+    if let Err(e) = prepare_corpus(Path::new(&corpus)) {
+        eprintln!("failed to prepare corpus directory {corpus}: {e}");
+        process::exit(exitcode::IOERR)
+    }
+
+    let directory = options
+        .artifact_prefix
+        .or_else(|| std::env::var("ARTIFACT_PREFIX").ok())
+        .map(|prefix| artifact_directory(&prefix).to_string_lossy().to_string())
+        .unwrap_or_else(|| ".".to_string());
+
+    let mut config = FuzzConfig {
+        hash: String::new(),
+        binary: BinaryConfig {
+            path: target.to_string(),
+            pass_style: PassStyle::File,
+            interesting_codes: ExitCodeFilter::Any,
+            evaluator: Default::default(),
+            trace_identity: Default::default(),
+            timeout: None,
+            stuck_samples: 3,
+            reanalyze_on_change: false,
+            detector: None,
+            confirmation: None,
+            prune_warmup: None,
+            prune_threshold: 0.99,
+            include_symbols: vec![],
+            exclude_symbols: vec![],
+            run_as: None,
+            crash_signals: vec![],
+            wrapper: vec![],
+            stdin: Default::default(),
+            stdin_chunk: 64,
+            stdin_delay: 10,
+            message_sync: Default::default(),
+            read_response: false,
+        },
+        input: InputOptions::Seeds { seeds: corpus },
+        output: OutputOptions {
+            directory,
+            headless: true,
+            ..Default::default()
+        },
+        log: Default::default(),
+        fuzzing: Default::default(),
+        triage: Default::default(),
+    };
+
+    config.fuzzing.max_runs = options.runs;
+    config.fuzzing.max_time = options.max_total_time;
+
+    config
+}
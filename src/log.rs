@@ -1,3 +1,7 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
 use std::time::Instant;
 
@@ -7,12 +11,86 @@ use lazy_static::lazy_static;
 use ringbuffer::RingBufferWrite;
 use ringbuffer::{AllocRingBuffer, RingBufferExt};
 
+use crate::{confirm::Confirmation, execution::Hits, triage::CasrSummary};
+
 lazy_static! {
-    static ref BUFFER: Mutex<AllocRingBuffer<String>> =
+    static ref BUFFER: Mutex<AllocRingBuffer<LogMessage>> =
         Mutex::new(AllocRingBuffer::with_capacity(128));
 }
 
-pub fn write_message(message: &str) {
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+static ECHO: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Debug,
+    Info,
+    Warning,
+    Error,
+    Crash,
+}
+
+impl Level {
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warning => "warning",
+            Level::Error => "error",
+            Level::Crash => "crash",
+        }
+    }
+
+    /// next level in filtering order, wrapping around to the least severe one
+    pub fn next(self) -> Self {
+        match self {
+            Level::Debug => Level::Info,
+            Level::Info => Level::Warning,
+            Level::Warning => Level::Error,
+            Level::Error => Level::Crash,
+            Level::Crash => Level::Debug,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LogMessage {
+    pub level: Level,
+    pub text: String,
+}
+
+/// resize message buffer keeping the most recent messages
+pub fn set_capacity(capacity: usize) {
+    let mut buffer = BUFFER.lock().unwrap();
+
+    let mut resized = AllocRingBuffer::with_capacity(capacity.max(1).next_power_of_two());
+
+    for message in buffer.iter() {
+        resized.push(message.clone());
+    }
+
+    *buffer = resized;
+}
+
+/// record debug messages, dropped otherwise
+pub fn set_verbose(verbose: bool) {
+    VERBOSE.store(verbose, Ordering::SeqCst);
+}
+
+pub fn is_verbose() -> bool {
+    VERBOSE.load(Ordering::SeqCst)
+}
+
+/// additionally print every message to stderr, used when running without terminal interface
+pub fn set_echo(echo: bool) {
+    ECHO.store(echo, Ordering::SeqCst);
+}
+
+pub fn write_message(level: Level, message: &str) {
+    if level == Level::Debug && !is_verbose() {
+        return;
+    }
+
     let time = Local::now();
 
     let human_readable = format!(
@@ -24,25 +102,49 @@ pub fn write_message(message: &str) {
         time.second()
     );
 
+    let text = format!("[{human_readable}] {message}");
+
+    if ECHO.load(Ordering::SeqCst) {
+        eprintln!("{text}");
+    }
+
     let mut buffer = BUFFER.lock().unwrap();
 
-    buffer.push(format!("[{human_readable}] {message}"))
+    buffer.push(LogMessage { level, text })
 }
 
 macro_rules! log{
+    (debug: $($e:expr),+) => {
+        crate::log::write_message(crate::log::Level::Debug, &format!($($e),+))
+    };
+    (warning: $($e:expr),+) => {
+        crate::log::write_message(crate::log::Level::Warning, &format!($($e),+))
+    };
+    (error: $($e:expr),+) => {
+        crate::log::write_message(crate::log::Level::Error, &format!($($e),+))
+    };
+    (crash: $($e:expr),+) => {
+        crate::log::write_message(crate::log::Level::Crash, &format!($($e),+))
+    };
     ($($e:expr),+) => {
-        crate::log::write_message(&format!($($e),+))
-    }
+        crate::log::write_message(crate::log::Level::Info, &format!($($e),+))
+    };
 }
 
 pub(crate) use log;
 use serde_derive::Serialize;
 
-pub fn pull_messages(n: usize) -> Vec<String> {
+pub fn pull_messages(n: usize, min_level: Level) -> Vec<LogMessage> {
     let mut items = {
         let buffer = BUFFER.lock().unwrap();
 
-        buffer.iter().rev().take(n).map(Clone::clone).collect_vec()
+        buffer
+            .iter()
+            .rev()
+            .filter(|message| message.level >= min_level)
+            .take(n)
+            .map(Clone::clone)
+            .collect_vec()
     };
     items.reverse();
     items
@@ -57,14 +159,193 @@ pub enum NewPathKind {
 
 #[derive(Clone, Debug, Serialize)]
 pub struct FuzzingEvent {
+    /// seconds passed since start of campaign
     pub time_as_seconds: f64,
+    pub campaign: String,
     pub kind: FuzzingEventKind,
 }
 
+/// Function hit different number of times than by parent sample.
+#[derive(Clone, Debug, Serialize)]
+pub struct HitChange {
+    pub function: String,
+    /// none if parent did not hit function
+    pub before: Option<Hits>,
+    pub after: Hits,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SampleInfo {
+    pub sample_size: usize,
+    pub trajectory_size: usize,
+    /// name of library entry that was mutated to obtain sample
+    pub parent: Option<String>,
+}
+
 #[derive(Clone, Debug, Serialize)]
 #[serde(tag = "type")]
 pub enum FuzzingEventKind {
-    NewPath { kind: NewPathKind, trace_id: String },
+    /// marks start of a new run so that appended logs can be told apart
+    CampaignStart {
+        config_hash: String,
+        target: String,
+        start_time: String,
+        functions_total: usize,
+        workers: usize,
+        /// configuration after profiles and command line overrides are applied
+        config: serde_json::Value,
+    },
+
+    NewPath {
+        kind: NewPathKind,
+        trace_id: String,
+        #[serde(flatten)]
+        sample: SampleInfo,
+        /// functions no earlier execution hit
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        new_functions: Vec<String>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        hit_changes: Vec<HitChange>,
+    },
+
+    SizeImprovement {
+        trace_id: String,
+        delta: usize,
+        #[serde(flatten)]
+        sample: SampleInfo,
+    },
+
+    /// repeated runs of new crash, flaky if not every one of them crashed
+    CrashReproduction {
+        trace_id: String,
+        runs: usize,
+        crashes: usize,
+        flaky: bool,
+    },
+
+    /// core dumped by target on crash, kept next to crash sample
+    CoreDump { trace_id: String, path: String },
+
+    /// casr analysis of new crash
+    CrashTriage {
+        trace_id: String,
+        #[serde(flatten)]
+        report: CasrSummary,
+        /// earlier crash with the same classification and crash line
+        #[serde(skip_serializing_if = "Option::is_none")]
+        duplicate_of: Option<String>,
+    },
+
+    /// verdict of confirmation build on new path or crash
+    Confirmation {
+        trace_id: String,
+        #[serde(flatten)]
+        confirmation: Confirmation,
+    },
+
+    /// periodic snapshot of campaign progress
+    Stats {
+        executions: usize,
+        /// mutated samples skipped as duplicates of recent ones
+        duplicates: usize,
+        /// executions that failed with error
+        failed: usize,
+        /// failed executions where target could not be started
+        spawn_failures: usize,
+        /// failed executions where input could not be written to target
+        input_failures: usize,
+        /// executions where target closed stdin before reading whole input
+        input_cut_short: usize,
+        /// executions killed after timeout
+        timeouts: usize,
+        /// executions that did not hit any function
+        empty_traces: usize,
+        unique_paths: usize,
+        unique_crashes: usize,
+        functions_covered: usize,
+        functions_total: usize,
+    },
+}
 
-    SizeImprovement { trace_id: String, delta: usize },
+/// Line-delimited json sink for fuzzing events with size-based rotation.
+pub struct EventLog {
+    campaign: String,
+    started: Instant,
+    path: PathBuf,
+    file: File,
+    written: u64,
+    max_size: Option<u64>,
+    keep: usize,
+}
+
+impl EventLog {
+    /// open event log for appending, creating it (and parent directories) if needed
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        max_size: Option<u64>,
+        keep: usize,
+        campaign: String,
+    ) -> std::io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+
+        Ok(EventLog {
+            campaign,
+            started: Instant::now(),
+            path,
+            file,
+            written,
+            max_size,
+            keep,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{index}"));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        if self.keep > 0 {
+            for index in (1..self.keep).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    std::fs::rename(from, self.rotated_path(index + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        self.file = File::create(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+
+    /// stamp event with campaign id and time since log was opened and write it out
+    pub fn record(&mut self, kind: FuzzingEventKind) -> std::io::Result<()> {
+        let event = FuzzingEvent {
+            time_as_seconds: self.started.elapsed().as_secs_f64(),
+            campaign: self.campaign.clone(),
+            kind,
+        };
+
+        let line = format!("{}\n", serde_json::to_string(&event).unwrap());
+
+        if let Some(max_size) = self.max_size {
+            if self.written > 0 && self.written + line.len() as u64 > max_size {
+                self.rotate()?;
+            }
+        }
+
+        self.file.write_all(line.as_bytes())?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
 }
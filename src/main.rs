@@ -1,66 +1,240 @@
+// tracing is Linux only, code and imports serving it are left unused elsewhere
+#![cfg_attr(not(target_os = "linux"), allow(dead_code, unused_imports))]
+
+use clap::Parser;
+use cli::{Cli, Command, ConfigCommand};
+use configuration::FuzzConfig;
 use fuzz_thread::spawn_fuzzer;
 
-use ptracer::disable_aslr;
 use sample_library::VectorLibrary;
-use state::{State, FUZZER_RUNNNIG};
-use std::sync::{Arc, Mutex};
+use state::{ShutdownToken, State};
+use std::{
+    io::IsTerminal,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
+use headless::serve_headless;
 use std::process;
 use ui::serve_ui;
 
-use crate::configuration::{load_config, ConfigReadError};
+use crate::{
+    campaign::StateFiles,
+    configuration::{load_config, validate_config, ConfigFormat, ConfigReadError},
+    storage::{LockError, OutputLock},
+};
 
 mod analysys;
+mod bench;
+mod campaign;
+mod child_io;
+mod cli;
+mod colorization;
 mod configuration;
+mod confirm;
+mod coredump;
 mod execution;
+mod export;
 mod flags;
 mod fuzz_thread;
 mod fuzzing;
 mod grammar;
+mod hang;
+mod headless;
+mod hit_stats;
+mod libafl;
+mod libfuzzer;
 mod mutation;
+mod privileges;
+mod provenance;
+mod replay;
 mod sample;
 mod sample_library;
+mod session;
+mod show;
+mod snapshot;
 mod ui;
 
 mod log;
 mod state;
+mod storage;
+mod triage;
 
 pub(crate) use log::log;
 
+fn read_config(cli: &Cli) -> FuzzConfig {
+    let path = cli.config_path();
+
+    let mut config = match load_config(&path, cli.profile.as_deref()) {
+        Ok(config) => config,
+        Err(ConfigReadError::ReadError(e)) => {
+            eprintln!("failed to read {path}: {e}");
+            process::exit(exitcode::IOERR)
+        }
+
+        Err(ConfigReadError::ParseError(e)) => {
+            eprintln!("{e}");
+            process::exit(exitcode::CONFIG)
+        }
+
+        Err(ConfigReadError::UnknownProfile { name, available }) => {
+            eprintln!(
+                "profile `{name}` is not defined in {path}, available profiles: [{}]",
+                available.join(", ")
+            );
+            process::exit(exitcode::CONFIG)
+        }
+
+        Err(ConfigReadError::UndefinedVariable(name)) => {
+            eprintln!("environment variable `{name}` referenced in {path} is not set");
+            process::exit(exitcode::CONFIG)
+        }
+    };
+
+    cli.overrides.apply(&mut config);
+
+    config
+}
+
+fn init_config(path: &str, force: bool) {
+    if std::path::Path::new(path).exists() && !force {
+        eprintln!("{path} already exists, use --force to overwrite it");
+        process::exit(exitcode::CANTCREAT)
+    }
+
+    let content = match ConfigFormat::from_path(path) {
+        ConfigFormat::Toml => cli::CONFIG_TEMPLATE.to_string(),
+        format => {
+            let template: toml::Value = toml::from_str(cli::CONFIG_TEMPLATE).unwrap();
+            format.serialize(&template).unwrap()
+        }
+    };
+
+    if let Err(e) = std::fs::write(path, content) {
+        eprintln!("failed to write {path}: {e}");
+        process::exit(exitcode::IOERR)
+    }
+
+    println!("configuration template written to {path}");
+}
+
+fn show_config(config: &FuzzConfig) {
+    match ConfigFormat::Toml.serialize(config) {
+        Ok(content) => print!("{content}"),
+        Err(e) => {
+            eprintln!("failed to serialize configuration: {e}");
+            process::exit(exitcode::SOFTWARE)
+        }
+    }
+}
+
 fn main() {
-    unsafe {
-        disable_aslr();
+    let cli = Cli::parse();
+
+    match cli.command.as_ref().unwrap_or(&Command::Fuzz) {
+        Command::Config {
+            action: ConfigCommand::Init { force },
+        } => init_config(&cli.config_path(), *force),
+        Command::Config {
+            action: ConfigCommand::Show,
+        } => show_config(&read_config(&cli)),
+        Command::Show { sample } => show::show_sample(sample),
+        Command::Export { file, names } => export::export_tests(&read_config(&cli), names, file),
+        Command::Bench { seconds, sample } => {
+            bench::run_bench(&read_config(&cli), sample.as_deref(), *seconds)
+        }
+        Command::ReplayDir { directory } => replay::replay_dir(&read_config(&cli), directory),
+        Command::Verify { crashes } => {
+            let config = read_config(&cli);
+            let directory = crashes
+                .clone()
+                .unwrap_or_else(|| config.output.directory.clone().into());
+
+            replay::verify_crashes(&config, &directory)
+        }
+        Command::MinimizeHang { sample, output } => {
+            hang::minimize_hang(&read_config(&cli), sample, output.as_deref())
+        }
+        Command::Libfuzzer { target, args } => {
+            let mut config = libfuzzer::engine_config(target, args);
+            cli.overrides.apply(&mut config);
+            run_fuzzer(config, cli.force, cli.state_files())
+        }
+        Command::Fuzz => run_fuzzer(read_config(&cli), cli.force, cli.state_files()),
     }
+}
+
+fn run_fuzzer(mut config: FuzzConfig, force: bool, state_files: StateFiles) {
+    execution::disable_aslr();
+
+    let lock_path = std::path::Path::new(&config.output.directory).join(storage::LOCK_FILE);
 
+    let shutdown = ShutdownToken::new();
+
+    let handler_shutdown = shutdown.clone();
+
+    // first Ctrl+C lets fuzzing finish current execution, second one exits at once
     ctrlc::set_handler(move || {
-        println!("received Ctrl+C!");
+        if !handler_shutdown.is_cancelled() {
+            println!("received Ctrl+C, stopping (press again to exit immediately)");
+            handler_shutdown.cancel();
+            return;
+        }
 
-        unsafe { FUZZER_RUNNNIG.store(false, std::sync::atomic::Ordering::SeqCst) };
+        storage::release_lock(&lock_path);
 
         process::exit(exitcode::SOFTWARE);
     })
     .expect("Error setting Ctrl-C handler");
 
-    let config = match load_config("fuzz.toml") {
-        Ok(config) => config,
-        Err(ConfigReadError::ReadError(e)) => {
-            eprintln!("failed to read fuzz.toml: {e}");
-            process::exit(exitcode::IOERR)
-        }
+    if let Err(e) = validate_config(&config) {
+        eprintln!("problems found in configuration:");
+        eprintln!("{e}");
+        process::exit(exitcode::CONFIG)
+    }
 
-        Err(ConfigReadError::ParseError(e)) => {
+    let lock = match OutputLock::acquire(std::path::Path::new(&config.output.directory), force) {
+        Ok(lock) => lock,
+        Err(e @ LockError::Locked(_)) => {
+            eprintln!("{e}, use --force to start anyway");
+            process::exit(exitcode::TEMPFAIL)
+        }
+        Err(e) => {
             eprintln!("{e}");
-            process::exit(exitcode::CONFIG)
+            process::exit(exitcode::CANTCREAT)
         }
     };
 
-    let config = Box::leak(Box::new(config));
+    // raw mode and escape sequences of terminal interface would end up in redirected output
+    if !config.output.headless && !std::io::stdout().is_terminal() {
+        eprintln!("stdout is not a terminal, running headless");
+        config.output.headless = true;
+    }
+
+    let config = Arc::new(config);
+
+    log::set_capacity(config.log.capacity);
+    log::set_verbose(config.output.debug);
+    log::set_echo(config.output.headless);
+
+    let library = Arc::new(Mutex::new(
+        VectorLibrary::new()
+            .with_size_weight(config.fuzzing.size_weight)
+            .with_scheduler(config.fuzzing.scheduler.build()),
+    ));
 
-    let library = Arc::new(Mutex::new(VectorLibrary::new()));
+    let mut state = State::new(Duration::from_secs_f64(config.output.speed_window));
+    state.exploit_crashes = config.fuzzing.exploit_crashes;
 
-    let state = Arc::new(Mutex::new(State::new()));
+    let state = Arc::new(Mutex::new(state));
 
-    let fuzzer_thread_handle = match spawn_fuzzer(config, library.clone(), state.clone()) {
+    let fuzzer_thread_handle = match spawn_fuzzer(
+        config.clone(),
+        library.clone(),
+        state.clone(),
+        shutdown.clone(),
+        state_files,
+    ) {
         Ok(handle) => handle,
         Err(e) => {
             eprintln!("error while spawning fuzzer thread: {e}");
@@ -68,15 +242,20 @@ fn main() {
         }
     };
 
-    let ui_errors = if !config.output.debug {
-        serve_ui(library, state, config)
-    } else {
+    let ui_errors = if config.output.headless {
+        serve_headless(library, state, &fuzzer_thread_handle);
         Ok(())
+    } else {
+        serve_ui(library, state, config.clone(), &shutdown)
     };
 
-    unsafe { FUZZER_RUNNNIG.store(false, std::sync::atomic::Ordering::SeqCst) };
+    shutdown.cancel();
+
+    let thread_result = fuzzer_thread_handle.join();
+
+    drop(lock);
 
-    let _ = fuzzer_thread_handle.join().map_err(|e| {
+    let _ = thread_result.map_err(|e| {
         eprintln!("error inside fuzzing thread: {e:?}");
         process::exit(exitcode::SOFTWARE)
     });
@@ -7,6 +7,13 @@ use crate::sample::{Patch, PatchKind, Sample};
 
 pub trait MutateBytes {
     fn mutate(&self, reference: &[u8], library: &[Sample]) -> Patch;
+
+    fn name(&self) -> &'static str;
+
+    /// whether mutation makes small local changes, used when exploiting crashes
+    fn is_conservative(&self) -> bool {
+        false
+    }
 }
 
 lazy_static! {
@@ -48,6 +55,14 @@ impl MutateBytes for BitFlip {
             kind: PatchKind::Replacement(vec![new_data]),
         }
     }
+
+    fn name(&self) -> &'static str {
+        "bit flip"
+    }
+
+    fn is_conservative(&self) -> bool {
+        true
+    }
 }
 
 pub struct Erasure {
@@ -66,6 +81,10 @@ impl MutateBytes for Erasure {
             kind: PatchKind::Erasure(random_size),
         }
     }
+
+    fn name(&self) -> &'static str {
+        "erasure"
+    }
 }
 
 pub struct KnownBytes {
@@ -97,6 +116,14 @@ impl MutateBytes for KnownBytes {
             kind: PatchKind::Replacement(content),
         }
     }
+
+    fn name(&self) -> &'static str {
+        "known bytes"
+    }
+
+    fn is_conservative(&self) -> bool {
+        true
+    }
 }
 
 impl KnownBytes {
@@ -146,6 +173,10 @@ impl MutateBytes for Garbage {
             }
         }
     }
+
+    fn name(&self) -> &'static str {
+        "garbage"
+    }
 }
 
 pub struct CopyFragment {
@@ -198,4 +229,93 @@ impl MutateBytes for CopyFragment {
             kind: PatchKind::Insertion(patch_content),
         }
     }
+
+    fn name(&self) -> &'static str {
+        "copy fragment"
+    }
+}
+
+/// Lengths length-sensitive parsers tend to handle specially: powers of two
+/// with their neighbours and lengths of library samples.
+fn interesting_lengths(library: &[Sample]) -> Vec<usize> {
+    (0..=16)
+        .map(|power| 1usize << power)
+        .flat_map(|length| [length - 1, length, length + 1])
+        .chain(library.iter().map(|item| item.get_folded().len()))
+        .unique()
+        .collect()
+}
+
+pub struct Truncation {}
+
+impl MutateBytes for Truncation {
+    fn mutate(&self, reference: &[u8], library: &[Sample]) -> Patch {
+        let mut rng = rand::thread_rng();
+
+        if reference.is_empty() {
+            return Patch {
+                position: 0,
+                kind: PatchKind::Replacement(vec![]),
+            };
+        }
+
+        let shorter = interesting_lengths(library)
+            .into_iter()
+            .filter(|&length| length < reference.len())
+            .collect_vec();
+
+        let length = if !shorter.is_empty() && rng.gen_bool(0.5) {
+            shorter[rng.gen_range(0..shorter.len())]
+        } else {
+            rng.gen_range(0..reference.len())
+        };
+
+        Patch {
+            position: length,
+            kind: PatchKind::Erasure(reference.len() - length),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "truncation"
+    }
+}
+
+pub struct Padding {
+    pub max_size: usize,
+}
+
+impl MutateBytes for Padding {
+    fn mutate(&self, reference: &[u8], library: &[Sample]) -> Patch {
+        let mut rng = rand::thread_rng();
+
+        let longer = interesting_lengths(library)
+            .into_iter()
+            .filter(|&length| length > reference.len() && length - reference.len() <= self.max_size)
+            .collect_vec();
+
+        let size = if !longer.is_empty() && rng.gen_bool(0.5) {
+            longer[rng.gen_range(0..longer.len())] - reference.len()
+        } else {
+            rng.gen_range(1..=self.max_size)
+        };
+
+        let position = get_random_position(reference);
+
+        let filler = match rng.gen_range(0..4) {
+            0 => 0x00,
+            1 => 0xff,
+            2 => b'A',
+            _ => reference.get(position).copied().unwrap_or(0x00),
+        };
+
+        Patch {
+            position,
+            kind: PatchKind::Insertion(vec![filler; size]),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "padding"
+    }
 }
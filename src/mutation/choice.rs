@@ -1,28 +1,226 @@
+use std::ops::Range;
+
+use itertools::Itertools;
 use rand::Rng;
 
-use crate::fuzzing::Mutator;
+use crate::{
+    colorization::EffectiveBytes,
+    fuzzing::{MutationLevel, Mutator, RunResultStatus},
+    grammar::generation::Generator,
+};
 
 use super::{
     binary_level,
-    tree_level::{self},
+    focus::FocusMask,
+    tree_level::{self, RuleStats, SubtreePool},
 };
 
 pub struct MutationChooser {
     binary: Vec<Box<dyn binary_level::MutateBytes>>,
     tree: Vec<Box<dyn tree_level::MutateTree>>,
+    /// indices of conservative binary mutations
+    conservative: Vec<usize>,
+    use_conservative: bool,
+    rules: RuleStats,
+    /// subtrees of samples that found new paths
+    pool: SubtreePool,
+    /// generator of fresh samples injected instead of mutated ones
+    fresh: Option<Generator>,
+    fresh_rate: f64,
+    /// byte ranges binary mutations avoid or prefer
+    focus: Option<FocusMask>,
+    /// bytes of current sample target inspects, found by colorization
+    effective: Option<EffectiveBytes>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum MutationKind {
+    Binary(usize),
+    Tree(usize),
+    Fresh,
+}
+
+#[derive(Clone, Debug)]
+pub struct MutationInfo {
+    kind: MutationKind,
+    /// bytes of mutated sample that differ from original one
+    changed: Range<usize>,
+}
+
+/// Range of `mutated` left after stripping prefix and suffix shared with `original`.
+fn changed_range(original: &[u8], mutated: &[u8]) -> Range<usize> {
+    let prefix = original
+        .iter()
+        .zip(mutated)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let suffix = original[prefix..]
+        .iter()
+        .rev()
+        .zip(mutated[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    prefix..mutated.len() - suffix
 }
 
 impl Mutator for MutationChooser {
     type Item = crate::sample::Sample;
 
-    type MutInfo = (bool, usize);
+    type MutInfo = MutationInfo;
 
     fn mutate_sample(
         &mut self,
-        mut sample: Self::Item,
+        sample: Self::Item,
         library: &[Self::Item],
     ) -> (Self::Item, Self::MutInfo) {
+        let original = sample.get_folded().to_vec();
+
+        let (mutated, kind) = self.pick_and_apply(sample, library);
+
+        let changed = changed_range(&original, mutated.get_folded());
+
+        (mutated, MutationInfo { kind, changed })
+    }
+
+    fn update_scores(&mut self, index: Self::MutInfo, result: crate::fuzzing::RunResult) {
+        if !self.tree.is_empty() && matches!(result.status, RunResultStatus::New) {
+            self.rules
+                .record_new_path(result.sample.tree(), &index.changed);
+            self.pool.harvest(result.sample.tree());
+        }
+    }
+
+    fn set_conservative(&mut self, conservative: bool) {
+        self.use_conservative = conservative;
+    }
+
+    fn describe(&self, index: &Self::MutInfo) -> &'static str {
+        match index.kind {
+            MutationKind::Binary(idx) => self.binary[idx].name(),
+            MutationKind::Tree(idx) => self.tree[idx].name(),
+            MutationKind::Fresh => Self::FRESH,
+        }
+    }
+
+    fn level(&self, index: &Self::MutInfo) -> MutationLevel {
+        match index.kind {
+            MutationKind::Binary(_) => MutationLevel::Binary,
+            MutationKind::Tree(_) => MutationLevel::Tree,
+            MutationKind::Fresh => MutationLevel::Fresh,
+        }
+    }
+
+    fn set_effective_bytes(&mut self, effective: Option<EffectiveBytes>) {
+        self.effective = effective;
+    }
+}
+
+impl MutationChooser {
+    /// share of binary mutations moved onto effective bytes when they are known
+    const EFFECTIVE_BYTES_PROBA: f64 = 0.75;
+
+    const FRESH: &'static str = "fresh sample";
+
+    /// names of every mutation chooser can report
+    pub fn names(&self) -> Vec<&'static str> {
+        self.binary
+            .iter()
+            .map(|mutation| mutation.name())
+            .chain(self.tree.iter().map(|mutation| mutation.name()))
+            .chain([Self::FRESH])
+            .collect()
+    }
+
+    pub fn new(
+        binary: Vec<Box<dyn binary_level::MutateBytes>>,
+        tree: Vec<Box<dyn tree_level::MutateTree>>,
+    ) -> Self {
+        let conservative = binary
+            .iter()
+            .positions(|mutator| mutator.is_conservative())
+            .collect();
+
+        MutationChooser {
+            binary,
+            tree,
+            conservative,
+            use_conservative: false,
+            rules: RuleStats::default(),
+            pool: SubtreePool::default(),
+            fresh: None,
+            fresh_rate: 0.0,
+            focus: None,
+            effective: None,
+        }
+    }
+
+    /// replace given share of mutated samples with freshly generated ones
+    pub fn with_fresh_samples(mut self, generator: Generator, rate: f64) -> Self {
+        self.fresh = Some(generator);
+        self.fresh_rate = rate;
+        self
+    }
+
+    /// keep binary mutations away from protected bytes and move them into hot ones
+    pub fn with_focus(mut self, focus: FocusMask) -> Self {
+        self.focus = Some(focus);
+        self
+    }
+
+    /// apply binary mutation with given index, sample is returned unchanged
+    /// if mutation can not be placed outside of protected bytes
+    fn apply_binary(
+        &self,
+        idx: usize,
+        sample: crate::sample::Sample,
+        library: &[crate::sample::Sample],
+    ) -> crate::sample::Sample {
+        let mut patch = self.binary[idx].mutate(sample.get_folded(), library);
+
+        if let Some(effective) = &self.effective {
+            if rand::thread_rng().gen_bool(Self::EFFECTIVE_BYTES_PROBA) {
+                if let Some(position) = effective.random_position() {
+                    patch.position = position;
+                }
+            }
+        }
+
+        let patch = match &self.focus {
+            Some(focus) => focus.apply(&sample, patch),
+            None => Some(patch),
+        };
+
+        match patch {
+            Some(patch) => sample.apply_patch(patch),
+            None => sample,
+        }
+    }
+
+    /// apply random mutation returning mutated sample and applied mutation
+    fn pick_and_apply(
+        &self,
+        mut sample: crate::sample::Sample,
+        library: &[crate::sample::Sample],
+    ) -> (crate::sample::Sample, MutationKind) {
         let mut rng = rand::thread_rng();
+
+        if self.use_conservative && !self.conservative.is_empty() {
+            let idx = self.conservative[rng.gen_range(0..self.conservative.len())];
+
+            return (
+                self.apply_binary(idx, sample, library),
+                MutationKind::Binary(idx),
+            );
+        }
+
+        if let Some(generator) = &self.fresh {
+            if rng.gen_bool(self.fresh_rate) {
+                return (generator.generate(), MutationKind::Fresh);
+            }
+        }
+
         loop {
             let m1 = rng.gen_bool(0.7);
             if m1 && !self.tree.is_empty() {
@@ -30,9 +228,9 @@ impl Mutator for MutationChooser {
 
                 let mutator = &self.tree[idx];
 
-                match mutator.mutate(sample, library) {
+                match mutator.mutate(sample, library, &self.rules, &self.pool) {
                     Ok(res) => {
-                        break (res, (m1, idx));
+                        break (res, MutationKind::Tree(idx));
                     }
                     Err(res) => {
                         sample = res;
@@ -41,27 +239,11 @@ impl Mutator for MutationChooser {
             } else {
                 let idx = rng.gen_range(0..self.binary.len());
 
-                let mutator = &self.binary[idx];
-
-                let new_patch = mutator.mutate(sample.get_folded(), library);
-
-                let patched = sample.apply_patch(new_patch);
-
-                break (patched, (m1, idx));
+                break (
+                    self.apply_binary(idx, sample, library),
+                    MutationKind::Binary(idx),
+                );
             }
         }
     }
-
-    fn update_scores(&mut self, _index: Self::MutInfo, _result: crate::fuzzing::RunResult) {
-        //nothing
-    }
-}
-
-impl MutationChooser {
-    pub fn new(
-        binary: Vec<Box<dyn binary_level::MutateBytes>>,
-        tree: Vec<Box<dyn tree_level::MutateTree>>,
-    ) -> Self {
-        MutationChooser { binary, tree }
-    }
 }
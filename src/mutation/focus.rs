@@ -0,0 +1,138 @@
+use std::ops::Range;
+
+use rand::Rng;
+
+use crate::{
+    configuration::FocusOptions,
+    sample::{Patch, PatchKind, Sample, TreeNodeItem},
+};
+
+/// attempts to move patch out of protected bytes before it is dropped
+const RELOCATE_ROLLS: usize = 10;
+
+/// Parts of sample binary mutations must not touch or should prefer, given
+/// as byte offsets or names of grammar rules.
+pub struct FocusMask {
+    protect: Vec<Range<usize>>,
+    protect_rules: Vec<String>,
+    hot: Vec<Range<usize>>,
+    hot_rules: Vec<String>,
+    hot_rate: f64,
+}
+
+fn to_ranges(ranges: &[(usize, usize)]) -> Vec<Range<usize>> {
+    ranges.iter().map(|&(start, end)| start..end).collect()
+}
+
+/// Byte ranges of given sample: fixed ones cut to sample size and spans of nodes produced by given rules.
+fn resolve(sample: &Sample, fixed: &[Range<usize>], rules: &[String]) -> Vec<Range<usize>> {
+    let size = sample.get_folded().len();
+
+    let mut ranges: Vec<_> = fixed
+        .iter()
+        .filter(|range| range.start < size)
+        .map(|range| range.start..range.end.min(size))
+        .collect();
+
+    if rules.is_empty() {
+        return ranges;
+    }
+
+    let tree = sample.tree();
+
+    let paths = tree.find_paths(&|node| {
+        matches!(&node.item, TreeNodeItem::ProductionApplication(p) if rules.contains(&p.rule_name))
+    });
+
+    ranges.extend(
+        paths
+            .iter()
+            .filter_map(|path| tree.get(path))
+            .map(|node| node.start..node.start + node.size)
+            .filter(|range| !range.is_empty()),
+    );
+
+    ranges
+}
+
+/// Cut patch starting at unprotected position so that it ends before next protected range.
+fn fit(patch: Patch, protected: &[Range<usize>]) -> Patch {
+    let Some(limit) = protected
+        .iter()
+        .map(|range| range.start)
+        .filter(|&start| start > patch.position)
+        .min()
+    else {
+        return patch;
+    };
+
+    let room = limit - patch.position;
+
+    let kind = match patch.kind {
+        PatchKind::Replacement(mut content) => {
+            content.truncate(room);
+            PatchKind::Replacement(content)
+        }
+        PatchKind::Erasure(size) => PatchKind::Erasure(size.min(room)),
+        PatchKind::Splice(size, content) => PatchKind::Splice(size.min(room), content),
+        insertion @ PatchKind::Insertion(..) => insertion,
+    };
+
+    Patch {
+        position: patch.position,
+        kind,
+    }
+}
+
+impl FocusMask {
+    pub fn new(options: &FocusOptions) -> Self {
+        Self {
+            protect: to_ranges(&options.protect),
+            protect_rules: options.protect_rules.clone(),
+            hot: to_ranges(&options.hot),
+            hot_rules: options.hot_rules.clone(),
+            hot_rate: options.hot_rate,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.protect.is_empty()
+            && self.protect_rules.is_empty()
+            && self.hot.is_empty()
+            && self.hot_rules.is_empty()
+    }
+
+    /// Adjust patch generated for sample: move it into one of hot ranges with
+    /// configured probability and keep it clear of protected ones. Returns
+    /// `None` if no unprotected position was found.
+    pub fn apply(&self, sample: &Sample, mut patch: Patch) -> Option<Patch> {
+        let mut rng = rand::thread_rng();
+
+        let hot = resolve(sample, &self.hot, &self.hot_rules);
+
+        if !hot.is_empty() && rng.gen_bool(self.hot_rate) {
+            let range = hot[rng.gen_range(0..hot.len())].clone();
+            patch.position = rng.gen_range(range);
+        }
+
+        let protected = resolve(sample, &self.protect, &self.protect_rules);
+
+        if protected.is_empty() {
+            return Some(patch);
+        }
+
+        let size = sample.get_folded().len();
+
+        for _ in 0..RELOCATE_ROLLS {
+            let position = patch.position;
+
+            if !protected.iter().any(|range| range.contains(&position)) {
+                return Some(fit(patch, &protected));
+            }
+
+            patch.position = rng.gen_range(0..size);
+        }
+
+        None
+    }
+}
@@ -1,5 +1,7 @@
 pub mod binary_level;
 mod choice;
+pub mod focus;
+pub mod text_level;
 pub mod tree_level;
 
 pub use tree_level::MutateTree;
@@ -8,24 +10,43 @@ pub use choice::MutationChooser;
 
 use crate::{
     configuration::{FuzzConfig, InputOptions},
-    grammar::Grammar,
+    grammar::{generation::Generator, Grammar, Token},
 };
 
 use self::{
-    binary_level::{BitFlip, CopyFragment, Erasure, Garbage, KnownBytes, MutateBytes},
-    tree_level::{Resample, TreeRegrow},
+    binary_level::{
+        BitFlip, CopyFragment, Erasure, Garbage, KnownBytes, MutateBytes, Padding, Truncation,
+    },
+    focus::FocusMask,
+    text_level::{BoundaryNumber, ChangeNumber, DeleteToken, DuplicateLine, SwapLines},
+    tree_level::{RegexRegrow, Resample, TreeRegrow, UnicodeInsert},
 };
 
 pub fn build_mutator(config: &FuzzConfig, grammar: &Grammar) -> MutationChooser {
-    let binary: Vec<Box<dyn MutateBytes>> = vec![
-        Box::new(BitFlip {}),
-        Box::new(Erasure { max_size: 100 }),
-        Box::new(KnownBytes::new()),
-        Box::new(Garbage { max_size: 20 }),
-        Box::new(CopyFragment { max_size: 100 }),
-    ];
-
-    let tree: Vec<Box<dyn MutateTree>> = if matches!(config.input, InputOptions::Grammar { .. }) {
+    let binary: Vec<Box<dyn MutateBytes>> = if config.fuzzing.text {
+        vec![
+            Box::new(SwapLines {}),
+            Box::new(DuplicateLine {}),
+            Box::new(DeleteToken {}),
+            Box::new(ChangeNumber {}),
+            Box::new(BoundaryNumber {}),
+            Box::new(CopyFragment { max_size: 100 }),
+        ]
+    } else {
+        vec![
+            Box::new(BitFlip {}),
+            Box::new(Erasure { max_size: 100 }),
+            Box::new(KnownBytes::new()),
+            Box::new(Garbage { max_size: 20 }),
+            Box::new(CopyFragment { max_size: 100 }),
+            Box::new(Truncation {}),
+            Box::new(Padding { max_size: 4096 }),
+            Box::new(BoundaryNumber {}),
+        ]
+    };
+
+    let mut tree: Vec<Box<dyn MutateTree>> = if matches!(config.input, InputOptions::Grammar { .. })
+    {
         vec![
             Box::new(TreeRegrow {
                 grammar: grammar.clone(),
@@ -33,12 +54,36 @@ pub fn build_mutator(config: &FuzzConfig, grammar: &Grammar) -> MutationChooser
                 descend_rolls: 10,
                 regenerate_rolls: 10,
                 mut_proba: 3,
+                reuse_proba: 0.3,
             }),
             Box::new(Resample::new(grammar.clone(), 100)),
+            Box::new(RegexRegrow::new(grammar.clone())),
         ]
     } else {
         vec![]
     };
 
-    MutationChooser::new(binary, tree)
+    if grammar
+        .tokens()
+        .any(|token| matches!(token, Token::Regex { unicode: true, .. }))
+    {
+        tree.push(Box::new(UnicodeInsert::new(grammar.clone())));
+    }
+
+    let mut chooser = MutationChooser::new(binary, tree);
+
+    let focus = FocusMask::new(&config.fuzzing.focus);
+
+    if !focus.is_empty() {
+        chooser = chooser.with_focus(focus);
+    }
+
+    if matches!(config.input, InputOptions::Grammar { .. }) {
+        chooser.with_fresh_samples(
+            Generator::new(grammar.clone(), 100),
+            config.fuzzing.fresh_samples,
+        )
+    } else {
+        chooser
+    }
 }
@@ -0,0 +1,258 @@
+use std::ops::Range;
+
+use rand::Rng;
+
+use crate::sample::{Patch, PatchKind, Sample};
+
+use super::binary_level::MutateBytes;
+
+/// patch leaving sample as is, returned when there is nothing to mutate
+fn unchanged() -> Patch {
+    Patch {
+        position: 0,
+        kind: PatchKind::Replacement(vec![]),
+    }
+}
+
+/// spans of lines without line terminators
+fn lines(text: &[u8]) -> Vec<Range<usize>> {
+    let mut spans = vec![];
+    let mut start = 0;
+
+    for (position, byte) in text.iter().enumerate() {
+        if *byte == b'\n' {
+            spans.push(start..position);
+            start = position + 1;
+        }
+    }
+
+    if start < text.len() {
+        spans.push(start..text.len());
+    }
+
+    spans
+}
+
+/// spans of maximal runs of bytes matching predicate
+fn runs(text: &[u8], predicate: impl Fn(u8) -> bool) -> Vec<Range<usize>> {
+    let mut spans = vec![];
+    let mut start = None;
+
+    for (position, byte) in text.iter().enumerate() {
+        match (predicate(*byte), start) {
+            (true, None) => start = Some(position),
+            (false, Some(run_start)) => {
+                spans.push(run_start..position);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(run_start) = start {
+        spans.push(run_start..text.len());
+    }
+
+    spans
+}
+
+/// spans of whitespace-separated tokens
+fn tokens(text: &[u8]) -> Vec<Range<usize>> {
+    runs(text, |byte| !byte.is_ascii_whitespace())
+}
+
+/// spans of decimal integers including minus sign in front of them
+fn numbers(text: &[u8]) -> Vec<Range<usize>> {
+    runs(text, |byte| byte.is_ascii_digit())
+        .into_iter()
+        .map(|span| match span.start.checked_sub(1) {
+            Some(sign) if text[sign] == b'-' => sign..span.end,
+            _ => span,
+        })
+        .collect()
+}
+
+fn pick<T: Clone>(items: &[T]) -> Option<T> {
+    if items.is_empty() {
+        return None;
+    }
+
+    Some(items[rand::thread_rng().gen_range(0..items.len())].clone())
+}
+
+pub struct SwapLines {}
+
+impl MutateBytes for SwapLines {
+    fn mutate(&self, reference: &[u8], _library: &[Sample]) -> Patch {
+        let lines = lines(reference);
+
+        if lines.len() < 2 {
+            return unchanged();
+        }
+
+        let mut rng = rand::thread_rng();
+
+        let first = rng.gen_range(0..lines.len() - 1);
+        let second = rng.gen_range(first + 1..lines.len());
+
+        let (first, second) = (lines[first].clone(), lines[second].clone());
+
+        let mut content = reference[second.clone()].to_vec();
+        content.extend_from_slice(&reference[first.end..second.start]);
+        content.extend_from_slice(&reference[first.clone()]);
+
+        Patch {
+            position: first.start,
+            kind: PatchKind::Replacement(content),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "swap lines"
+    }
+}
+
+pub struct DuplicateLine {}
+
+impl MutateBytes for DuplicateLine {
+    fn mutate(&self, reference: &[u8], _library: &[Sample]) -> Patch {
+        let Some(line) = pick(&lines(reference)) else {
+            return unchanged();
+        };
+
+        let mut content = reference[line.clone()].to_vec();
+        content.push(b'\n');
+
+        Patch {
+            position: line.start,
+            kind: PatchKind::Insertion(content),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "duplicate line"
+    }
+}
+
+pub struct DeleteToken {}
+
+impl MutateBytes for DeleteToken {
+    fn mutate(&self, reference: &[u8], _library: &[Sample]) -> Patch {
+        let Some(token) = pick(&tokens(reference)) else {
+            return unchanged();
+        };
+
+        Patch {
+            position: token.start,
+            kind: PatchKind::Erasure(token.len()),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "delete token"
+    }
+}
+
+pub struct ChangeNumber {}
+
+impl MutateBytes for ChangeNumber {
+    fn mutate(&self, reference: &[u8], _library: &[Sample]) -> Patch {
+        let Some(number) = pick(&numbers(reference)) else {
+            return unchanged();
+        };
+
+        let mut rng = rand::thread_rng();
+
+        let value = std::str::from_utf8(&reference[number.clone()])
+            .ok()
+            .and_then(|text| text.parse::<i128>().ok())
+            .unwrap_or_default();
+
+        let changed = match rng.gen_range(0..4) {
+            0 => value.saturating_add(1),
+            1 => value.saturating_sub(1),
+            2 => value.saturating_neg(),
+            _ => value.saturating_add(rng.gen_range(-256..=256)),
+        };
+
+        Patch {
+            position: number.start,
+            kind: PatchKind::Splice(number.len(), changed.to_string().into_bytes()),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "change number"
+    }
+
+    fn is_conservative(&self) -> bool {
+        true
+    }
+}
+
+/// Replaces integer found in text with value likely to hit overflow or
+/// huge allocation: zero, minus one, limits of common integer types or
+/// very long string of digits.
+pub struct BoundaryNumber {}
+
+impl BoundaryNumber {
+    const VALUES: &'static [&'static str] = &[
+        "0",
+        "-1",
+        "1",
+        "127",
+        "128",
+        "255",
+        "256",
+        "-128",
+        "32767",
+        "32768",
+        "65535",
+        "65536",
+        "-32768",
+        "2147483647",
+        "2147483648",
+        "4294967295",
+        "4294967296",
+        "-2147483648",
+        "-2147483649",
+        "9223372036854775807",
+        "9223372036854775808",
+        "18446744073709551615",
+        "18446744073709551616",
+        "-9223372036854775808",
+        "-9223372036854775809",
+    ];
+}
+
+impl MutateBytes for BoundaryNumber {
+    fn mutate(&self, reference: &[u8], _library: &[Sample]) -> Patch {
+        let Some(number) = pick(&numbers(reference)) else {
+            return unchanged();
+        };
+
+        let mut rng = rand::thread_rng();
+
+        let content = if rng.gen_ratio(1, 8) {
+            let digits = rng.gen_range(20..=1000);
+            (0..digits).map(|_| rng.gen_range(b'0'..=b'9')).collect()
+        } else {
+            Self::VALUES[rng.gen_range(0..Self::VALUES.len())]
+                .as_bytes()
+                .to_vec()
+        };
+
+        Patch {
+            position: number.start,
+            kind: PatchKind::Splice(number.len(), content),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "boundary number"
+    }
+
+    fn is_conservative(&self) -> bool {
+        true
+    }
+}
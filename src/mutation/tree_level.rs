@@ -1,110 +1,224 @@
+use std::{collections::HashMap, ops::Range};
+
 use itertools::Itertools;
-use rand::Rng;
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
 
 use crate::{
     grammar::{
         generation::{self, Generator},
-        Grammar,
+        Grammar, Token,
     },
-    sample::{Sample, TreeNode, TreeNodeItem},
+    sample::{NodePath, ProductionApplication, Sample, TokenKind, TreeNode, TreeNodeItem},
 };
 
 pub trait MutateTree {
-    fn mutate(&self, sample: Sample, bank: &[Sample]) -> Result<Sample, Sample>;
-}
+    fn mutate(
+        &self,
+        sample: Sample,
+        bank: &[Sample],
+        rules: &RuleStats,
+        pool: &SubtreePool,
+    ) -> Result<Sample, Sample>;
 
-pub struct TreeRegrow {
-    pub grammar: Grammar,
-    pub depth_limit: usize,
-    pub descend_rolls: usize,
-    pub regenerate_rolls: usize,
-    pub mut_proba: u32,
+    fn name(&self) -> &'static str;
 }
 
-type Depth = usize;
+/// Number of new paths found by changing terminals of each grammar rule.
+#[derive(Clone, Debug, Default)]
+pub struct RuleStats {
+    new_paths: HashMap<String, usize>,
+}
 
-pub(crate) fn select_random_subtree<'n>(
-    root: &'n mut TreeNode,
-    filter: &dyn Fn(&TreeNode) -> bool,
-) -> Option<(&'n mut TreeNode, Depth)> {
-    // if bugs, check here first
+impl RuleStats {
+    /// Credit rules directly containing terminals that overlap changed
+    /// bytes of sample that produced new path.
+    pub fn record_new_path(&mut self, tree: &TreeNode, changed: &Range<usize>) {
+        let mut rules = vec![];
 
-    let mut buf = vec![];
+        collect_changed_rules(tree, changed, &mut rules);
 
-    writeout_nodes(root, &mut buf, 0, filter);
-    if buf.is_empty() {
-        return None;
+        for rule in rules.into_iter().unique() {
+            *self.new_paths.entry(rule.to_string()).or_default() += 1;
+        }
     }
-    let idx = rand::thread_rng().gen_range(0..buf.len());
 
-    let (ptr, depth) = buf[idx];
+    /// selection weight of production of given rule, grows with number of
+    /// new paths found by changing it
+    pub fn weight(&self, rule: &str) -> f64 {
+        let new_paths = self.new_paths.get(rule).copied().unwrap_or_default();
 
-    Some((unsafe { ptr.as_mut().unwrap() }, depth))
+        1.0 + (new_paths as f64).sqrt()
+    }
 }
 
-pub fn select_random_production(root: &mut TreeNode) -> Option<(&mut TreeNode, Depth)> {
-    select_random_subtree(root, &|tree| {
-        matches!(tree.item, TreeNodeItem::ProductionApplication(..))
-    })
+/// Subtrees of samples that found new paths grouped by rule, reused by
+/// `TreeRegrow` in place of freshly generated ones.
+#[derive(Clone, Debug, Default)]
+pub struct SubtreePool {
+    subtrees: HashMap<String, Vec<ProductionApplication>>,
 }
 
-pub fn writeout_terminals(root: &mut TreeNode) -> Vec<&mut TreeNode> {
-    let mut buf = vec![];
+impl SubtreePool {
+    /// number of subtrees kept for every rule, random one is replaced when full
+    const CAPACITY: usize = 64;
+
+    /// store every production of tree
+    pub fn harvest(&mut self, tree: &TreeNode) {
+        match &tree.item {
+            TreeNodeItem::ProductionApplication(production) => {
+                let subtrees = self
+                    .subtrees
+                    .entry(production.rule_name.clone())
+                    .or_default();
 
-    fn filter(node: &TreeNode) -> bool {
-        match &node.item {
-            TreeNodeItem::ProductionApplication(_) => false,
-            TreeNodeItem::Data(_) => true,
+                if subtrees.len() < Self::CAPACITY {
+                    subtrees.push(production.clone());
+                } else {
+                    subtrees[rand::thread_rng().gen_range(0..Self::CAPACITY)] = production.clone();
+                }
+
+                for item in &production.items {
+                    self.harvest(item);
+                }
+            }
+            TreeNodeItem::Encoded(_, inner) => self.harvest(inner),
+            TreeNodeItem::Data(..) => {}
         }
     }
 
-    writeout_nodes(root, &mut buf, 0, &filter);
+    /// random stored subtree of given rule
+    pub fn random(&self, rule: &str) -> Option<&ProductionApplication> {
+        let subtrees = self.subtrees.get(rule)?;
 
-    buf.into_iter()
-        .map(|(item, _)| unsafe { item.as_mut().unwrap() })
-        .collect_vec()
-}
+        if subtrees.is_empty() {
+            return None;
+        }
 
-fn writeout_nodes(
-    node: &mut TreeNode,
-    buf: &mut Vec<(*mut TreeNode, Depth)>,
-    current_depth: usize,
-    filter: &dyn Fn(&TreeNode) -> bool,
-) {
-    if filter(node) {
-        buf.push((node as *mut TreeNode, current_depth));
+        Some(&subtrees[rand::thread_rng().gen_range(0..subtrees.len())])
     }
+}
+
+fn overlaps(node: &TreeNode, changed: &Range<usize>) -> bool {
+    // pure erasure leaves empty range, count node containing its position
+    let end = changed.end.max(changed.start + 1);
+
+    node.start < end && changed.start < node.start + node.size.max(1)
+}
+
+fn collect_changed_rules<'t>(node: &'t TreeNode, changed: &Range<usize>, rules: &mut Vec<&'t str>) {
+    let TreeNodeItem::ProductionApplication(production) = &node.item else {
+        return;
+    };
+
+    for item in &production.items {
+        if !overlaps(item, changed) {
+            continue;
+        }
 
-    if let TreeNodeItem::ProductionApplication(p) = &mut node.item {
-        for subnode in &mut p.items {
-            writeout_nodes(subnode, buf, current_depth + 1, filter);
+        match &item.item {
+            TreeNodeItem::Data(..) | TreeNodeItem::Encoded(..) => rules.push(&production.rule_name),
+            TreeNodeItem::ProductionApplication(_) => collect_changed_rules(item, changed, rules),
         }
     }
 }
 
+pub struct TreeRegrow {
+    pub grammar: Grammar,
+    pub depth_limit: usize,
+    pub descend_rolls: usize,
+    pub regenerate_rolls: usize,
+    pub mut_proba: u32,
+    /// chance to insert subtree from pool instead of generating new one
+    pub reuse_proba: f64,
+}
+
+/// Select path to random node matching filter, nodes are picked
+/// proportionally to their weight. Depth of node is length of its path.
+pub(crate) fn select_random_subtree(
+    root: &TreeNode,
+    filter: &dyn Fn(&TreeNode) -> bool,
+    weight: &dyn Fn(&TreeNode) -> f64,
+) -> Option<NodePath> {
+    let mut paths = root.find_paths(filter);
+    if paths.is_empty() {
+        return None;
+    }
+
+    let weights = paths
+        .iter()
+        .map(|path| root.get(path).map(weight).unwrap_or_default())
+        .collect_vec();
+
+    let idx = match WeightedIndex::new(&weights) {
+        Ok(dist) => dist.sample(&mut rand::thread_rng()),
+        Err(_) => rand::thread_rng().gen_range(0..paths.len()),
+    };
+
+    Some(paths.swap_remove(idx))
+}
+
+/// select production, preferring rules that historically led to new paths
+pub fn select_random_production(root: &TreeNode, rules: &RuleStats) -> Option<NodePath> {
+    select_random_subtree(
+        root,
+        &|tree| matches!(tree.item, TreeNodeItem::ProductionApplication(..)),
+        &|tree| match &tree.item {
+            TreeNodeItem::ProductionApplication(production) => rules.weight(&production.rule_name),
+            _ => 1.0,
+        },
+    )
+}
+
 impl MutateTree for TreeRegrow {
-    fn mutate(&self, sample: Sample, _bank: &[Sample]) -> Result<Sample, Sample> {
+    fn mutate(
+        &self,
+        sample: Sample,
+        _bank: &[Sample],
+        rules: &RuleStats,
+        pool: &SubtreePool,
+    ) -> Result<Sample, Sample> {
         // TODO keep patches in place when mutating
 
         let (mut tree, folded) = sample.strip();
 
         'reroll: for _roll in 0..self.descend_rolls {
-            let Some((node, depth)) = select_random_production(&mut tree) else {
+            let Some(path) = select_random_production(&tree, rules) else {
                 return Err(Sample::recombine(tree, folded));
             };
 
-            let remaining_depth = self.depth_limit - depth;
+            let remaining_depth = self.depth_limit - path.len();
 
             let generator = generation::Generator::new(self.grammar.clone(), remaining_depth);
 
-            let TreeNode{ item: TreeNodeItem::ProductionApplication(production), ..} = node else{
+            let Some(node) = tree.get_mut(&path) else {
                 continue 'reroll;
             };
 
-            let Ok(subtree) = generator.generate_of_type(&production.rule_name, self.regenerate_rolls) else {
+            let TreeNode {
+                item: TreeNodeItem::ProductionApplication(production),
+                ..
+            } = node
+            else {
                 continue 'reroll;
             };
 
+            let pooled = pool
+                .random(&production.rule_name)
+                .filter(|_| rand::thread_rng().gen_bool(self.reuse_proba));
+
+            let subtree = match pooled {
+                Some(subtree) => subtree.clone(),
+                None => {
+                    let Ok(subtree) =
+                        generator.generate_of_type(&production.rule_name, self.regenerate_rolls)
+                    else {
+                        continue 'reroll;
+                    };
+
+                    subtree
+                }
+            };
+
             *node = TreeNode {
                 item: TreeNodeItem::ProductionApplication(subtree),
                 start: 0,
@@ -118,15 +232,230 @@ impl MutateTree for TreeRegrow {
 
         Err(Sample::recombine(tree, folded))
     }
+
+    fn name(&self) -> &'static str {
+        "tree regrow"
+    }
 }
 
 pub struct Resample {
     generator: Generator,
+    attempts: usize,
 }
 
 impl MutateTree for Resample {
-    fn mutate(&self, _sample: Sample, _bank: &[Sample]) -> Result<Sample, Sample> {
-        Ok(self.generator.generate())
+    /// Regenerate one of productions directly under root keeping the rest
+    /// of tree, whole sample is regenerated if root has no such productions.
+    fn mutate(
+        &self,
+        sample: Sample,
+        _bank: &[Sample],
+        _rules: &RuleStats,
+        _pool: &SubtreePool,
+    ) -> Result<Sample, Sample> {
+        let (mut tree, _folded) = sample.strip();
+
+        let sections = tree
+            .children()
+            .iter()
+            .positions(|child| matches!(child.item, TreeNodeItem::ProductionApplication(..)))
+            .collect_vec();
+
+        if sections.is_empty() {
+            return Ok(self.generator.generate());
+        }
+
+        let index = sections[rand::thread_rng().gen_range(0..sections.len())];
+        let section = &mut tree.children_mut()[index];
+
+        let TreeNodeItem::ProductionApplication(production) = &section.item else {
+            unreachable!()
+        };
+
+        let Ok(regenerated) = self
+            .generator
+            .generate_of_type(&production.rule_name, self.attempts)
+        else {
+            return Err(tree.fold_into_sample());
+        };
+
+        *section = TreeNodeItem::ProductionApplication(regenerated).into();
+
+        Ok(tree.fold_into_sample())
+    }
+
+    fn name(&self) -> &'static str {
+        "resample"
+    }
+}
+
+/// Re-sample single terminal generated from regex keeping rest of tree.
+pub struct RegexRegrow {
+    generator: Generator,
+}
+
+impl MutateTree for RegexRegrow {
+    fn mutate(
+        &self,
+        sample: Sample,
+        _bank: &[Sample],
+        _rules: &RuleStats,
+        _pool: &SubtreePool,
+    ) -> Result<Sample, Sample> {
+        let (mut tree, folded) = sample.strip();
+
+        let paths = tree.find_paths(&|node| match &node.item {
+            TreeNodeItem::Data(_, Some(origin)) => origin.kind == TokenKind::Regex,
+            _ => false,
+        });
+
+        if paths.is_empty() {
+            return Err(Sample::recombine(tree, folded));
+        }
+
+        let path = &paths[rand::thread_rng().gen_range(0..paths.len())];
+
+        let Some(TreeNode {
+            item: TreeNodeItem::Data(data, Some(origin)),
+            ..
+        }) = tree.get_mut(path)
+        else {
+            return Err(Sample::recombine(tree, folded));
+        };
+
+        // token may be gone from grammar sample was generated with
+        let Some(regenerated) = self.generator.regenerate_regex(origin) else {
+            return Err(Sample::recombine(tree, folded));
+        };
+
+        *data = regenerated;
+
+        Ok(tree.fold_into_sample())
+    }
+
+    fn name(&self) -> &'static str {
+        "regex regrow"
+    }
+}
+
+/// Insert unusual UTF-8 (multi-byte characters, overlong encodings,
+/// surrogates, BOM, runs of combining characters) into terminal generated
+/// from regex with `unicode` flag.
+pub struct UnicodeInsert {
+    grammar: Grammar,
+}
+
+impl UnicodeInsert {
+    const SEQUENCES: &'static [&'static [u8]] = &[
+        // byte order mark
+        b"\xef\xbb\xbf",
+        // overlong encodings of `/` and NUL
+        b"\xc0\xaf",
+        b"\xe0\x80\xaf",
+        b"\xf0\x80\x80\xaf",
+        b"\xc0\x80",
+        // lone surrogate, character above U+10FFFF, truncated sequence
+        b"\xed\xa0\x80",
+        b"\xf4\x90\x80\x80",
+        b"\xe2\x82",
+        // zero width joiner, right-to-left override, replacement character
+        b"\xe2\x80\x8d",
+        b"\xe2\x80\xae",
+        b"\xef\xbf\xbd",
+    ];
+
+    const COMBINING: &'static [char] = &['\u{0301}', '\u{0308}', '\u{0336}', '\u{20dd}'];
+
+    pub fn new(grammar: Grammar) -> Self {
+        Self { grammar }
+    }
+
+    fn is_unicode(&self, item: &TreeNodeItem) -> bool {
+        let TreeNodeItem::Data(_, Some(origin)) = item else {
+            return false;
+        };
+
+        origin.kind == TokenKind::Regex
+            && matches!(
+                self.grammar.token(origin),
+                Some(Token::Regex { unicode: true, .. })
+            )
+    }
+
+    fn random_insertion() -> Vec<u8> {
+        let mut rng = rand::thread_rng();
+
+        match rng.gen_range(0..3) {
+            0 => Self::SEQUENCES[rng.gen_range(0..Self::SEQUENCES.len())].to_vec(),
+            1 => {
+                let combining = Self::COMBINING[rng.gen_range(0..Self::COMBINING.len())];
+                combining
+                    .to_string()
+                    .repeat(rng.gen_range(1..=32))
+                    .into_bytes()
+            }
+            _ => {
+                // characters taking 2, 3 and 4 bytes
+                let (low, high) =
+                    [(0x80, 0x800), (0x800, 0xd800), (0x10000, 0x110000)][rng.gen_range(0..3)];
+                let character = char::from_u32(rng.gen_range(low..high)).unwrap_or('\u{fffd}');
+                character.to_string().into_bytes()
+            }
+        }
+    }
+}
+
+impl MutateTree for UnicodeInsert {
+    fn mutate(
+        &self,
+        sample: Sample,
+        _bank: &[Sample],
+        _rules: &RuleStats,
+        _pool: &SubtreePool,
+    ) -> Result<Sample, Sample> {
+        let (mut tree, folded) = sample.strip();
+
+        let paths = tree.find_paths(&|node| self.is_unicode(&node.item));
+
+        if paths.is_empty() {
+            return Err(Sample::recombine(tree, folded));
+        }
+
+        let mut rng = rand::thread_rng();
+
+        let path = &paths[rng.gen_range(0..paths.len())];
+
+        let Some(TreeNode {
+            item: TreeNodeItem::Data(data, _),
+            ..
+        }) = tree.get_mut(path)
+        else {
+            unreachable!()
+        };
+
+        // insert between characters, not inside of one
+        let boundaries = (0..=data.len())
+            .filter(|&position| data.get(position).is_none_or(|byte| byte & 0xc0 != 0x80))
+            .collect_vec();
+
+        let position = boundaries[rng.gen_range(0..boundaries.len())];
+
+        data.splice(position..position, Self::random_insertion());
+
+        Ok(tree.fold_into_sample())
+    }
+
+    fn name(&self) -> &'static str {
+        "unicode insert"
+    }
+}
+
+impl RegexRegrow {
+    pub fn new(grammar: Grammar) -> Self {
+        Self {
+            // only terminals are generated, depth does not matter
+            generator: Generator::new(grammar, 0),
+        }
     }
 }
 
@@ -134,6 +463,7 @@ impl Resample {
     pub fn new(grammar: Grammar, depth_limit: usize) -> Self {
         Self {
             generator: crate::grammar::generation::Generator::new(grammar, depth_limit),
+            attempts: 10,
         }
     }
 }
@@ -0,0 +1,87 @@
+use std::process::Command;
+#[cfg(unix)]
+use std::{ffi::CString, os::unix::process::CommandExt};
+
+use anyhow::{anyhow, bail};
+
+/// User and group target processes are run as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Credentials {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+/// uid and primary group of user from passwd database
+#[cfg(unix)]
+fn lookup(passwd: *mut libc::passwd) -> Option<Credentials> {
+    if passwd.is_null() {
+        return None;
+    }
+
+    let passwd = unsafe { &*passwd };
+
+    Some(Credentials {
+        uid: passwd.pw_uid,
+        gid: passwd.pw_gid,
+    })
+}
+
+impl Credentials {
+    /// Resolve user name, uid or `uid:gid`. Group of named user or bare
+    /// uid is primary group of that user.
+    #[cfg(unix)]
+    pub fn resolve(user: &str) -> Result<Self, anyhow::Error> {
+        if let Some((uid, gid)) = user.split_once(':') {
+            return Ok(Credentials {
+                uid: uid
+                    .parse()
+                    .map_err(|_| anyhow!("uid `{uid}` is not a number"))?,
+                gid: gid
+                    .parse()
+                    .map_err(|_| anyhow!("gid `{gid}` is not a number"))?,
+            });
+        }
+
+        if let Ok(uid) = user.parse::<u32>() {
+            return lookup(unsafe { libc::getpwuid(uid) })
+                .ok_or_else(|| anyhow!("no user with uid {uid}, use `uid:gid` to set group"));
+        }
+
+        let name = CString::new(user).map_err(|_| anyhow!("user name contains NUL byte"))?;
+
+        match lookup(unsafe { libc::getpwnam(name.as_ptr()) }) {
+            Some(credentials) => Ok(credentials),
+            None => bail!("no user named `{user}`"),
+        }
+    }
+
+    /// users can not be switched to outside of Unix
+    #[cfg(not(unix))]
+    pub fn resolve(_user: &str) -> Result<Self, anyhow::Error> {
+        bail!("running target as another user is only supported on Unix")
+    }
+
+    /// Make command drop privileges before executing target, supplementary
+    /// groups are cleared as well.
+    #[cfg(unix)]
+    pub fn apply(&self, command: &mut Command) {
+        command.uid(self.uid).gid(self.gid);
+    }
+
+    /// credentials are never resolved outside of Unix
+    #[cfg(not(unix))]
+    pub fn apply(&self, _command: &mut Command) {}
+
+    /// whether fuzzer itself may switch to these credentials
+    #[cfg(unix)]
+    pub fn can_switch(&self) -> bool {
+        let euid = unsafe { libc::geteuid() };
+
+        euid == 0 || (euid == self.uid && unsafe { libc::getegid() } == self.gid)
+    }
+
+    #[cfg(not(unix))]
+    pub fn can_switch(&self) -> bool {
+        false
+    }
+}
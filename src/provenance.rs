@@ -0,0 +1,96 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use serde_derive::Serialize;
+
+use crate::{fuzzing::MutationLevel, storage::write_atomic};
+
+/// Mutation that turned sample into library entry.
+#[derive(Clone, Debug, Serialize)]
+pub struct Step {
+    pub mutation: &'static str,
+    pub level: MutationLevel,
+    /// content hash of mutated sample
+    pub parent_hash: String,
+    /// library entry that was mutated, none for seeds kept outside of library
+    pub parent: Option<String>,
+}
+
+/// Mutations that produced library entries, keyed by entry name. Entry
+/// replaced by smaller sample gets mutation of the new one.
+#[derive(Default)]
+pub struct Lineage {
+    steps: HashMap<String, Step>,
+}
+
+impl Lineage {
+    pub fn record(&mut self, name: &str, step: Step) {
+        self.steps.insert(name.to_string(), step);
+    }
+
+    /// Mutations leading to entry, starting from the one applied to seed
+    /// or to the oldest entry still known.
+    pub fn chain(&self, name: &str) -> Vec<&Step> {
+        let mut chain = vec![];
+        let mut visited = HashSet::new();
+        let mut current = Some(name);
+
+        // entry improved by mutating itself is its own parent
+        while let Some(name) = current.filter(|name| visited.insert(*name)) {
+            let Some(step) = self.steps.get(name) else {
+                break;
+            };
+
+            chain.push(step);
+            current = step.parent.as_deref();
+        }
+
+        chain.reverse();
+        chain
+    }
+}
+
+#[derive(Serialize)]
+struct Provenance<'a> {
+    /// seed of random number generator, none until runs can be seeded
+    rng_seed: Option<u64>,
+    /// content hash of sample mutated into crash
+    parent_hash: Option<&'a str>,
+    parent: Option<&'a str>,
+    /// size of crash before it was minimized, saved crash is not produced by mutations alone then
+    minimized_from: Option<usize>,
+    mutations: Vec<&'a Step>,
+}
+
+/// path of file storing mutations that produced crash saved at given path
+pub fn provenance_path(crash_path: &Path) -> PathBuf {
+    let mut path = crash_path.as_os_str().to_owned();
+    path.push(".provenance.json");
+    PathBuf::from(path)
+}
+
+/// Write chain of mutations that produced crash next to it.
+pub fn save_provenance(
+    crash_path: &Path,
+    lineage: &Lineage,
+    name: &str,
+    minimized_from: Option<usize>,
+) -> std::io::Result<()> {
+    let mutations = lineage.chain(name);
+    let last = mutations.last();
+
+    let provenance = Provenance {
+        rng_seed: None,
+        parent_hash: last.map(|step| step.parent_hash.as_str()),
+        parent: last.and_then(|step| step.parent.as_deref()),
+        minimized_from,
+        mutations,
+    };
+
+    write_atomic(
+        &provenance_path(crash_path),
+        &serde_json::to_vec_pretty(&provenance).map_err(std::io::Error::from)?,
+    )
+}
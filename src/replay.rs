@@ -0,0 +1,242 @@
+use std::{
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    configuration::FuzzConfig,
+    execution::{self, ExecResult, RunTrace},
+    export, fuzz_thread,
+    sample::{TreeNode, TreeNodeItem},
+};
+
+/// exit code of `verify` when some crash still reproduces
+const REPRODUCED: i32 = 1;
+
+/// What happened when target ran on one file.
+pub enum Verdict {
+    Finished(RunTrace),
+    Timeout,
+    Failed(String),
+}
+
+pub struct Replayed {
+    pub path: PathBuf,
+    pub verdict: Verdict,
+    pub elapsed: Duration,
+}
+
+impl Verdict {
+    fn status(&self) -> String {
+        match self {
+            Verdict::Finished(trace) => trace.result.to_string(),
+            Verdict::Timeout => "timeout".to_string(),
+            Verdict::Failed(e) => format!("error: {e}"),
+        }
+    }
+}
+
+/// suffixes of files fuzzer writes next to saved inputs
+const SIDECARS: &[&str] = &[
+    ".core",
+    ".casrep",
+    ".provenance.json",
+    ".stderr",
+    ".stuck",
+    ".tree.json",
+];
+
+/// regular files of directory in name order, hidden ones and sidecars
+/// of saved inputs are skipped
+fn list_files(directory: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+
+    for entry in std::fs::read_dir(directory)? {
+        let entry = entry?;
+
+        let name = entry.file_name().to_string_lossy().to_string();
+        let hidden = name.starts_with('.');
+        let sidecar = SIDECARS.iter().any(|suffix| name.ends_with(suffix));
+
+        if entry.file_type()?.is_file() && !hidden && !sidecar {
+            files.push(entry.path());
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Run configured target on every given file.
+pub fn replay_files(
+    config: &FuzzConfig,
+    files: &[PathBuf],
+) -> Result<Vec<Replayed>, anyhow::Error> {
+    let mut evaluator = fuzz_thread::target_evaluator(config)?;
+
+    let mut replayed = vec![];
+
+    for path in files {
+        let content = match std::fs::read(path) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("skipping {}: {e}", path.display());
+                continue;
+            }
+        };
+
+        let tree: TreeNode = TreeNodeItem::Data(content, None).into();
+
+        let started = Instant::now();
+        let verdict = match evaluator.score(tree.fold_into_sample()) {
+            Ok(tested) => Verdict::Finished(tested.result),
+            Err(e) if execution::is_timeout(&e) => Verdict::Timeout,
+            Err(e) => Verdict::Failed(e.to_string()),
+        };
+
+        replayed.push(Replayed {
+            path: path.clone(),
+            verdict,
+            elapsed: started.elapsed(),
+        });
+    }
+
+    Ok(replayed)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().to_string(),
+    )
+}
+
+/// Run target on every file of directory and print table of results.
+/// Files with the same trace as earlier one are marked as known.
+pub fn replay_dir(config: &FuzzConfig, directory: &Path) {
+    let files = match list_files(directory) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("failed to list {}: {e}", directory.display());
+            process::exit(exitcode::NOINPUT)
+        }
+    };
+
+    execution::disable_aslr();
+
+    let replayed = match replay_files(config, &files) {
+        Ok(replayed) => replayed,
+        Err(e) => {
+            eprintln!("failed to replay {}: {e:#}", directory.display());
+            process::exit(exitcode::SOFTWARE)
+        }
+    };
+
+    let width = replayed
+        .iter()
+        .map(|replayed| file_name(&replayed.path).len())
+        .max()
+        .unwrap_or_default()
+        .max("file".len());
+
+    println!(
+        "{:<width$} {:<12} {:>9} {:>10}  trace",
+        "file", "status", "functions", "time"
+    );
+
+    for (index, run) in replayed.iter().enumerate() {
+        let functions = match &run.verdict {
+            Verdict::Finished(trace) => trace.trajectory.len().to_string(),
+            _ => "-".to_string(),
+        };
+
+        let known = match &run.verdict {
+            Verdict::Finished(trace) => replayed[..index].iter().find(|earlier| {
+                matches!(&earlier.verdict, Verdict::Finished(earlier) if earlier == trace)
+            }),
+            _ => None,
+        };
+
+        let trace = match known {
+            Some(earlier) => format!("known, same as {}", file_name(&earlier.path)),
+            None if matches!(run.verdict, Verdict::Finished(_)) => "new".to_string(),
+            None => "-".to_string(),
+        };
+
+        println!(
+            "{:<width$} {:<12} {:>9} {:>8}ms  {trace}",
+            file_name(&run.path),
+            run.verdict.status(),
+            functions,
+            run.elapsed.as_millis()
+        );
+    }
+}
+
+/// Run target on every crash saved in directory and exit with nonzero code
+/// if any of them still crashes, so that CI can check fixed bugs stay fixed.
+pub fn verify_crashes(config: &FuzzConfig, directory: &Path) {
+    let crashes = match export::list_crashes(directory) {
+        Ok(crashes) => crashes,
+        Err(e) => {
+            eprintln!("failed to list crashes in {}: {e}", directory.display());
+            process::exit(exitcode::NOINPUT)
+        }
+    };
+
+    execution::disable_aslr();
+
+    let replayed = match replay_files(config, &crashes) {
+        Ok(replayed) => replayed,
+        Err(e) => {
+            eprintln!("failed to run crashes: {e:#}");
+            process::exit(exitcode::SOFTWARE)
+        }
+    };
+
+    let mut reproduced = 0;
+    let mut failed = 0;
+    let mut timed_out = 0;
+
+    for run in &replayed {
+        let name = file_name(&run.path);
+
+        match &run.verdict {
+            Verdict::Finished(RunTrace {
+                result: ExecResult::Signal,
+                ..
+            }) => {
+                reproduced += 1;
+                println!("{name}: still crashes");
+            }
+            Verdict::Finished(trace) => println!("{name}: fixed ({})", trace.result),
+            Verdict::Timeout => {
+                timed_out += 1;
+                println!("{name}: target times out");
+            }
+            Verdict::Failed(e) => {
+                failed += 1;
+                println!("{name}: could not be run: {e}");
+            }
+        }
+    }
+
+    println!(
+        "{reproduced} of {} crash(es) still reproduce against {}",
+        replayed.len(),
+        config.binary.path
+    );
+
+    if reproduced > 0 {
+        process::exit(REPRODUCED)
+    }
+
+    if failed > 0 {
+        process::exit(exitcode::SOFTWARE)
+    }
+
+    if timed_out > 0 {
+        process::exit(exitcode::TEMPFAIL)
+    }
+}
@@ -1,6 +1,21 @@
-use std::{io::Write, ops::Range};
-
-use crate::{mutation::tree_level::writeout_terminals, sample_library::SizeScore};
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt::Display,
+    hash::{Hash, Hasher},
+    io::Write,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use base64::Engine;
+use flate2::{write::GzEncoder, Compression};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    sample_library::{Content, Footprint, SizeScore},
+    storage::write_atomic,
+};
 
 #[derive(Clone, Debug)]
 pub struct Patch {
@@ -15,6 +30,9 @@ pub enum PatchKind {
     Erasure(usize),
 
     Insertion(Vec<u8>),
+
+    /// replace given number of bytes with content of possibly different size
+    Splice(usize, Vec<u8>),
 }
 
 fn intersect_intervals(first: (usize, usize), second: (usize, usize)) -> Option<Range<usize>> {
@@ -35,31 +53,130 @@ fn remap_interval_to_segment(range: Range<usize>, data_start: usize) -> Range<us
     range.start - data_start..range.end - data_start
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ProductionApplication {
     pub rule_name: String,
     pub production_variant: usize,
     pub items: Vec<TreeNode>,
 }
 
-#[derive(Clone, Debug)]
+/// Location of node inside tree as indices of children to descend into, empty for root.
+pub type NodePath = Vec<usize>;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TreeNode {
     pub start: usize,
     pub size: usize,
     pub item: TreeNodeItem,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenKind {
+    String,
+    Hex,
+    Regex,
+    Bytes,
+    Bits,
+    Float,
+    /// separator inserted before token by grammar `joiner` option
+    Separator,
+}
+
+impl TokenKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            TokenKind::String => "string",
+            TokenKind::Hex => "hex",
+            TokenKind::Regex => "regex",
+            TokenKind::Bytes => "bytes",
+            TokenKind::Bits => "bits",
+            TokenKind::Float => "float",
+            TokenKind::Separator => "separator",
+        }
+    }
+}
+
+/// Grammar token that produced data: its kind, rule, alternative of
+/// the rule and index of token in it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenOrigin {
+    pub kind: TokenKind,
+    pub rule: String,
+    pub variant: usize,
+    pub position: usize,
+}
+
+impl Display for TokenOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} token #{} of {} (variant {})",
+            self.kind.name(),
+            self.position,
+            self.rule,
+            self.variant
+        )
+    }
+}
+
+/// Transformation applied to folded content of subtree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    Gzip,
+    Base64,
+    Hex,
+}
+
+impl Encoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Base64 => "base64",
+            Encoding::Hex => "hexenc",
+        }
+    }
+
+    pub fn encode(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(vec![], Compression::default());
+                // writing into vector does not fail
+                encoder.write_all(data).unwrap();
+                encoder.finish().unwrap()
+            }
+            Encoding::Base64 => base64::engine::general_purpose::STANDARD
+                .encode(data)
+                .into_bytes(),
+            Encoding::Hex => data
+                .iter()
+                .flat_map(|byte| format!("{byte:02x}").into_bytes())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum TreeNodeItem {
     ProductionApplication(ProductionApplication),
-    Data(Vec<u8>),
+    /// data with grammar token it was generated from, no origin for seeds
+    Data(Vec<u8>, Option<TokenOrigin>),
+    /// subtree written in encoded form, offsets of nodes inside it are
+    /// relative to its decoded content
+    Encoded(Encoding, Box<TreeNode>),
 }
 
 impl TreeNodeItem {
     fn find_tree_span(&self) -> usize {
         match self {
             TreeNodeItem::ProductionApplication(p) => p.items.iter().map(|item| item.size).sum(),
-            TreeNodeItem::Data(data) => data.len(),
+            TreeNodeItem::Data(data, _) => data.len(),
+            TreeNodeItem::Encoded(encoding, inner) => {
+                let mut decoded = vec![];
+                inner.as_ref().clone().fold(&mut decoded);
+                encoding.encode(&decoded).len()
+            }
         }
     }
 
@@ -83,6 +200,113 @@ impl From<TreeNodeItem> for TreeNode {
 }
 
 impl TreeNode {
+    pub fn children(&self) -> &[TreeNode] {
+        match &self.item {
+            TreeNodeItem::ProductionApplication(p) => &p.items,
+            TreeNodeItem::Data(..) => &[],
+            TreeNodeItem::Encoded(_, inner) => std::slice::from_ref(inner),
+        }
+    }
+
+    pub fn children_mut(&mut self) -> &mut [TreeNode] {
+        match &mut self.item {
+            TreeNodeItem::ProductionApplication(p) => &mut p.items,
+            TreeNodeItem::Data(..) => &mut [],
+            TreeNodeItem::Encoded(_, inner) => std::slice::from_mut(inner),
+        }
+    }
+
+    pub fn get(&self, path: &[usize]) -> Option<&TreeNode> {
+        path.iter()
+            .try_fold(self, |node, &index| node.children().get(index))
+    }
+
+    pub fn get_mut(&mut self, path: &[usize]) -> Option<&mut TreeNode> {
+        path.iter()
+            .try_fold(self, |node, &index| node.children_mut().get_mut(index))
+    }
+
+    /// paths to all nodes matching filter in depth-first order
+    pub fn find_paths(&self, filter: &dyn Fn(&TreeNode) -> bool) -> Vec<NodePath> {
+        fn walk(
+            node: &TreeNode,
+            path: &mut NodePath,
+            filter: &dyn Fn(&TreeNode) -> bool,
+            buf: &mut Vec<NodePath>,
+        ) {
+            if filter(node) {
+                buf.push(path.clone());
+            }
+
+            for (index, child) in node.children().iter().enumerate() {
+                path.push(index);
+                walk(child, path, filter, buf);
+                path.pop();
+            }
+        }
+
+        let mut buf = vec![];
+        walk(self, &mut vec![], filter, &mut buf);
+        buf
+    }
+
+    /// number of nodes in tree
+    pub fn node_count(&self) -> usize {
+        1 + self
+            .children()
+            .iter()
+            .map(TreeNode::node_count)
+            .sum::<usize>()
+    }
+
+    /// number of nodes on the longest path from root to leaf
+    pub fn depth(&self) -> usize {
+        1 + self
+            .children()
+            .iter()
+            .map(TreeNode::depth)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// names of rules applied anywhere in tree
+    pub fn rules(&self) -> Vec<&str> {
+        let mut rules = vec![];
+
+        if let TreeNodeItem::ProductionApplication(production) = &self.item {
+            rules.push(production.rule_name.as_str());
+        }
+
+        for child in self.children() {
+            rules.extend(child.rules());
+        }
+
+        rules
+    }
+
+    /// data nodes of tree in depth-first order, ones inside encoded
+    /// subtrees are skipped as their offsets do not match folded content
+    pub fn terminals_mut(&mut self) -> Vec<&mut TreeNode> {
+        fn walk<'t>(node: &'t mut TreeNode, buf: &mut Vec<&'t mut TreeNode>) {
+            match node.item {
+                TreeNodeItem::Data(..) => {
+                    buf.push(node);
+                    return;
+                }
+                TreeNodeItem::Encoded(..) => return,
+                TreeNodeItem::ProductionApplication(..) => {}
+            }
+
+            for child in node.children_mut() {
+                walk(child, buf);
+            }
+        }
+
+        let mut buf = vec![];
+        walk(self, &mut buf);
+        buf
+    }
+
     /// write this tree to buffer setting indices in the process
     pub fn fold(&mut self, buffer: &mut Vec<u8>) {
         let before = buffer.len();
@@ -92,9 +316,14 @@ impl TreeNode {
                     item.fold(buffer);
                 }
             }
-            TreeNodeItem::Data(data) => {
+            TreeNodeItem::Data(data, _) => {
                 buffer.write_all(data).unwrap();
             }
+            TreeNodeItem::Encoded(encoding, inner) => {
+                let mut decoded = vec![];
+                inner.fold(&mut decoded);
+                buffer.write_all(&encoding.encode(&decoded)).unwrap();
+            }
         }
         self.start = before;
         self.size = buffer.len() - before;
@@ -128,6 +357,23 @@ pub struct GrammarSample {
 
 pub type Sample = GrammarSample;
 
+/// path of file storing derivation tree of sample saved at given path
+pub fn structure_path(sample_path: &Path) -> PathBuf {
+    let mut path = sample_path.as_os_str().to_owned();
+    path.push(".tree.json");
+    PathBuf::from(path)
+}
+
+pub fn load_structure(sample_path: &Path) -> Result<TreeNode, anyhow::Error> {
+    let path = structure_path(sample_path);
+
+    let content = std::fs::read(&path)
+        .with_context(|| format!("reading sample structure from {}", path.display()))?;
+
+    serde_json::from_slice(&content)
+        .with_context(|| format!("parsing sample structure from {}", path.display()))
+}
+
 fn apply_patch(data: &mut Vec<u8>, data_pos: usize, patch: &Patch) {
     if data.is_empty() {
         return;
@@ -177,6 +423,19 @@ fn apply_patch(data: &mut Vec<u8>, data_pos: usize, patch: &Patch) {
                 data.append(&mut suffix);
             }
         }
+        PatchKind::Splice(size, content) => {
+            if patch.position >= data_pos && patch.position < data_pos + data.len() {
+                let start = patch.position - data_pos;
+                let end = (start + size).min(data.len());
+
+                data.splice(start..end, content.iter().copied());
+            } else if let Some(span_in_data) = intersect_intervals(
+                (data_pos, data_pos + data.len()),
+                (patch.position, patch.position + size),
+            ) {
+                data.drain(remap_interval_to_segment(span_in_data, data_pos));
+            }
+        }
     }
 }
 
@@ -185,6 +444,52 @@ impl Sample {
         &self.folded
     }
 
+    pub fn tree(&self) -> &TreeNode {
+        &self.tree
+    }
+
+    /// Offsets at which outermost applications of `rule` start, except for
+    /// the one at the beginning. They split folded content into messages,
+    /// content between messages belongs to the one before it.
+    pub fn message_starts(&self, rule: &str) -> Vec<usize> {
+        fn walk(node: &TreeNode, rule: &str, starts: &mut Vec<usize>) {
+            match &node.item {
+                TreeNodeItem::ProductionApplication(p) if p.rule_name == rule => {
+                    starts.push(node.start)
+                }
+                TreeNodeItem::ProductionApplication(p) => {
+                    for item in &p.items {
+                        walk(item, rule, starts);
+                    }
+                }
+                // offsets inside encoded content do not point into folded one
+                TreeNodeItem::Data(..) | TreeNodeItem::Encoded(..) => {}
+            }
+        }
+
+        let mut starts = vec![];
+        walk(&self.tree, rule, &mut starts);
+
+        starts.retain(|&start| start > 0);
+        starts.dedup();
+        starts
+    }
+
+    /// hash of folded content, samples with equal bytes have equal hashes
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.folded.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// write derivation tree next to sample saved at given path
+    pub fn save_structure(&self, sample_path: &Path) -> Result<(), std::io::Error> {
+        write_atomic(
+            &structure_path(sample_path),
+            &serde_json::to_vec(&self.tree).map_err(std::io::Error::from)?,
+        )
+    }
+
     pub fn strip(self) -> (TreeNode, Vec<u8>) {
         (self.tree, self.folded)
     }
@@ -194,20 +499,33 @@ impl Sample {
     }
 
     pub fn apply_patch(mut self, patch: Patch) -> Self {
-        if self.folded.is_empty() && matches!(patch.kind, PatchKind::Insertion(..)) {
-            let Some( TreeNode { start: _, size: _, item: TreeNodeItem::Data(data) } ) = writeout_terminals(&mut self.tree).into_iter().next() else {
-                unreachable!()
+        if self.folded.is_empty()
+            && matches!(patch.kind, PatchKind::Insertion(..) | PatchKind::Splice(..))
+        {
+            let Some(TreeNode {
+                start: _,
+                size: _,
+                item: TreeNodeItem::Data(data, _),
+            }) = self.tree.terminals_mut().into_iter().next()
+            else {
+                // all data is inside of encoded subtrees
+                return self;
             };
 
             *data = match patch.kind {
-                PatchKind::Insertion(data) => data,
+                PatchKind::Insertion(data) | PatchKind::Splice(_, data) => data,
                 _ => unreachable!(),
             };
             return self.tree.fold_into_sample();
         }
 
-        for terminal in writeout_terminals(&mut self.tree) {
-            let TreeNode{item: TreeNodeItem::Data(data), start,..} = terminal else {
+        for terminal in self.tree.terminals_mut() {
+            let TreeNode {
+                item: TreeNodeItem::Data(data, _),
+                start,
+                ..
+            } = terminal
+            else {
                 unreachable!()
             };
 
@@ -223,3 +541,36 @@ impl SizeScore for Sample {
         self.folded.len()
     }
 }
+
+impl Footprint for TreeNode {
+    fn footprint(&self) -> usize {
+        let own = match &self.item {
+            TreeNodeItem::ProductionApplication(production) => production.rule_name.len(),
+            TreeNodeItem::Data(data, origin) => {
+                data.len() + origin.as_ref().map_or(0, |origin| origin.rule.len())
+            }
+            TreeNodeItem::Encoded(..) => 0,
+        };
+
+        std::mem::size_of::<TreeNode>()
+            + own
+            + self
+                .children()
+                .iter()
+                .map(TreeNode::footprint)
+                .sum::<usize>()
+    }
+}
+
+impl Footprint for Sample {
+    fn footprint(&self) -> usize {
+        std::mem::size_of::<Sample>() + self.folded.len() + self.tree.footprint()
+            - std::mem::size_of::<TreeNode>()
+    }
+}
+
+impl Content for Sample {
+    fn content(&self) -> &[u8] {
+        &self.folded
+    }
+}
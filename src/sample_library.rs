@@ -1,110 +1,556 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    time::Duration,
+};
+
+use bocchifuzz::scheduler::{self, Candidate, Rarity, Scheduler};
 use itertools::Itertools;
 use rand::distributions::WeightedIndex;
 use rand::prelude::*;
 use vector_map::VecMap;
 
+use crate::colorization::EffectiveBytes;
+
 pub trait Library {
     type Key: Clone + Eq + CoverageScore;
     type Item: Sized + Clone;
 
-    fn find_existing(&self, reference: &Self::Key) -> Option<&LibraryEntry<Self::Item>>;
+    fn find_existing(&self, reference: &Self::Key) -> Option<(&LibraryEntry, &Self::Item)>;
 
-    fn upsert(&mut self, key: Self::Key, object: Self::Item);
+    fn upsert(&mut self, key: Self::Key, object: Self::Item, exec_time: Duration);
 
     fn add_name(&mut self, key: &Self::Key, name: String);
 
-    fn pick_random(&self) -> Self::Item;
+    /// count execution towards global function hit frequencies
+    fn record_hits(&mut self, key: &Self::Key);
+
+    /// pick random item returning it together with its index,
+    /// preferring items scheduler scores higher
+    fn pick_random(&mut self) -> (usize, Self::Item);
+
+    /// pick random item among ones with key matching predicate
+    fn pick_random_matching<F: Fn(&Self::Key) -> bool>(
+        &mut self,
+        predicate: F,
+    ) -> Option<(usize, Self::Item)>;
+
+    /// number of mutations to try in a row on item at given index
+    fn energy(&self, index: usize) -> usize;
 
+    /// effective bytes of item at given index, none if it was not colorized yet
+    fn effective_bytes(&self, index: usize) -> Option<&EffectiveBytes>;
+
+    fn set_effective_bytes(&mut self, index: usize, effective: EffectiveBytes);
+
+    /// distinct items of library, entries with equal content share one item
     fn linearize(&mut self) -> &[Self::Item];
+
+    /// approximate number of bytes held by library
+    fn footprint(&self) -> usize;
+
+    /// Remove entries until library fits into `budget` bytes, entries with
+    /// key matching `keep` are never removed. Entries reaching fewest rare
+    /// functions per byte go first. Returns number of removed entries.
+    fn evict<F: Fn(&Self::Key) -> bool>(&mut self, budget: usize, keep: F) -> usize;
+
+    /// keys of all entries
+    fn keys(&self) -> Vec<Self::Key>;
+
+    /// key of entry at given index
+    fn key(&self, index: usize) -> Option<Self::Key>;
+
+    /// remove entry with given key, none if there is no such entry
+    fn remove(&mut self, key: &Self::Key) -> Option<LibraryEntry>;
 }
 
-pub struct LibraryEntry<V> {
-    pub item: V,
+pub struct LibraryEntry {
     index: usize,
+    /// position of item in content store
+    slot: usize,
     pub unique_name: Option<String>,
+    /// time it took to execute item
+    pub exec_time: Duration,
+    /// bytes of item target inspects, kept while item is replaced with one of the same size
+    pub effective: Option<EffectiveBytes>,
 }
 
-pub struct VectorLibrary<K, V> {
-    /// cached contiguous items array
+/// Items stored once per distinct content and referenced by slot from
+/// library entries, so that entries reaching different paths with the
+/// same bytes (eg. due to nondeterminism) do not keep copies of them.
+struct ContentStore<V> {
     items: Vec<V>,
-    buffer: vector_map::VecMap<K, LibraryEntry<V>>,
+    /// slot of item with given content hash
+    slots: HashMap<u64, usize>,
+    /// number of entries referencing each slot
+    refs: Vec<usize>,
+    /// approximate number of bytes held by items
+    footprint: usize,
+}
+
+impl<V> ContentStore<V> {
+    fn new() -> Self {
+        Self {
+            items: vec![],
+            slots: HashMap::new(),
+            refs: vec![],
+            footprint: 0,
+        }
+    }
+}
+
+impl<V: Content + Footprint> ContentStore<V> {
+    fn hash(item: &V) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        item.content().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Slot of item with the same content, item is stored if there is none.
+    fn intern(&mut self, item: V) -> usize {
+        let hash = Self::hash(&item);
+
+        if let Some(&slot) = self.slots.get(&hash) {
+            // on hash collision item is stored separately
+            if self.items[slot].content() == item.content() {
+                self.refs[slot] += 1;
+                return slot;
+            }
+        }
+
+        let slot = self.items.len();
+
+        self.slots.entry(hash).or_insert(slot);
+        self.footprint += item.footprint();
+        self.items.push(item);
+        self.refs.push(1);
+
+        slot
+    }
+
+    /// Drop reference to slot. Unreferenced item is removed and last
+    /// item takes its place, former slot of moved item is returned.
+    fn release(&mut self, slot: usize) -> Option<usize> {
+        self.refs[slot] -= 1;
+
+        if self.refs[slot] > 0 {
+            return None;
+        }
+
+        let hash = Self::hash(&self.items[slot]);
+        if self.slots.get(&hash) == Some(&slot) {
+            self.slots.remove(&hash);
+        }
+
+        let last = self.items.len() - 1;
+
+        self.footprint -= self.items[slot].footprint();
+        self.items.swap_remove(slot);
+        self.refs.swap_remove(slot);
+
+        if slot == last {
+            return None;
+        }
+
+        let moved = Self::hash(&self.items[slot]);
+        if self.slots.get(&moved) == Some(&last) {
+            self.slots.insert(moved, slot);
+        }
+
+        Some(last)
+    }
+}
+
+pub struct VectorLibrary<K, V> {
+    items: ContentStore<V>,
+    buffer: vector_map::VecMap<K, LibraryEntry>,
+    /// number of executions that reached each function
+    hits: HashMap<usize, usize>,
+    /// cached selection weights, dropped on library change
+    weights: Option<WeightedIndex<f64>>,
+    /// scheduler scores weights were built from
+    scores: Vec<f64>,
+    scheduler: Box<dyn Scheduler>,
+    executions_since_rescore: usize,
+    /// exponent of size factor in energy, 0 ignores size
+    size_weight: f64,
+    /// approximate number of bytes held by keys and entries, kept up to
+    /// date on change so that eviction does not walk the whole library
+    entries_footprint: usize,
 }
 
 pub trait CoverageScore {
-    fn get_score(&self) -> f64;
+    /// identifiers of functions reached during execution
+    fn covered_functions(&self) -> Vec<usize>;
 }
 
 pub trait SizeScore {
     fn get_size_score(&self) -> usize;
 }
 
-impl<K: Clone + CoverageScore + Eq, V: Clone + SizeScore> Library for VectorLibrary<K, V> {
+pub trait Footprint {
+    /// approximate number of bytes held by value including its allocations
+    fn footprint(&self) -> usize;
+}
+
+pub trait Content {
+    /// bytes identifying item, items with equal content are stored once
+    fn content(&self) -> &[u8];
+}
+
+impl<K, V> Library for VectorLibrary<K, V>
+where
+    K: Clone + CoverageScore + Eq + Footprint,
+    V: Clone + SizeScore + Content + Footprint,
+{
     type Item = V;
     type Key = K;
 
-    fn find_existing(&self, reference: &Self::Key) -> Option<&LibraryEntry<Self::Item>> {
-        self.buffer.get(reference)
+    fn find_existing(&self, reference: &Self::Key) -> Option<(&LibraryEntry, &Self::Item)> {
+        let entry = self.buffer.get(reference)?;
+
+        Some((entry, &self.items.items[entry.slot]))
     }
 
-    fn upsert(&mut self, key: Self::Key, object: Self::Item) {
+    fn upsert(&mut self, key: Self::Key, object: Self::Item, exec_time: Duration) {
+        self.weights = None;
+
+        let size = object.get_size_score();
+        let slot = self.items.intern(object);
+
         if let Some(exisiting) = self.buffer.get_mut(&key) {
-            exisiting.item = object.clone();
-            self.items[exisiting.index] = object;
+            let before = exisiting.footprint();
+            let previous = std::mem::replace(&mut exisiting.slot, slot);
+
+            if self.items.items[previous].get_size_score() != size {
+                exisiting.effective = None;
+            }
+            exisiting.exec_time = exec_time;
+            self.entries_footprint = self.entries_footprint + exisiting.footprint() - before;
+
+            if let Some(moved) = self.items.release(previous) {
+                for entry in self.buffer.values_mut() {
+                    if entry.slot == moved {
+                        entry.slot = previous;
+                    }
+                }
+            }
         } else {
-            let index = self.items.len();
+            let index = self.buffer.len();
+            let entry = LibraryEntry {
+                index,
+                slot,
+                unique_name: None,
+                exec_time,
+                effective: None,
+            };
 
-            self.buffer.insert(
-                key,
-                LibraryEntry {
-                    item: object.clone(),
-                    index,
-                    unique_name: None,
-                },
-            );
-            self.items.push(object)
+            self.entries_footprint += key.footprint() + entry.footprint();
+            self.buffer.insert(key, entry);
         }
     }
 
     fn add_name(&mut self, key: &Self::Key, name: String) {
-        let Some(existing) = self.buffer.get_mut(key) else{
-            panic!("called add_name without prior upsert");
+        // entry may have been evicted before it got its name
+        let Some(existing) = self.buffer.get_mut(key) else {
+            return;
         };
 
+        let before = existing.footprint();
         existing.unique_name = Some(name);
+        self.entries_footprint = self.entries_footprint + existing.footprint() - before;
     }
 
-    fn pick_random(&self) -> Self::Item {
-        let weights = self
-            .buffer
-            .keys()
-            .map(CoverageScore::get_score)
-            .collect_vec();
+    fn record_hits(&mut self, key: &Self::Key) {
+        for function in key.covered_functions() {
+            *self.hits.entry(function).or_default() += 1;
+        }
 
-        let dist = WeightedIndex::new(&weights).unwrap();
+        self.executions_since_rescore += 1;
+    }
+
+    fn pick_random(&mut self) -> (usize, Self::Item) {
+        if self.executions_since_rescore >= Self::RESCORE_INTERVAL {
+            self.weights = None;
+        }
+
+        let dist = match self.weights.take() {
+            Some(dist) => dist,
+            None => {
+                self.executions_since_rescore = 0;
+
+                self.scores = self
+                    .iter()
+                    .map(|(key, entry, item)| self.score(key, entry, item))
+                    .collect_vec();
+
+                // scheduler may score every entry 0
+                WeightedIndex::new(&self.scores)
+                    .unwrap_or_else(|_| WeightedIndex::new(vec![1.0; self.scores.len()]).unwrap())
+            }
+        };
 
         let mut rng = thread_rng();
 
-        self.items[dist.sample(&mut rng)].clone()
+        let index = dist.sample(&mut rng);
+
+        self.weights = Some(dist);
+
+        // entries are reindexed after every removal, so index is position in buffer
+        let slot = self.buffer.values().nth(index).unwrap().slot;
+
+        (index, self.items.items[slot].clone())
+    }
+
+    fn pick_random_matching<F: Fn(&Self::Key) -> bool>(
+        &mut self,
+        predicate: F,
+    ) -> Option<(usize, Self::Item)> {
+        let (_key, entry) = self
+            .buffer
+            .iter()
+            .filter(|(key, _entry)| predicate(key))
+            .choose(&mut thread_rng())?;
+
+        Some((entry.index, self.items.items[entry.slot].clone()))
+    }
+
+    fn energy(&self, index: usize) -> usize {
+        let Some((key, entry, item)) = self.get_by_index(index) else {
+            return 1;
+        };
+
+        let count = self.buffer.len() as f64;
+
+        let score = self
+            .scores
+            .get(index)
+            .copied()
+            .unwrap_or_else(|| self.score(key, entry, item));
+        let avg_score = self.scores.iter().sum::<f64>() / self.scores.len().max(1) as f64;
+
+        let time = entry.exec_time.as_secs_f64().max(Self::MIN_EXEC_TIME);
+        let avg_time = self
+            .buffer
+            .values()
+            .map(|entry| entry.exec_time.as_secs_f64().max(Self::MIN_EXEC_TIME))
+            .sum::<f64>()
+            / count;
+
+        let size = item.get_size_score().max(1) as f64;
+        let avg_size = self
+            .iter()
+            .map(|(_key, _entry, item)| item.get_size_score().max(1) as f64)
+            .sum::<f64>()
+            / count;
+
+        let score_factor = if avg_score > 0.0 {
+            score / avg_score
+        } else {
+            1.0
+        };
+
+        let size_factor = (avg_size / size).powf(self.size_weight);
+
+        let factor = score_factor * (avg_time / time) * size_factor;
+
+        ((Self::BASE_ENERGY * factor).round() as usize).clamp(1, Self::MAX_ENERGY)
+    }
+
+    fn effective_bytes(&self, index: usize) -> Option<&EffectiveBytes> {
+        self.get_by_index(index)?.1.effective.as_ref()
+    }
+
+    fn set_effective_bytes(&mut self, index: usize, effective: EffectiveBytes) {
+        if let Some(entry) = self.buffer.values_mut().find(|entry| entry.index == index) {
+            let before = entry.footprint();
+            entry.effective = Some(effective);
+            self.entries_footprint = self.entries_footprint + entry.footprint() - before;
+        }
     }
 
     fn linearize(&mut self) -> &[Self::Item] {
-        &self.items
+        &self.items.items
+    }
+
+    fn footprint(&self) -> usize {
+        self.entries_footprint + self.items.footprint
+    }
+
+    fn evict<F: Fn(&Self::Key) -> bool>(&mut self, budget: usize, keep: F) -> usize {
+        let mut candidates = self
+            .iter()
+            .filter(|(key, _entry, _item)| !keep(key))
+            .map(|(key, _entry, item)| {
+                let value = self.rarity_score(key) / item.footprint().max(1) as f64;
+                (key.clone(), value)
+            })
+            .collect_vec();
+
+        candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        let mut evicted = 0;
+
+        for (key, _value) in candidates {
+            if self.footprint() <= budget {
+                break;
+            }
+
+            if self.detach(&key).is_some() {
+                evicted += 1;
+            }
+        }
+
+        if evicted > 0 {
+            self.reindex();
+        }
+
+        evicted
+    }
+
+    fn keys(&self) -> Vec<Self::Key> {
+        self.buffer
+            .iter()
+            .map(|(key, _entry)| key.clone())
+            .collect()
+    }
+
+    fn key(&self, index: usize) -> Option<Self::Key> {
+        self.get_by_index(index)
+            .map(|(key, _entry, _item)| key.clone())
+    }
+
+    fn remove(&mut self, key: &Self::Key) -> Option<LibraryEntry> {
+        let entry = self.detach(key)?;
+        self.reindex();
+        Some(entry)
     }
 }
 
-impl<K: Eq, V> VectorLibrary<K, V> {
+impl LibraryEntry {
+    /// approximate number of bytes held by entry, not counting its key and item
+    fn footprint(&self) -> usize {
+        std::mem::size_of::<LibraryEntry>()
+            + self.unique_name.as_ref().map_or(0, String::len)
+            + self.effective.as_ref().map_or(0, Footprint::footprint)
+    }
+}
+
+impl<K: Eq + Footprint, V: Content + Footprint> VectorLibrary<K, V> {
+    /// Take entry out of buffer and release its item, indices of
+    /// remaining entries are stale until `reindex`.
+    fn detach(&mut self, key: &K) -> Option<LibraryEntry> {
+        let entry = self.buffer.remove(key)?;
+        self.entries_footprint -= key.footprint() + entry.footprint();
+
+        if let Some(moved) = self.items.release(entry.slot) {
+            for other in self.buffer.values_mut() {
+                if other.slot == moved {
+                    other.slot = entry.slot;
+                }
+            }
+        }
+
+        Some(entry)
+    }
+
+    fn reindex(&mut self) {
+        // indices of entries are their positions
+        for (index, entry) in self.buffer.values_mut().enumerate() {
+            entry.index = index;
+        }
+
+        self.weights = None;
+        self.scores.clear();
+    }
+}
+
+impl<K: CoverageScore, V> VectorLibrary<K, V> {
+    /// number of executions after which selection weights are recomputed
+    const RESCORE_INTERVAL: usize = 1000;
+
+    /// mutations per burst for item of average score, speed and size
+    const BASE_ENERGY: f64 = 16.0;
+    const MAX_ENERGY: usize = 256;
+    const MIN_EXEC_TIME: f64 = 1e-6;
+
+    fn rarity_score(&self, key: &K) -> f64 {
+        scheduler::rarity(&key.covered_functions(), &self.hits)
+    }
+}
+
+impl<K: CoverageScore, V: SizeScore> VectorLibrary<K, V> {
+    /// selection weight of entry, invalid scores count as 0
+    fn score(&self, key: &K, entry: &LibraryEntry, item: &V) -> f64 {
+        let score = self.scheduler.score(&Candidate {
+            functions: &key.covered_functions(),
+            hits: &self.hits,
+            exec_time: entry.exec_time,
+            size: item.get_size_score(),
+        });
+
+        if score.is_finite() {
+            score.max(0.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl<K: Eq, V: Content> VectorLibrary<K, V> {
     pub fn new() -> Self {
         Self {
             buffer: VecMap::new(),
-            items: vec![],
+            items: ContentStore::new(),
+            hits: HashMap::new(),
+            weights: None,
+            scores: vec![],
+            scheduler: Box::new(Rarity),
+            executions_since_rescore: 0,
+            size_weight: 1.0,
+            entries_footprint: 0,
         }
     }
 
+    /// Set strategy weighting entries picked for mutation.
+    pub fn with_scheduler(mut self, scheduler: Box<dyn Scheduler>) -> Self {
+        self.scheduler = scheduler;
+        self
+    }
+
+    /// Set how strongly smaller items are preferred when assigning energy.
+    pub fn with_size_weight(mut self, size_weight: f64) -> Self {
+        self.size_weight = size_weight;
+        self
+    }
+
     pub fn len(&self) -> usize {
         self.buffer.len()
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = (&K, &LibraryEntry<V>)> {
-        self.buffer.iter()
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &LibraryEntry, &V)> {
+        self.buffer
+            .iter()
+            .map(|(key, entry)| (key, entry, &self.items.items[entry.slot]))
+    }
+
+    /// number of distinct items stored for library entries
+    pub fn distinct_items(&self) -> usize {
+        self.items.items.len()
+    }
+
+    /// number of executions that reached each function, used for scheduling
+    pub fn hit_counts(&self) -> &HashMap<usize, usize> {
+        &self.hits
+    }
+
+    pub fn set_hit_counts(&mut self, hits: HashMap<usize, usize>) {
+        self.hits = hits;
+        self.weights = None;
+    }
+
+    pub fn get_by_index(&self, index: usize) -> Option<(&K, &LibraryEntry, &V)> {
+        self.iter()
+            .find(|(_key, entry, _item)| entry.index == index)
     }
 }
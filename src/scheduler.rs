@@ -0,0 +1,73 @@
+use std::{collections::HashMap, time::Duration};
+
+/// Library entry as seen by scheduler.
+pub struct Candidate<'a> {
+    /// identifiers of functions reached by entry
+    pub functions: &'a [usize],
+    /// number of executions that reached each function
+    pub hits: &'a HashMap<usize, usize>,
+    /// time it took to execute entry
+    pub exec_time: Duration,
+    /// size of entry sample in bytes
+    pub size: usize,
+}
+
+/// Strategy weighting library entries picked for mutation. Entries are
+/// picked with probability proportional to their score and entries
+/// scoring above average get more mutations in a row. Scores are
+/// recomputed when library changes and periodically as hit counts grow.
+pub trait Scheduler: Send {
+    /// non-negative weight of entry, entries scoring 0 are never picked
+    /// unless every entry does
+    fn score(&self, candidate: &Candidate) -> f64;
+}
+
+/// Sum of inverse hit counts of reached functions, so entries touching
+/// cold code score higher.
+pub fn rarity(functions: &[usize], hits: &HashMap<usize, usize>) -> f64 {
+    functions
+        .iter()
+        .map(|function| 1.0 / hits.get(function).copied().unwrap_or(1) as f64)
+        .sum::<f64>()
+        + 0.1
+}
+
+/// every entry is equally likely
+pub struct Uniform;
+
+impl Scheduler for Uniform {
+    fn score(&self, _candidate: &Candidate) -> f64 {
+        1.0
+    }
+}
+
+/// entries reaching more functions are preferred
+pub struct Coverage;
+
+impl Scheduler for Coverage {
+    fn score(&self, candidate: &Candidate) -> f64 {
+        candidate.functions.len() as f64 + 1.0
+    }
+}
+
+/// entries reaching rarely executed functions are preferred
+pub struct Rarity;
+
+impl Scheduler for Rarity {
+    fn score(&self, candidate: &Candidate) -> f64 {
+        rarity(candidate.functions, candidate.hits)
+    }
+}
+
+/// entries executing quickly are preferred
+pub struct Fast;
+
+impl Fast {
+    const MIN_EXEC_TIME: f64 = 1e-6;
+}
+
+impl Scheduler for Fast {
+    fn score(&self, candidate: &Candidate) -> f64 {
+        1.0 / candidate.exec_time.as_secs_f64().max(Self::MIN_EXEC_TIME)
+    }
+}
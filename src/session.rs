@@ -0,0 +1,113 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use chrono::Local;
+use serde_derive::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    configuration::{FuzzConfig, InputOptions},
+    storage::write_atomic,
+};
+
+/// Name of file describing setup of current campaign, inside output directory.
+pub const SESSION_FILE: &str = "session.json";
+
+/// hex-encoded sha256 of file content
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let content = std::fs::read(path)?;
+    Ok(format!("{:x}", Sha256::digest(content)))
+}
+
+/// Detects changes of file on disk, cheap metadata check is done first
+/// and content is hashed only if metadata changed.
+pub struct TargetWatch {
+    path: PathBuf,
+    hash: String,
+    metadata: (Option<SystemTime>, u64),
+    last_check: Instant,
+}
+
+impl TargetWatch {
+    const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+    pub fn new(path: &str) -> std::io::Result<Self> {
+        let path = PathBuf::from(path);
+
+        Ok(TargetWatch {
+            hash: sha256_file(&path)?,
+            metadata: Self::metadata(&path)?,
+            path,
+            last_check: Instant::now(),
+        })
+    }
+
+    fn metadata(path: &Path) -> std::io::Result<(Option<SystemTime>, u64)> {
+        let metadata = std::fs::metadata(path)?;
+        Ok((metadata.modified().ok(), metadata.len()))
+    }
+
+    /// returns new hash if file content changed since last check
+    pub fn check(&mut self) -> Option<String> {
+        if self.last_check.elapsed() < Self::CHECK_INTERVAL {
+            return None;
+        }
+        self.last_check = Instant::now();
+
+        // file may be missing while it is being rebuilt, check again later
+        let metadata = Self::metadata(&self.path).ok()?;
+        if metadata == self.metadata {
+            return None;
+        }
+
+        let hash = sha256_file(&self.path).ok()?;
+        self.metadata = metadata;
+
+        if hash == self.hash {
+            return None;
+        }
+
+        self.hash = hash.clone();
+        Some(hash)
+    }
+}
+
+/// Everything needed to trace findings back to setup that produced them.
+#[derive(Serialize)]
+struct Session<'c> {
+    campaign: &'c str,
+    version: &'static str,
+    start_time: String,
+    config_hash: &'c str,
+    target_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grammar_hash: Option<String>,
+    config: &'c FuzzConfig,
+}
+
+/// Write session file for campaign into output directory.
+pub fn write_session(config: &FuzzConfig, campaign: &str) -> Result<PathBuf, anyhow::Error> {
+    let target_hash = sha256_file(Path::new(&config.binary.path))?;
+
+    let grammar_hash = match &config.input {
+        InputOptions::Grammar { grammar } => Some(sha256_file(Path::new(grammar))?),
+        InputOptions::Seeds { .. } => None,
+    };
+
+    let session = Session {
+        campaign,
+        version: env!("CARGO_PKG_VERSION"),
+        start_time: Local::now().to_rfc3339(),
+        config_hash: &config.hash,
+        target_hash,
+        grammar_hash,
+        config,
+    };
+
+    let path = Path::new(&config.output.directory).join(SESSION_FILE);
+    write_atomic(&path, &serde_json::to_vec_pretty(&session)?)?;
+
+    Ok(path)
+}
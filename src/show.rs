@@ -0,0 +1,69 @@
+use std::{path::Path, process};
+
+use crate::sample::{load_structure, TreeNode, TreeNodeItem};
+
+/// longest data preview printed for terminal
+const PREVIEW_SIZE: usize = 40;
+
+fn preview(data: &[u8]) -> String {
+    let escaped = data.escape_ascii().to_string();
+
+    if escaped.len() > PREVIEW_SIZE {
+        format!("\"{}\"...", &escaped[..PREVIEW_SIZE])
+    } else {
+        format!("\"{escaped}\"")
+    }
+}
+
+fn write_node(node: &TreeNode, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    let range = format!("[{}..{}]", node.start, node.start + node.size);
+
+    match &node.item {
+        TreeNodeItem::ProductionApplication(production) => {
+            out.push_str(&format!(
+                "{indent}{} (variant {}) {range}\n",
+                production.rule_name, production.production_variant
+            ));
+
+            for child in &production.items {
+                write_node(child, depth + 1, out);
+            }
+        }
+        TreeNodeItem::Data(data, origin) => {
+            let origin = origin
+                .as_ref()
+                .map(|origin| origin.to_string())
+                .unwrap_or_else(|| "seed data".to_string());
+
+            out.push_str(&format!("{indent}{} {range} {origin}\n", preview(data)));
+        }
+        TreeNodeItem::Encoded(encoding, inner) => {
+            out.push_str(&format!("{indent}{} {range}\n", encoding.name()));
+
+            write_node(inner, depth + 1, out);
+        }
+    }
+}
+
+/// Print derivation tree of sample saved by fuzzer in grammar mode.
+pub fn show_sample(path: &Path) {
+    if !path.exists() {
+        eprintln!("sample {} does not exist", path.display());
+        process::exit(exitcode::NOINPUT)
+    }
+
+    let tree = match load_structure(path) {
+        Ok(tree) => tree,
+        Err(e) => {
+            eprintln!("{e:#}");
+            eprintln!("structure is only saved for samples found in grammar mode");
+            process::exit(exitcode::DATAERR)
+        }
+    };
+
+    let mut out = String::new();
+    write_node(&tree, 0, &mut out);
+
+    print!("{out}");
+}
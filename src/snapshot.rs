@@ -0,0 +1,167 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use chrono::Local;
+use flate2::{write::GzEncoder, Compression};
+
+use crate::{execution::ExecResult, libafl, state::Library, storage::write_atomic};
+
+/// Directory inside output directory receiving snapshots.
+pub const SNAPSHOT_DIR: &str = "snapshots";
+
+const BLOCK: usize = 512;
+
+/// write `value` as zero-padded octal number filling `field` except for trailing NUL
+fn octal(field: &mut [u8], value: u64) {
+    let width = field.len() - 1;
+    let digits = format!("{value:0width$o}");
+    field[..width].copy_from_slice(digits.as_bytes());
+}
+
+/// size of name field of tar header
+const NAME_LEN: usize = 100;
+
+/// Write tar header of entry with given type, name that does not fit is cut.
+fn write_header(
+    archive: &mut impl Write,
+    name: &[u8],
+    kind: u8,
+    size: usize,
+    mtime: u64,
+) -> std::io::Result<()> {
+    let mut header = [0u8; BLOCK];
+
+    let name = &name[..name.len().min(NAME_LEN)];
+    header[..name.len()].copy_from_slice(name);
+    octal(&mut header[100..108], 0o644);
+    octal(&mut header[108..116], 0);
+    octal(&mut header[116..124], 0);
+    octal(&mut header[124..136], size as u64);
+    octal(&mut header[136..148], mtime);
+    header[156] = kind;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // checksum is computed with its own field filled with spaces
+    header[148..156].fill(b' ');
+    let checksum = header.iter().map(|&byte| byte as u64).sum();
+    octal(&mut header[148..155], checksum);
+
+    archive.write_all(&header)
+}
+
+/// Write entry content padded to whole blocks.
+fn write_content(archive: &mut impl Write, content: &[u8]) -> std::io::Result<()> {
+    archive.write_all(content)?;
+    archive.write_all(&vec![0; (BLOCK - content.len() % BLOCK) % BLOCK])
+}
+
+/// Append regular file to tar archive in ustar format. Name longer than
+/// header field is preceded by GNU long name entry.
+fn append_file(
+    archive: &mut impl Write,
+    name: &str,
+    content: &[u8],
+    mtime: u64,
+) -> std::io::Result<()> {
+    if name.len() > NAME_LEN {
+        let long_name = [name.as_bytes(), b"\0"].concat();
+
+        write_header(archive, b"././@LongLink", b'L', long_name.len(), mtime)?;
+        write_content(archive, &long_name)?;
+    }
+
+    write_header(archive, name.as_bytes(), b'0', content.len(), mtime)?;
+    write_content(archive, content)
+}
+
+/// Library entry copied out of library to be written into snapshot.
+pub struct SnapshotEntry {
+    name: String,
+    content: Vec<u8>,
+    crash: bool,
+}
+
+/// Copy entries of library, so that snapshot is written without holding its lock.
+pub fn snapshot_entries(library: &Library) -> Vec<SnapshotEntry> {
+    library
+        .iter()
+        .map(|(trace, entry, sample)| {
+            let content = sample.get_folded();
+
+            SnapshotEntry {
+                name: libafl::testcase_name(entry.unique_name.as_ref(), content),
+                content: content.to_vec(),
+                crash: matches!(trace.result, ExecResult::Signal),
+            }
+        })
+        .collect()
+}
+
+/// Write gzipped tarball of library entries into snapshot directory, every
+/// entry goes to `queue/` and crashing ones also to `crashes/`. Oldest
+/// snapshots are removed so that at most `keep` of them remain.
+pub fn write_snapshot(
+    entries: &[SnapshotEntry],
+    output: &Path,
+    keep: usize,
+) -> std::io::Result<PathBuf> {
+    let directory = output.join(SNAPSHOT_DIR);
+    std::fs::create_dir_all(&directory)?;
+
+    let mtime = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+
+    let mut archive = GzEncoder::new(vec![], Compression::default());
+
+    for SnapshotEntry {
+        name,
+        content,
+        crash,
+    } in entries
+    {
+        append_file(&mut archive, &format!("queue/{name}"), content, mtime)?;
+
+        if *crash {
+            append_file(&mut archive, &format!("crashes/{name}"), content, mtime)?;
+        }
+    }
+
+    archive.write_all(&[0; 2 * BLOCK])?;
+
+    let path = directory.join(format!(
+        "snapshot-{}.tar.gz",
+        Local::now().format("%Y%m%d-%H%M%S")
+    ));
+
+    write_atomic(&path, &archive.finish()?)?;
+
+    remove_old_snapshots(&directory, keep)?;
+
+    Ok(path)
+}
+
+fn remove_old_snapshots(directory: &Path, keep: usize) -> std::io::Result<()> {
+    let mut snapshots = std::fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    snapshots.retain(|path| {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("snapshot-") && name.ends_with(".tar.gz"))
+    });
+
+    // timestamps in names sort chronologically
+    snapshots.sort();
+
+    for path in &snapshots[..snapshots.len().saturating_sub(keep)] {
+        std::fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
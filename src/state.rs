@@ -1,9 +1,21 @@
 use std::{
-    sync::{atomic::AtomicBool, Arc, Mutex},
-    time::Instant,
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
 
-use crate::sample_library::VectorLibrary;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    configuration::Throttle,
+    fuzzing::{MemoryUsage, MutationLevel, Reproduction},
+    sample::TreeNode,
+    sample_library::VectorLibrary,
+    triage::CasrSummary,
+};
 
 #[derive(Clone)]
 pub struct State {
@@ -12,30 +24,412 @@ pub struct State {
     pub total_crashes: usize,
     pub total_nonzero: usize,
     pub total_working: usize,
+    /// mutated samples identical to recently executed ones, not run
+    pub duplicates: usize,
+    /// executions that failed with error and were skipped
+    pub failed: usize,
+    /// failed executions where target could not be started
+    pub spawn_failures: usize,
+    /// failed executions where input could not be written to target
+    pub input_failures: usize,
+    /// executions where target closed stdin before reading whole input
+    pub input_cut_short: usize,
+    /// executions killed after running over timeout
+    pub timeouts: usize,
+    /// executions that did not hit any function of target
+    pub empty_traces: usize,
 
     pub start_time: Instant,
     pub last_unique_crash: Option<Instant>,
     pub last_new_path: Option<Instant>,
-    pub executions: ringbuffer::AllocRingBuffer<Instant>,
+    /// last time each exit code was returned by target
+    pub exit_codes_seen: HashMap<i32, Instant>,
+    pub speed: SpeedMeter,
+
+    pub mutators: HashMap<&'static str, MutatorStats>,
+
+    /// number of functions instrumented in target
+    pub functions_total: usize,
+    /// union of trajectories of all library entries
+    pub covered_functions: HashSet<usize>,
+
+    /// number of rules in grammar, zero when fuzzing with seeds
+    pub rules_total: usize,
+    /// rules applied in samples of library
+    pub covered_rules: HashSet<String>,
+
+    pub status: FuzzerStatus,
+
+    /// mutate known crashes instead of exploring
+    pub exploit_crashes: bool,
+
+    /// casr analysis results by crash name
+    pub casr_reports: HashMap<String, CasrSummary>,
+    /// crashes casr considers duplicates, by name of first such crash
+    pub casr_duplicates: HashMap<String, String>,
+
+    /// timeouts by function target was stuck in
+    pub stuck_locations: HashMap<String, usize>,
+
+    /// results of repeated runs by crash name
+    pub reproductions: HashMap<String, Reproduction>,
+
+    /// corpus export was requested by user
+    pub export_requested: bool,
+
+    /// memory held by library and fuzzer buffers as of last library change
+    pub memory: MemoryUsage,
+    /// library entries evicted to stay within memory budget
+    pub evicted_entries: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FuzzerStatus {
+    Running,
+    /// target could not be executed, fuzzing thread waits for retry
+    Paused(String),
+    /// fuzzing thread exited with error
+    Stopped(String),
+    /// configured limit was reached
+    Finished(String),
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MutatorStats {
+    pub level: MutationLevel,
+    pub applied: usize,
+    pub new_paths: usize,
+    pub improvements: usize,
+}
+
+/// Tracks failures among recent executions, budget is exceeded once
+/// share of failures in window grows over allowed rate.
+#[derive(Clone)]
+pub struct ErrorBudget {
+    window: usize,
+    allowed: usize,
+    /// outcomes of recent executions, true for failure
+    recent: VecDeque<bool>,
+    failures: usize,
+}
+
+impl ErrorBudget {
+    pub fn new(window: usize, max_rate: f64) -> Self {
+        ErrorBudget {
+            window,
+            allowed: (window as f64 * max_rate).floor() as usize,
+            recent: VecDeque::with_capacity(window),
+            failures: 0,
+        }
+    }
+
+    /// record outcome of execution, returns false if budget is exceeded
+    pub fn record(&mut self, failed: bool) -> bool {
+        if self.recent.len() == self.window && self.recent.pop_front() == Some(true) {
+            self.failures -= 1;
+        }
+
+        self.recent.push_back(failed);
+        if failed {
+            self.failures += 1;
+        }
+
+        self.failures <= self.allowed
+    }
+
+    pub fn reset(&mut self) {
+        self.recent.clear();
+        self.failures = 0;
+    }
+}
+
+/// Sleeps between executions to keep fuzzing within execution rate and
+/// share of CPU time, left for other users of shared machine.
+pub struct Throttler {
+    limit: Throttle,
+    /// moment next execution is due at when rate is limited
+    next: Instant,
+    /// start of current window and CPU time taken by then when CPU share is limited
+    window: (Instant, Duration),
+}
+
+impl Throttler {
+    /// executions this late behind schedule are not made up for by running faster
+    const MAX_LAG: Duration = Duration::from_secs(1);
+
+    /// CPU share is measured over windows of this length
+    const CPU_WINDOW: Duration = Duration::from_secs(1);
+
+    /// longest sleep before shutdown is checked again
+    const SLEEP_SLICE: Duration = Duration::from_millis(50);
+
+    pub fn new(limit: Throttle) -> Self {
+        Throttler {
+            limit,
+            next: Instant::now(),
+            window: (Instant::now(), cpu_time()),
+        }
+    }
+
+    /// sleep for given time or until fuzzing is shut down
+    fn sleep(duration: Duration, shutdown: &ShutdownToken) {
+        let until = Instant::now() + duration;
+
+        while !shutdown.is_cancelled() {
+            let left = until.saturating_duration_since(Instant::now());
+
+            if left.is_zero() {
+                break;
+            }
+
+            std::thread::sleep(left.min(Self::SLEEP_SLICE));
+        }
+    }
+
+    /// account for given number of executions that just finished, sleeping
+    /// if a limit is exceeded
+    pub fn pace(&mut self, executions: usize, shutdown: &ShutdownToken) {
+        if let Some(rate) = self.limit.execs {
+            let now = Instant::now();
+            self.next += Duration::from_secs_f64(executions as f64 / rate);
+
+            if self.next > now {
+                Self::sleep(self.next - now, shutdown);
+            } else if now - self.next > Self::MAX_LAG {
+                self.next = now;
+            }
+        }
+
+        if let Some(share) = self.limit.cpu {
+            let (start, cpu_start) = self.window;
+            let used = cpu_time().saturating_sub(cpu_start);
+
+            // wall time it takes CPU time used so far to be within share
+            let due = used.div_f64(share / 100.0);
+            let elapsed = start.elapsed();
+
+            if due > elapsed {
+                Self::sleep(due - elapsed, shutdown);
+            }
+
+            if elapsed >= Self::CPU_WINDOW {
+                self.window = (Instant::now(), cpu_time());
+            }
+        }
+    }
+}
+
+/// CPU time taken by fuzzer and its finished child processes
+#[cfg(unix)]
+fn cpu_time() -> Duration {
+    [libc::RUSAGE_SELF, libc::RUSAGE_CHILDREN]
+        .into_iter()
+        .map(|who| {
+            let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+
+            if unsafe { libc::getrusage(who, &mut usage) } != 0 {
+                return Duration::ZERO;
+            }
+
+            [usage.ru_utime, usage.ru_stime]
+                .into_iter()
+                .map(|time| {
+                    Duration::from_secs(time.tv_sec as u64)
+                        + Duration::from_micros(time.tv_usec as u64)
+                })
+                .sum()
+        })
+        .sum()
+}
+
+/// CPU share is rejected when configuration is checked outside of Unix
+#[cfg(not(unix))]
+fn cpu_time() -> Duration {
+    Duration::ZERO
+}
+
+/// Counts executions in fixed-size time buckets to estimate speed over a sliding window.
+#[derive(Clone)]
+pub struct SpeedMeter {
+    started: Instant,
+    window: Duration,
+    /// (bucket index since start, executions in bucket)
+    buckets: VecDeque<(u128, usize)>,
+    total: usize,
+}
+
+impl SpeedMeter {
+    const BUCKET_MILLIS: u128 = 100;
+
+    pub fn new(window: Duration) -> Self {
+        SpeedMeter {
+            started: Instant::now(),
+            window,
+            buckets: VecDeque::new(),
+            total: 0,
+        }
+    }
+
+    fn current_bucket(&self) -> u128 {
+        self.started.elapsed().as_millis() / Self::BUCKET_MILLIS
+    }
+
+    fn oldest_bucket_in_window(&self) -> u128 {
+        self.current_bucket()
+            .saturating_sub(self.window.as_millis() / Self::BUCKET_MILLIS)
+    }
+
+    pub fn record(&mut self) {
+        let bucket = self.current_bucket();
+
+        match self.buckets.back_mut() {
+            Some((index, count)) if *index == bucket => *count += 1,
+            _ => self.buckets.push_back((bucket, 1)),
+        }
+
+        let oldest = self.oldest_bucket_in_window();
+        while matches!(self.buckets.front(), Some(&(index, _)) if index < oldest) {
+            self.buckets.pop_front();
+        }
+
+        self.total += 1;
+    }
+
+    /// executions per second over configured window
+    pub fn windowed(&self) -> Option<f64> {
+        let oldest = self.oldest_bucket_in_window();
+
+        let executions: usize = self
+            .buckets
+            .iter()
+            .filter(|(index, _)| *index >= oldest)
+            .map(|(_, count)| count)
+            .sum();
+
+        let duration = self.started.elapsed().min(self.window).as_secs_f64();
+
+        (self.total > 0 && duration > 0.0).then(|| executions as f64 / duration)
+    }
+
+    /// executions per second since start
+    pub fn lifetime(&self) -> Option<f64> {
+        let duration = self.started.elapsed().as_secs_f64();
+
+        (self.total > 0 && duration > 0.0).then(|| self.total as f64 / duration)
+    }
 }
 
 impl State {
-    pub fn new() -> Self {
+    /// executions per second over configured window
+    pub fn execution_speed(&self) -> Option<f64> {
+        self.speed.windowed()
+    }
+
+    /// covered and total number of functions
+    pub fn coverage(&self) -> (usize, usize) {
+        (
+            self.covered_functions.len().min(self.functions_total),
+            self.functions_total,
+        )
+    }
+
+    /// covered and total number of grammar rules
+    pub fn rule_coverage(&self) -> (usize, usize) {
+        (self.covered_rules.len(), self.rules_total)
+    }
+
+    /// remember rules applied in tree of library sample
+    pub fn record_rules(&mut self, tree: &TreeNode) {
+        if self.rules_total > 0 {
+            self.covered_rules
+                .extend(tree.rules().into_iter().map(str::to_string));
+        }
+    }
+
+    /// resume fuzzing thread if it is paused
+    pub fn retry(&mut self) {
+        if let FuzzerStatus::Paused(_) = self.status {
+            crate::log!("retrying after target error");
+            self.status = FuzzerStatus::Running;
+        }
+    }
+
+    /// executions that could not contribute to coverage
+    pub fn wasted(&self) -> usize {
+        self.duplicates + self.failed + self.timeouts + self.empty_traces
+    }
+
+    pub fn toggle_crash_exploitation(&mut self) {
+        self.exploit_crashes = !self.exploit_crashes;
+
+        if self.exploit_crashes {
+            crate::log!("crash exploitation mode enabled");
+        } else {
+            crate::log!("crash exploitation mode disabled");
+        }
+    }
+
+    pub fn request_export(&mut self) {
+        self.export_requested = true;
+    }
+
+    pub fn new(speed_window: Duration) -> Self {
         State {
             tested_samples: 0,
             improvements: 0,
             total_crashes: 0,
             total_nonzero: 0,
             total_working: 0,
+            duplicates: 0,
+            failed: 0,
+            spawn_failures: 0,
+            input_failures: 0,
+            input_cut_short: 0,
+            timeouts: 0,
+            empty_traces: 0,
             start_time: Instant::now(),
             last_unique_crash: None,
             last_new_path: None,
-            executions: ringbuffer::AllocRingBuffer::with_capacity(512),
+            exit_codes_seen: HashMap::new(),
+            speed: SpeedMeter::new(speed_window),
+            mutators: HashMap::new(),
+            functions_total: 0,
+            covered_functions: HashSet::new(),
+            rules_total: 0,
+            covered_rules: HashSet::new(),
+            status: FuzzerStatus::Running,
+            exploit_crashes: false,
+            casr_reports: HashMap::new(),
+            casr_duplicates: HashMap::new(),
+            reproductions: HashMap::new(),
+            stuck_locations: HashMap::new(),
+            export_requested: false,
+            memory: MemoryUsage::default(),
+            evicted_entries: 0,
         }
     }
 }
 
-pub static mut FUZZER_RUNNNIG: AtomicBool = AtomicBool::new(true);
+/// Shared flag telling fuzzing thread and interface to stop, set by
+/// interface on quit or by Ctrl+C handler.
+#[derive(Clone, Default)]
+pub struct ShutdownToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ShutdownToken {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
 
 pub type AM<T> = Arc<Mutex<T>>;
 
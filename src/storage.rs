@@ -0,0 +1,127 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Write file so that it either keeps previous content or has the new one
+/// in full: content goes to temporary file in the same directory which is
+/// then renamed over target, directory is synced so rename survives host crash
+/// (on unix, directories can not be opened for syncing elsewhere).
+pub fn write_atomic(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    // threads writing the same path at once get different temporary files
+    static WRITES: AtomicUsize = AtomicUsize::new(0);
+    let write = WRITES.fetch_add(1, Ordering::Relaxed);
+
+    let mut temporary_name = std::ffi::OsString::from(".");
+    temporary_name.push(path.file_name().unwrap_or_default());
+    temporary_name.push(format!(".tmp-{}-{write}", std::process::id()));
+    let temporary = directory.join(temporary_name);
+
+    let result = (|| {
+        let mut file = File::create(&temporary)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+
+        std::fs::rename(&temporary, path)?;
+
+        #[cfg(unix)]
+        File::open(directory)?.sync_all()?;
+
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temporary);
+    }
+
+    result
+}
+
+/// Name of file marking output directory as used by running instance.
+pub const LOCK_FILE: &str = ".bocchi.lock";
+
+#[derive(Debug, thiserror::Error)]
+pub enum LockError {
+    #[error("output directory is used by running instance (pid {0})")]
+    Locked(u32),
+    #[error("failed to lock output directory: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Lock of output directory holding pid of fuzzer, released on drop.
+pub struct OutputLock {
+    path: PathBuf,
+}
+
+fn lock_owner(path: &Path) -> Option<u32> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    // signal 0 only checks whether process exists, process of another user can not be signalled
+    let signalled = unsafe { libc::kill(pid as libc::pid_t, 0) } == 0;
+
+    signalled || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// there are no signals to probe process with, tasklist names it if it runs
+#[cfg(not(unix))]
+fn is_running(pid: u32) -> bool {
+    std::process::Command::new("tasklist")
+        .arg("/NH")
+        .arg("/FI")
+        .arg(format!("PID eq {pid}"))
+        .output()
+        .map_or(true, |output| {
+            String::from_utf8_lossy(&output.stdout)
+                .split_whitespace()
+                .any(|field| field == pid.to_string())
+        })
+}
+
+impl OutputLock {
+    /// Take lock of directory, lock left by process that is no longer
+    /// running is taken over, lock of running one only if `force` is set.
+    pub fn acquire(directory: &Path, force: bool) -> Result<Self, LockError> {
+        let path = directory.join(LOCK_FILE);
+
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                if let Some(pid) = lock_owner(&path) {
+                    if pid != std::process::id() && is_running(pid) && !force {
+                        return Err(LockError::Locked(pid));
+                    }
+                }
+
+                File::create(&path)?
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        write!(file, "{}", std::process::id())?;
+
+        Ok(OutputLock { path })
+    }
+}
+
+/// Remove lock at given path if it is held by this process.
+pub fn release_lock(path: &Path) {
+    if lock_owner(path) == Some(std::process::id()) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+impl Drop for OutputLock {
+    fn drop(&mut self) {
+        release_lock(&self.path);
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{mpsc, Arc},
+    thread,
+};
+
+use anyhow::{anyhow, bail, Context};
+use serde_derive::Serialize;
+
+use crate::configuration::{CasrTool, FuzzConfig, PassStyle};
+
+/// Crash classification extracted from casr report.
+#[derive(Clone, Debug, Serialize)]
+pub struct CasrSummary {
+    /// EXPLOITABLE, PROBABLY_EXPLOITABLE or NOT_EXPLOITABLE
+    pub severity: String,
+    pub classification: String,
+    /// source location of crash if debug info is present
+    pub crash_line: Option<String>,
+}
+
+impl CasrSummary {
+    /// crashes with equal keys are considered duplicates by casr
+    pub fn dedup_key(&self) -> (&str, Option<&str>) {
+        (&self.classification, self.crash_line.as_deref())
+    }
+}
+
+impl CasrTool {
+    fn executable(self) -> &'static str {
+        match self {
+            CasrTool::Gdb => "casr-gdb",
+            CasrTool::San => "casr-san",
+        }
+    }
+
+    /// look up tool executable in PATH
+    pub fn is_installed(self) -> bool {
+        std::env::var_os("PATH")
+            .map(|paths| {
+                std::env::split_paths(&paths).any(|dir| dir.join(self.executable()).is_file())
+            })
+            .unwrap_or(false)
+    }
+}
+
+/// name of crash and its analysis
+pub type TriageResult = (String, Result<CasrSummary, anyhow::Error>);
+
+/// Analyzes crashes one at a time in background thread, so that slow
+/// casr runs neither block bookkeeping nor pile up as parallel processes.
+pub struct Triager {
+    jobs: mpsc::Sender<(String, PathBuf)>,
+    results: mpsc::Receiver<TriageResult>,
+}
+
+impl Triager {
+    pub fn spawn(tool: CasrTool, config: Arc<FuzzConfig>) -> Self {
+        let (jobs, received) = mpsc::channel::<(String, PathBuf)>();
+        let (sender, results) = mpsc::channel();
+
+        thread::spawn(move || {
+            for (name, path) in received {
+                if sender.send((name, analyze(tool, &config, &path))).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Triager { jobs, results }
+    }
+
+    pub fn submit(&self, name: &str, crash_path: &Path) {
+        let _ = self.jobs.send((name.to_string(), crash_path.to_path_buf()));
+    }
+
+    /// analyses finished so far, in order crashes were submitted
+    pub fn results(&self) -> impl Iterator<Item = TriageResult> + '_ {
+        self.results.try_iter()
+    }
+}
+
+/// path of casr report written for crash saved at given path
+pub fn report_path(crash_path: &Path) -> PathBuf {
+    let mut path = crash_path.as_os_str().to_owned();
+    path.push(".casrep");
+    PathBuf::from(path)
+}
+
+fn parse_report(content: &[u8]) -> Result<CasrSummary, anyhow::Error> {
+    let report: serde_json::Value = serde_json::from_slice(content)?;
+
+    let severity = &report["CrashSeverity"];
+
+    let field = |value: &serde_json::Value, name: &str| {
+        value[name]
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| anyhow!("report has no `{name}` field"))
+    };
+
+    Ok(CasrSummary {
+        severity: field(severity, "Type")?,
+        classification: field(severity, "ShortDescription")?,
+        crash_line: report["CrashLine"]
+            .as_str()
+            .filter(|line| !line.is_empty())
+            .map(|line| line.to_string()),
+    })
+}
+
+/// Run target on saved crash under casr, report is left next to crash.
+pub fn analyze(
+    tool: CasrTool,
+    config: &FuzzConfig,
+    crash_path: &Path,
+) -> Result<CasrSummary, anyhow::Error> {
+    let report = report_path(crash_path);
+
+    let mut command = Command::new(tool.executable());
+    command.arg("--output").arg(&report);
+
+    if config.binary.pass_style == PassStyle::Stdin {
+        command.arg("--stdin").arg(crash_path);
+    }
+
+    command.arg("--").arg(&config.binary.path);
+
+    if config.binary.pass_style == PassStyle::File {
+        command.arg(crash_path);
+    }
+
+    let output = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .output()
+        .with_context(|| format!("running {}", tool.executable()))?;
+
+    if !output.status.success() {
+        bail!(
+            "{} failed: {}",
+            tool.executable(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let content = std::fs::read(&report)
+        .with_context(|| format!("reading casr report {}", report.display()))?;
+
+    parse_report(&content).with_context(|| format!("parsing casr report {}", report.display()))
+}
@@ -1,7 +1,7 @@
 use std::{
-    collections::HashSet,
     marker::PhantomData,
-    sync::MutexGuard,
+    path::PathBuf,
+    sync::{Arc, MutexGuard},
     time::{Duration, Instant},
 };
 
@@ -12,32 +12,111 @@ use crossterm::{
 };
 use humantime::format_duration;
 use itertools::Itertools;
-use ringbuffer::{RingBuffer, RingBufferExt};
 use tui::{
     backend::{Backend, CrosstermBackend},
     layout::{Constraint, Direction, Layout, Margin, Rect},
-    widgets::{Block, Borders, Cell, Row, Table},
+    style::{Color, Modifier, Style},
+    text::Spans,
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, Tabs, Wrap},
     Frame, Terminal,
 };
 
 use crate::{
     configuration::FuzzConfig,
     execution::{ExecResult, RunTrace},
-    state::{Library, State, AM},
+    fuzzing::MutationLevel,
+    log::Level,
+    state::{FuzzerStatus, Library, ShutdownToken, State, AM},
 };
 
+/// size in bytes with binary unit prefix
+fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tab {
+    Overview,
+    Corpus,
+    Crashes,
+    Mutators,
+    Log,
+}
+
+impl Tab {
+    const ALL: [Tab; 5] = [
+        Tab::Overview,
+        Tab::Corpus,
+        Tab::Crashes,
+        Tab::Mutators,
+        Tab::Log,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            Tab::Overview => "Overview",
+            Tab::Corpus => "Corpus",
+            Tab::Crashes => "Crashes",
+            Tab::Mutators => "Mutators",
+            Tab::Log => "Log",
+        }
+    }
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|&tab| tab == self).unwrap()
+    }
+
+    fn next(self) -> Self {
+        Self::ALL[(self.index() + 1) % Self::ALL.len()]
+    }
+
+    fn prev(self) -> Self {
+        Self::ALL[(self.index() + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
+/// Keys handled by [`serve_ui`] and what they do, shown in help panel.
+const KEYS: [(&str, &str); 9] = [
+    ("1-5", "switch to tab with given number"),
+    ("Right, Tab", "next tab"),
+    ("Left, Shift+Tab", "previous tab"),
+    ("l", "cycle minimal level of displayed messages"),
+    ("x", "toggle crash exploitation mode"),
+    ("e", "export corpus in LibAFL layout"),
+    ("r", "retry after fuzzing was paused on errors"),
+    ("?", "show or hide this help"),
+    ("q", "quit"),
+];
+
 pub struct TerminalUi<B: Backend + std::io::Write> {
     library: AM<Library>,
     state: AM<State>,
     terminal: Option<Terminal<B>>,
-    config: &'static FuzzConfig,
+    config: Arc<FuzzConfig>,
+    tab: Tab,
+    log_level: Level,
+    help: bool,
 }
 
 impl TerminalUi<CrosstermBackend<std::io::Stdout>> {
     pub fn new(
         library: AM<Library>,
         state: AM<State>,
-        config: &'static FuzzConfig,
+        config: Arc<FuzzConfig>,
     ) -> Result<Self, anyhow::Error> {
         enable_raw_mode()?;
         let mut stdout = std::io::stdout();
@@ -50,6 +129,9 @@ impl TerminalUi<CrosstermBackend<std::io::Stdout>> {
             state,
             terminal: Some(terminal),
             config,
+            tab: Tab::Overview,
+            log_level: Level::Info,
+            help: false,
         })
     }
 }
@@ -57,11 +139,52 @@ impl TerminalUi<CrosstermBackend<std::io::Stdout>> {
 struct TerminalInstance<'m, B: Backend + std::io::Write> {
     pub library: MutexGuard<'m, Library>,
     pub state: MutexGuard<'m, State>,
-    pub config: &'static FuzzConfig,
+    pub config: &'m FuzzConfig,
+    pub tab: Tab,
+    pub log_level: Level,
+    pub help: bool,
     pub backend: PhantomData<B>,
 }
 
 impl<B: Backend + std::io::Write> TerminalUi<B> {
+    pub fn select_tab(&mut self, index: usize) {
+        if let Some(&tab) = Tab::ALL.get(index) {
+            self.tab = tab;
+        }
+    }
+
+    pub fn next_tab(&mut self) {
+        self.tab = self.tab.next();
+    }
+
+    pub fn prev_tab(&mut self) {
+        self.tab = self.tab.prev();
+    }
+
+    pub fn toggle_help(&mut self) {
+        self.help = !self.help;
+    }
+
+    pub fn close_help(&mut self) {
+        self.help = false;
+    }
+
+    pub fn cycle_log_level(&mut self) {
+        self.log_level = self.log_level.next();
+    }
+
+    pub fn retry(&mut self) {
+        self.state.lock().unwrap().retry();
+    }
+
+    pub fn toggle_crash_exploitation(&mut self) {
+        self.state.lock().unwrap().toggle_crash_exploitation();
+    }
+
+    pub fn request_export(&mut self) {
+        self.state.lock().unwrap().request_export();
+    }
+
     pub fn tick(&mut self) -> Result<(), anyhow::Error> {
         let mut terminal = self.terminal.take().unwrap();
 
@@ -75,7 +198,10 @@ impl<B: Backend + std::io::Write> TerminalUi<B> {
             let mut instance = TerminalInstance {
                 library,
                 state,
-                config: self.config,
+                config: &self.config,
+                tab: self.tab,
+                log_level: self.log_level,
+                help: self.help,
                 backend: PhantomData {},
             };
 
@@ -96,6 +222,135 @@ impl<'m, B: Backend + std::io::Write> TerminalInstance<'m, B> {
             horizontal: 1,
         });
 
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+            .split(target);
+
+        self.draw_tabs(frame, layout[0]);
+
+        match self.tab {
+            Tab::Overview => self.draw_overview(frame, layout[1]),
+            Tab::Corpus => self.draw_corpus(frame, layout[1]),
+            Tab::Crashes => self.draw_crashes(frame, layout[1]),
+            Tab::Mutators => self.draw_mutators(frame, layout[1]),
+            Tab::Log => self.write_messages_panel(frame, layout[1]),
+        }
+
+        self.draw_status_popup(frame, target);
+
+        if self.help {
+            Self::draw_help(frame, target);
+        }
+    }
+
+    /// area in the middle of target that popups are drawn over
+    fn popup_area(target: Rect) -> Rect {
+        let vertical = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Percentage(30),
+                    Constraint::Percentage(40),
+                    Constraint::Percentage(30),
+                ]
+                .as_ref(),
+            )
+            .split(target);
+
+        Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [
+                    Constraint::Percentage(15),
+                    Constraint::Percentage(70),
+                    Constraint::Percentage(15),
+                ]
+                .as_ref(),
+            )
+            .split(vertical[1])[1]
+    }
+
+    /// Keyboard shortcuts over the rest of interface, including status popup.
+    fn draw_help(frame: &mut Frame<B>, target: Rect) {
+        let mut popup = Self::popup_area(target);
+        // every key gets its row, plus borders
+        popup.height = popup
+            .height
+            .max(KEYS.len() as u16 + 2)
+            .min(target.bottom() - popup.y);
+
+        let rows = KEYS
+            .iter()
+            .map(|(key, effect)| Row::new([Cell::from(*key), Cell::from(*effect)]));
+
+        let table = Table::new(rows)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("keys (press ? or Esc to close)"),
+            )
+            .widths(&[Constraint::Length(16), Constraint::Min(0)]);
+
+        frame.render_widget(Clear, popup);
+        frame.render_widget(table, popup);
+    }
+
+    /// Error message over the rest of interface if fuzzing is not running.
+    fn draw_status_popup(&mut self, frame: &mut Frame<B>, target: Rect) {
+        let (title, message, hint) = match &self.state.status {
+            FuzzerStatus::Running => return,
+            FuzzerStatus::Paused(message) => {
+                ("fuzzing paused", message, "press r to retry or q to quit")
+            }
+            FuzzerStatus::Stopped(message) => ("fuzzing stopped", message, "press q to quit"),
+            FuzzerStatus::Finished(message) => ("fuzzing finished", message, "press q to quit"),
+        };
+
+        let popup = Self::popup_area(target);
+
+        let style = Style::default().fg(Color::Red).add_modifier(Modifier::BOLD);
+
+        let text = vec![
+            Spans::from(message.as_str()),
+            Spans::from(""),
+            Spans::from(hint),
+        ];
+
+        let paragraph = Paragraph::new(text)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(style)
+                    .title(title),
+            )
+            .style(style)
+            .wrap(Wrap { trim: false });
+
+        frame.render_widget(Clear, popup);
+        frame.render_widget(paragraph, popup);
+    }
+
+    fn draw_tabs(&mut self, frame: &mut Frame<B>, target: Rect) {
+        let titles = Tab::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, tab)| Spans::from(format!("{} {}", i + 1, tab.title())))
+            .collect_vec();
+
+        let tabs = Tabs::new(titles)
+            .block(Block::default().borders(Borders::ALL))
+            .select(self.tab.index())
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            );
+
+        frame.render_widget(tabs, target);
+    }
+
+    fn draw_overview(&mut self, frame: &mut Frame<B>, target: Rect) {
         let layout = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
@@ -103,9 +358,114 @@ impl<'m, B: Backend + std::io::Write> TerminalInstance<'m, B> {
 
         self.write_left_panel(frame, layout[0]);
 
-        self.write_right_panel(frame, layout[1]);
+        self.write_messages_panel(frame, layout[1]);
+    }
 
-        //frame.render_widget(block, self.size);
+    fn draw_corpus(&mut self, frame: &mut Frame<B>, target: Rect) {
+        let rows = self
+            .library
+            .iter()
+            .map(|(trace, entry, sample)| {
+                vec![
+                    entry.unique_name.clone().unwrap_or_else(|| "-".to_string()),
+                    trace.result.to_string(),
+                    trace.trajectory.len().to_string(),
+                    sample.get_folded().len().to_string(),
+                ]
+            })
+            .collect_vec();
+
+        Self::write_table_in_frame(
+            frame,
+            target,
+            &["name", "result", "functions", "size"],
+            rows,
+            "corpus",
+        )
+    }
+
+    fn draw_crashes(&mut self, frame: &mut Frame<B>, target: Rect) {
+        let rows = self
+            .library
+            .iter()
+            .filter(|(trace, _entry, _sample)| matches!(trace.result, ExecResult::Signal))
+            .map(|(trace, entry, sample)| {
+                let name = entry.unique_name.clone().unwrap_or_else(|| "-".to_string());
+                let path = PathBuf::from(&self.config.output.directory).join(&name);
+                let (severity, classification) = match self.state.casr_reports.get(&name) {
+                    Some(report) => match self.state.casr_duplicates.get(&name) {
+                        Some(original) => (
+                            report.severity.clone(),
+                            format!("{} (same as {original})", report.classification),
+                        ),
+                        None => (report.severity.clone(), report.classification.clone()),
+                    },
+                    None => ("-".to_string(), "-".to_string()),
+                };
+                let reproduced = self
+                    .state
+                    .reproductions
+                    .get(&name)
+                    .map_or_else(|| "-".to_string(), ToString::to_string);
+                vec![
+                    name,
+                    trace.trajectory.len().to_string(),
+                    sample.get_folded().len().to_string(),
+                    reproduced,
+                    severity,
+                    classification,
+                    path.to_string_lossy().to_string(),
+                ]
+            })
+            .collect_vec();
+
+        Self::write_table_in_frame(
+            frame,
+            target,
+            &[
+                "name",
+                "functions",
+                "size",
+                "reproduced",
+                "severity",
+                "class",
+                "saved as",
+            ],
+            rows,
+            "crashes",
+        )
+    }
+
+    fn draw_mutators(&mut self, frame: &mut Frame<B>, target: Rect) {
+        let rows = self
+            .state
+            .mutators
+            .iter()
+            .sorted_by_key(|(name, _stats)| **name)
+            .map(|(name, stats)| {
+                vec![
+                    name.to_string(),
+                    stats.level.name().to_string(),
+                    stats.applied.to_string(),
+                    stats.new_paths.to_string(),
+                    stats.improvements.to_string(),
+                ]
+            })
+            .collect_vec();
+
+        Self::write_table_in_frame(
+            frame,
+            target,
+            &[
+                "mutator",
+                "level",
+                "applied",
+                "new paths",
+                "size improvements",
+            ],
+            rows,
+            "mutators",
+        )
     }
 
     fn draw_outer_frame(&mut self, frame: &mut Frame<B>, target: Rect) {
@@ -124,7 +484,9 @@ impl<'m, B: Backend + std::io::Write> TerminalInstance<'m, B> {
             }
         };
 
-        let block = Block::default().title(title).borders(Borders::ALL);
+        let block = Block::default()
+            .title(format!("{title} (press ? for help)"))
+            .borders(Borders::ALL);
         frame.render_widget(block, target);
     }
 
@@ -143,48 +505,270 @@ impl<'m, B: Backend + std::io::Write> TerminalInstance<'m, B> {
                 "  - crashes".to_string(),
                 self.state.total_crashes.to_string(),
             ),
+            ("wasted".to_string(), self.state.wasted().to_string()),
+            (
+                "  - duplicates skipped".to_string(),
+                self.state.duplicates.to_string(),
+            ),
+            (
+                "  - empty traces".to_string(),
+                self.state.empty_traces.to_string(),
+            ),
+            ("  - timeouts".to_string(), self.state.timeouts.to_string()),
+            (
+                "  - failed to spawn".to_string(),
+                self.state.spawn_failures.to_string(),
+            ),
+            (
+                "  - input write errors".to_string(),
+                self.state.input_failures.to_string(),
+            ),
+            (
+                "  - other failures".to_string(),
+                (self.state.failed - self.state.spawn_failures - self.state.input_failures)
+                    .to_string(),
+            ),
+            (
+                "input cut short".to_string(),
+                self.state.input_cut_short.to_string(),
+            ),
             ("execution speed".to_string(), self.get_execution_speed()),
+            ("sample sizes".to_string(), self.get_sample_sizes()),
+            ("  - corpus total".to_string(), self.get_corpus_bytes()),
             (
                 "size improvements".to_string(),
                 self.state.improvements.to_string(),
             ),
+            ("functions covered".to_string(), self.get_coverage()),
+            (
+                "mode".to_string(),
+                if self.state.exploit_crashes {
+                    "exploiting crashes"
+                } else {
+                    "exploring"
+                }
+                .to_string(),
+            ),
         ]
     }
 
-    fn extract_unique_stats(&mut self) -> Vec<(String, String)> {
+    fn get_memory_usage(&self) -> String {
+        const MEGABYTE: f64 = 1024.0 * 1024.0;
+
+        let memory = self.state.memory;
+        let total = (memory.library + memory.buffers) as f64 / MEGABYTE;
+
+        let mut usage = format!(
+            "{total:.1} MiB (library {:.1}, buffers {:.1})",
+            memory.library as f64 / MEGABYTE,
+            memory.buffers as f64 / MEGABYTE
+        );
+
+        if let Some(budget) = self.config.fuzzing.memory_budget {
+            usage.push_str(&format!(" / {budget} MiB"));
+        }
+
+        if self.state.evicted_entries > 0 {
+            usage.push_str(&format!(", {} evicted", self.state.evicted_entries));
+        }
+
+        usage
+    }
+
+    /// min, median and max size of samples in library, growing sizes slow execution down
+    fn get_sample_sizes(&self) -> String {
+        let sizes = self
+            .library
+            .iter()
+            .map(|(_trace, _entry, sample)| sample.get_folded().len())
+            .sorted()
+            .collect::<Vec<_>>();
+
+        match (sizes.first(), sizes.last()) {
+            (Some(&min), Some(&max)) => format!(
+                "{} / {} / {} (min / median / max)",
+                format_bytes(min),
+                format_bytes(sizes[sizes.len() / 2]),
+                format_bytes(max)
+            ),
+            _ => "n/a".to_string(),
+        }
+    }
+
+    fn get_corpus_bytes(&self) -> String {
+        format_bytes(
+            self.library
+                .iter()
+                .map(|(_trace, _entry, sample)| sample.get_folded().len())
+                .sum(),
+        )
+    }
+
+    fn get_coverage(&self) -> String {
+        let (covered, total) = self.state.coverage();
+
+        if total == 0 {
+            return "n/a".to_string();
+        }
+
+        format!(
+            "{covered} / {total} ({:.1}%)",
+            covered as f64 * 100.0 / total as f64
+        )
+    }
+
+    /// Shape of derivation trees in library, rule coverage and
+    /// new paths found by each level of mutations.
+    fn extract_grammar_stats(&mut self) -> Vec<(String, String)> {
+        let (depth, nodes) = self
+            .library
+            .iter()
+            .map(|(_trace, _entry, sample)| (sample.tree().depth(), sample.tree().node_count()))
+            .fold((0, 0), |(depth, nodes), (d, n)| (depth + d, nodes + n));
+
+        let samples = self.library.len().max(1) as f64;
+
+        let (covered, total) = self.state.rule_coverage();
+
+        let new_paths = |level: MutationLevel| -> usize {
+            self.state
+                .mutators
+                .values()
+                .filter(|stats| stats.level == level)
+                .map(|stats| stats.new_paths)
+                .sum()
+        };
+
+        let (tree, binary, fresh) = (
+            new_paths(MutationLevel::Tree),
+            new_paths(MutationLevel::Binary),
+            new_paths(MutationLevel::Fresh),
+        );
+
+        let tree_share = if tree + binary > 0 {
+            format!(
+                " ({:.1}% tree)",
+                tree as f64 * 100.0 / (tree + binary) as f64
+            )
+        } else {
+            String::new()
+        };
+
         vec![
-            ("unique paths".to_string(), self.library.len().to_string()),
             (
-                "unique exit codes".to_string(),
-                self.library
-                    .iter()
-                    .filter_map(|(trace, _sample)| {
-                        if let RunTrace {
-                            result: ExecResult::Code(code),
-                            ..
-                        } = trace
-                        {
-                            Some(code)
-                        } else {
-                            None
-                        }
-                    })
-                    .collect::<HashSet<_>>()
-                    .len()
-                    .to_string(),
+                "average tree depth".to_string(),
+                format!("{:.1}", depth as f64 / samples),
             ),
             (
-                "unique crashes".to_string(),
-                self.library
-                    .iter()
-                    .map(|p| p.0)
-                    .filter(|run| matches!(run.result, ExecResult::Signal))
-                    .count()
-                    .to_string(),
+                "average tree size".to_string(),
+                format!("{:.1} nodes", nodes as f64 / samples),
             ),
+            (
+                "rules covered".to_string(),
+                format!(
+                    "{covered} / {total} ({:.1}%)",
+                    covered as f64 * 100.0 / total.max(1) as f64
+                ),
+            ),
+            (
+                "tree / binary paths".to_string(),
+                format!("{tree} / {binary}{tree_share}"),
+            ),
+            ("fresh sample paths".to_string(), fresh.to_string()),
         ]
     }
 
+    fn extract_unique_stats(&mut self) -> Vec<(String, String)> {
+        const TOP_EXIT_CODES: usize = 5;
+
+        let paths_by_code = self
+            .library
+            .iter()
+            .filter_map(|(trace, _entry, _sample)| {
+                if let RunTrace {
+                    result: ExecResult::Code(code),
+                    ..
+                } = trace
+                {
+                    Some(*code)
+                } else {
+                    None
+                }
+            })
+            .counts();
+
+        let mut stats = vec![
+            ("unique paths".to_string(), self.library.len().to_string()),
+            (
+                "  - distinct samples".to_string(),
+                self.library.distinct_items().to_string(),
+            ),
+            ("memory".to_string(), self.get_memory_usage()),
+            (
+                "unique exit codes".to_string(),
+                paths_by_code.len().to_string(),
+            ),
+        ];
+
+        stats.extend(
+            paths_by_code
+                .into_iter()
+                .sorted_by_key(|&(code, paths)| (std::cmp::Reverse(paths), code))
+                .take(TOP_EXIT_CODES)
+                .map(|(code, paths)| {
+                    (
+                        format!("  - code {code}"),
+                        format!(
+                            "{paths} paths, seen {} ago",
+                            Self::na_duration(self.state.exit_codes_seen.get(&code).copied())
+                        ),
+                    )
+                }),
+        );
+
+        let crashes = self
+            .library
+            .iter()
+            .map(|p| p.0)
+            .filter(|run| matches!(run.result, ExecResult::Signal))
+            .count();
+
+        stats.push(("unique crashes".to_string(), crashes.to_string()));
+
+        let flaky = self
+            .state
+            .reproductions
+            .values()
+            .filter(|reproduction| reproduction.is_flaky())
+            .count();
+
+        if flaky > 0 {
+            stats.push(("  - flaky".to_string(), flaky.to_string()));
+        }
+
+        if !self.state.casr_reports.is_empty() {
+            stats.push((
+                "  - unique by casr".to_string(),
+                crashes
+                    .saturating_sub(self.state.casr_duplicates.len())
+                    .to_string(),
+            ));
+        }
+
+        let responses = self
+            .library
+            .iter()
+            .filter_map(|(trace, _entry, _sample)| trace.response)
+            .unique()
+            .count();
+
+        if responses > 0 {
+            stats.push(("response classes".to_string(), responses.to_string()));
+        }
+
+        stats
+    }
+
     fn format_duration(duration: Duration) -> String {
         format_duration(Duration::from_secs(duration.as_secs())).to_string()
     }
@@ -215,20 +799,12 @@ impl<'m, B: Backend + std::io::Write> TerminalInstance<'m, B> {
     }
 
     fn get_execution_speed(&mut self) -> String {
-        let now = Instant::now();
-
-        self.state
-            .executions
-            .front()
-            .map(|&time| {
-                let items = self.state.executions.len() as f64;
-
-                let duration = (now - time).as_secs_f64();
-
-                items / duration
-            })
-            .map(|execs| format!("{:.1}/s", execs))
-            .unwrap_or_else(|| "n/a".to_string())
+        match (self.state.speed.windowed(), self.state.speed.lifetime()) {
+            (Some(current), Some(lifetime)) => {
+                format!("{:.1}/s (lifetime {:.1}/s)", current, lifetime)
+            }
+            _ => "n/a".to_string(),
+        }
     }
 
     fn write_stats(frame: &mut Frame<B>, target: Rect, stats: Vec<(String, String)>) {
@@ -243,10 +819,10 @@ impl<'m, B: Backend + std::io::Write> TerminalInstance<'m, B> {
         frame.render_widget(table, target);
     }
 
-    fn write_list(frame: &mut Frame<B>, target: Rect, stats: Vec<String>) {
+    fn write_list(frame: &mut Frame<B>, target: Rect, stats: Vec<(String, Style)>) {
         let rows = stats
             .into_iter()
-            .map(|item| Row::new(vec![Cell::from(item)]).height(1));
+            .map(|(item, style)| Row::new(vec![Cell::from(item)]).style(style).height(1));
 
         let table = Table::new(rows)
             .block(Block::default().borders(Borders::NONE))
@@ -255,6 +831,42 @@ impl<'m, B: Backend + std::io::Write> TerminalInstance<'m, B> {
         frame.render_widget(table, target);
     }
 
+    fn write_table_in_frame(
+        frame: &mut Frame<B>,
+        mut target: Rect,
+        header: &[&str],
+        rows: Vec<Vec<String>>,
+        title: &str,
+    ) {
+        let block = Block::default().title(title).borders(Borders::ALL);
+        frame.render_widget(block, target);
+
+        target = target.inner(&Margin {
+            vertical: 1,
+            horizontal: 1,
+        });
+
+        let widths = header
+            .iter()
+            .map(|_| Constraint::Percentage(100 / header.len() as u16))
+            .collect_vec();
+
+        let header = Row::new(header.iter().map(|&title| Cell::from(title)))
+            .style(Style::default().add_modifier(Modifier::BOLD))
+            .height(1);
+
+        let rows = rows
+            .into_iter()
+            .map(|row| Row::new(row.into_iter().map(Cell::from)).height(1));
+
+        let table = Table::new(rows)
+            .header(header)
+            .block(Block::default().borders(Borders::NONE))
+            .widths(&widths);
+
+        frame.render_widget(table, target);
+    }
+
     fn write_stats_in_frame(
         frame: &mut Frame<B>,
         mut target: Rect,
@@ -272,7 +884,12 @@ impl<'m, B: Backend + std::io::Write> TerminalInstance<'m, B> {
         Self::write_stats(frame, target, stats)
     }
 
-    fn write_list_in_frame(frame: &mut Frame<B>, mut target: Rect, list: Vec<String>, title: &str) {
+    fn write_list_in_frame(
+        frame: &mut Frame<B>,
+        mut target: Rect,
+        list: Vec<(String, Style)>,
+        title: &str,
+    ) {
         let block = Block::default().title(title).borders(Borders::ALL);
         frame.render_widget(block, target);
 
@@ -285,16 +902,31 @@ impl<'m, B: Backend + std::io::Write> TerminalInstance<'m, B> {
     }
 
     fn write_left_panel(&mut self, frame: &mut Frame<B>, target: Rect) {
+        let grammar_mode = matches!(
+            self.config.input,
+            crate::configuration::InputOptions::Grammar { .. }
+        );
+
+        let constraints = if grammar_mode {
+            [
+                Constraint::Length(5),
+                Constraint::Length(18),
+                Constraint::Min(0),
+                Constraint::Length(7),
+            ]
+            .as_ref()
+        } else {
+            [
+                Constraint::Percentage(25),
+                Constraint::Percentage(40),
+                Constraint::Percentage(35),
+            ]
+            .as_ref()
+        };
+
         let layout = Layout::default()
             .direction(Direction::Vertical)
-            .constraints(
-                [
-                    Constraint::Percentage(30),
-                    Constraint::Percentage(45),
-                    Constraint::Percentage(25),
-                ]
-                .as_ref(),
-            )
+            .constraints(constraints)
             .split(target);
 
         let time_stats = self.extract_time_stats();
@@ -308,22 +940,48 @@ impl<'m, B: Backend + std::io::Write> TerminalInstance<'m, B> {
         let unique_stats = self.extract_unique_stats();
 
         Self::write_stats_in_frame(frame, layout[2], unique_stats, "uniques");
+
+        if grammar_mode {
+            let grammar_stats = self.extract_grammar_stats();
+
+            Self::write_stats_in_frame(frame, layout[3], grammar_stats, "grammar");
+        }
     }
 
-    fn format_log(&self, space: Rect) -> Vec<String> {
-        let log = crate::log::pull_messages(space.height as usize)
+    fn level_style(level: Level) -> Style {
+        match level {
+            Level::Debug => Style::default().fg(Color::DarkGray),
+            Level::Info => Style::default(),
+            Level::Warning => Style::default().fg(Color::Yellow),
+            Level::Error => Style::default().fg(Color::Red),
+            Level::Crash => Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+        }
+    }
+
+    fn format_log(&self, space: Rect) -> Vec<(String, Style)> {
+        crate::log::pull_messages(space.height as usize, self.log_level)
             .into_iter()
-            .join("\n");
+            .flat_map(|message| {
+                let style = Self::level_style(message.level);
 
-        textwrap::wrap(&log, space.width as usize)
-            .iter()
+                textwrap::wrap(&message.text, space.width as usize)
+                    .into_iter()
+                    .map(|line| (line.to_string(), style))
+                    .collect_vec()
+            })
             .take(space.height as usize)
-            .map(|line| line.to_string())
             .collect_vec()
     }
 
-    fn write_right_panel(&mut self, frame: &mut Frame<B>, target: Rect) {
-        Self::write_list_in_frame(frame, target, self.format_log(target), "messages")
+    fn write_messages_panel(&mut self, frame: &mut Frame<B>, target: Rect) {
+        let title = match self.log_level {
+            Level::Info => "messages".to_string(),
+            level => format!("messages ({} and above)", level.name()),
+        };
+
+        Self::write_list_in_frame(frame, target, self.format_log(target), &title)
     }
 }
 
@@ -343,13 +1001,14 @@ impl<B: Backend + std::io::Write> Drop for TerminalUi<B> {
 pub fn serve_ui(
     library: AM<Library>,
     state: AM<State>,
-    config: &'static FuzzConfig,
+    config: Arc<FuzzConfig>,
+    shutdown: &ShutdownToken,
 ) -> Result<(), anyhow::Error> {
     let mut ui = TerminalUi::new(library, state, config)?;
 
     const FRAME_RATE: u32 = 30;
 
-    loop {
+    while !shutdown.is_cancelled() {
         ui.tick()?;
 
         if !event::poll(Duration::from_secs_f64(1.0 / (FRAME_RATE as f64)))? {
@@ -357,9 +1016,21 @@ pub fn serve_ui(
         }
 
         if let Event::Key(key) = event::read()? {
-            if let KeyCode::Char('q') = key.code {
-                return Ok(());
+            match key.code {
+                KeyCode::Char('q') => return Ok(()),
+                KeyCode::Char(c @ '1'..='9') => ui.select_tab(c as usize - '1' as usize),
+                KeyCode::Right | KeyCode::Tab => ui.next_tab(),
+                KeyCode::Left | KeyCode::BackTab => ui.prev_tab(),
+                KeyCode::Char('l') => ui.cycle_log_level(),
+                KeyCode::Char('r') => ui.retry(),
+                KeyCode::Char('x') => ui.toggle_crash_exploitation(),
+                KeyCode::Char('e') => ui.request_export(),
+                KeyCode::Char('?') => ui.toggle_help(),
+                KeyCode::Esc => ui.close_help(),
+                _ => {}
             }
         }
     }
+
+    Ok(())
 }